@@ -0,0 +1,335 @@
+/// Plotling Template, made with `new_plotling`. Change `Params` and `System` to draw something of your
+/// own, run it with `cargo run --release --bin plotling_template`
+mod params;
+mod system;
+
+use lib_plotings::camera::Camera;
+use lib_plotings::config::config;
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::Drawing;
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::pen::{PenLibrary, PenProfile, PIXELS_PER_MM};
+use lib_plotings::preset::{draw_error_banner, PresetFile};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::seed::Seeds;
+use log::{error, info, trace, warn};
+use nannou::{prelude::*, ui::prelude::*};
+use params::Params;
+use std::mem;
+use system::System;
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    nannou::app(model).update(update).run();
+}
+
+pub struct Model {
+    ui: Ui,
+    ids: Ids,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<Params>>,
+    pub params: Params,
+    pub system: System,
+    pub pens: PenLibrary,
+    pub pen: usize,
+    pub show_paper_preview: bool,
+}
+
+widget_ids! {
+    struct Ids {
+        export_dxf,
+        export_svg,
+        pen,
+        seed,
+        toggle_paper_preview,
+    }
+}
+
+fn model(app: &App) -> Model {
+    let (window_width, window_height) = config().window_size_or((1920, 1080));
+    let window_id = app
+        .new_window()
+        .size(window_width, window_height)
+        .view(view)
+        .event(event)
+        .build()
+        .expect("couldn't create a window");
+
+    // Create the UI.
+    let mut ui = app.new_ui().window(window_id).build().unwrap();
+
+    // Generate some ids for our widgets.
+    let ids = Ids::new(ui.widget_id_generator());
+    let mut preset = PresetFile::from_args();
+    let params = preset
+        .as_mut()
+        .and_then(|preset| preset.poll())
+        .unwrap_or_default();
+    let system = System::new(&params);
+    let pens = PenLibrary::load();
+    let pen = pens.index_of_or_first("0.3mm Marker");
+
+    Model {
+        ui,
+        ids,
+        input: Input::new(KeyBindings::load()),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset,
+        params,
+        system,
+        pens,
+        pen,
+        show_paper_preview: false,
+    }
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    handle_actions(app, model);
+    update_ui(model);
+
+    if let Some(params) = model.preset.as_mut().and_then(|preset| preset.poll()) {
+        model.params = params;
+        model.system = System::new(&model.params);
+    }
+}
+
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
+}
+
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => model.system = System::new(&model.params),
+            Action::NewSeed => {
+                model.params.seed = Seeds::random_master(0..=9999);
+                model.system = System::new(&model.params);
+            }
+            Action::ExportSvg => {
+                let pen = model.pens.get(model.pen);
+                if let Err(err) = export_as_svg(&model.params, &model.system, pen) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) = export_as_dxf(&model.params, &model.system) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => model.show_paper_preview = !model.show_paper_preview,
+            Action::FitToWindow => model.camera.fit(
+                Rect::from_w_h(model.params.width, model.params.height),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Undo | Action::Redo | Action::Export | Action::Custom(_) => (),
+        }
+    }
+}
+
+fn update_ui(model: &mut Model) {
+    // Calling `set_widgets` allows us to instantiate some widgets.
+    let mut ui_cell = model.ui.set_widgets();
+    let ui = &mut ui_cell;
+    let mut should_refresh_system = false;
+
+    for _click in widget::Button::new()
+        .top_left_with_margin(20.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Paper Preview")
+        .set(model.ids.toggle_paper_preview, ui)
+    {
+        model.show_paper_preview = !model.show_paper_preview;
+    }
+
+    if let Some(seed) = widget::NumberDialer::new(model.params.seed as f32, 0.0, 9999.0, 0)
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Seed")
+        .set(model.ids.seed, ui)
+    {
+        model.params.seed = seed as u64;
+        should_refresh_system = true;
+    }
+
+    let pen_names = model.pens.names();
+    for pen_index in widget::DropDownList::new(&pen_names, Some(model.pen))
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Pen")
+        .set(model.ids.pen, ui)
+    {
+        model.pen = pen_index;
+    }
+
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Export SVG")
+        .set(model.ids.export_svg, ui)
+    {
+        let pen = model.pens.get(model.pen);
+        if let Err(err) = export_as_svg(&model.params, &model.system, pen) {
+            error!("{:?}", err)
+        }
+    }
+
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Export DXF")
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.params, &model.system) {
+            error!("{:?}", err)
+        }
+    }
+
+    mem::drop(ui_cell);
+
+    if should_refresh_system {
+        model.system = System::new(&model.params);
+        trace!(
+            "should_refresh_system=true, creating {} shape(s)",
+            model.system.shapes().len()
+        );
+    }
+}
+
+// Draw the state of your `Model` into the given `Frame` here.
+fn view(app: &App, model: &Model, frame: Frame) {
+    // Begin drawing
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
+
+    let pen = model.pens.get(model.pen);
+
+    if model.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
+
+        let drawing = build_drawing_from_model(&model.params, &model.system);
+        model.paper.draw(&draw, &drawing, |_| pen);
+    } else {
+        draw.background().color(WHITE);
+
+        model.system.draw(&draw, &model.params, pen);
+    }
+
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
+    }
+
+    // Write the result of our drawing to the window's frame.
+    draw.to_frame(app, &frame).unwrap();
+
+    // Draw the state of the `Ui` to the frame.
+    model.ui.draw_to_frame(app, &frame).unwrap();
+}
+
+fn build_svg_document_from_model(
+    params: &Params,
+    system: &System,
+    pen: &PenProfile,
+) -> svg::Document {
+    let doc = svg::Document::new().set("viewBox", (0, 0, params.width, params.height));
+    let mut group = pen.style_svg_group(svg::node::element::Group::new());
+    for shape in system.shapes() {
+        group = shape.add_to_svg_group(group);
+    }
+    let bounding_rect = svg::node::element::Rectangle::new()
+        .set("width", params.width)
+        .set("height", params.height);
+    let border = pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
+
+    doc.add(group).add(border)
+}
+
+fn build_drawing_from_model(params: &Params, system: &System) -> Drawing {
+    let mut drawing = Drawing::new(params.width, params.height);
+    let border = drawing.border();
+
+    let shapes = drawing.layer_mut("shapes");
+    for shape in system.shapes() {
+        shapes.push(shape.clone());
+    }
+
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(params: &Params, system: &System) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let drawing = build_drawing_from_model(params, system);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(params.seed),
+        params,
+        &drawing,
+    )?;
+
+    Ok(())
+}
+
+fn export_as_svg(params: &Params, system: &System, pen: &PenProfile) -> Result<(), anyhow::Error> {
+    info!("exporting image as SVG...");
+    let document = build_svg_document_from_model(params, system, pen);
+    let svg_filepath = unused_export_path("svg")?;
+
+    svg::save(&svg_filepath, &document)?;
+    info!(
+        "SVG successfully exported to {}",
+        &svg_filepath.to_string_lossy()
+    );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(params.seed),
+        params,
+        &build_drawing_from_model(params, system),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use lib_plotings::preset::{check_range, Validate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Params {
+    pub width: f32,
+    pub height: f32,
+    pub seed: u64,
+    pub lines: usize,
+    pub points_per_line: usize,
+    /// How far each point can stray up or down from its line
+    pub jitter: f32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            width: 1000.0,
+            height: 700.0,
+            seed: 0,
+            lines: 40,
+            points_per_line: 50,
+            jitter: 4.0,
+        }
+    }
+}
+
+/// Presets are checked against these before they're used
+impl Validate for Params {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        check_range("width", self.width, 10.0..=10_000.0)?;
+        check_range("height", self.height, 10.0..=10_000.0)?;
+        check_range("lines", self.lines, 1..=1000)?;
+        check_range("points_per_line", self.points_per_line, 2..=1000)?;
+        check_range("jitter", self.jitter, 0.0..=100.0)
+    }
+}
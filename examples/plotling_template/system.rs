@@ -0,0 +1,59 @@
+use crate::params::Params;
+use lib_plotings::geometry::Shape;
+use lib_plotings::pen::PenProfile;
+use lib_plotings::seed::Seeds;
+use nannou::prelude::*;
+use rand::Rng;
+
+/// How closely curves are followed when they're drawn on screen, in pixels
+const DRAW_TOLERANCE: f32 = 0.5;
+
+/// Everything the plotling draws, in drawing coordinates with the origin in the top left
+#[derive(Debug, Default)]
+pub struct System {
+    shapes: Vec<Shape>,
+}
+
+impl System {
+    /// The same params, seed included, always give the same shapes
+    pub fn new(params: &Params) -> Self {
+        let mut rng = Seeds::new(params.seed).rng("jitter");
+        let jitter = params.jitter.abs();
+        let line_spacing = params.height / (params.lines + 1) as f32;
+        let point_spacing = params.width / params.points_per_line.saturating_sub(1).max(1) as f32;
+
+        let shapes = (1..=params.lines)
+            .map(|line| {
+                let y = line as f32 * line_spacing;
+                let points = (0..params.points_per_line)
+                    .map(|point| {
+                        pt2(
+                            point as f32 * point_spacing,
+                            y + rng.gen_range(-jitter..=jitter),
+                        )
+                    })
+                    .collect();
+
+                Shape::Polyline(points)
+            })
+            .collect();
+
+        Self { shapes }
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    /// Draw onto the window, which has its origin in the middle and y pointing up
+    pub fn draw(&self, draw: &Draw, params: &Params, pen: &PenProfile) {
+        let to_window = |xy: Point2| pt2(xy.x - params.width * 0.5, params.height * 0.5 - xy.y);
+
+        for shape in self.shapes.iter() {
+            draw.polyline()
+                .weight(pen.stroke_weight())
+                .color(pen.nannou_colour())
+                .points(shape.to_polyline(DRAW_TOLERANCE).into_iter().map(to_window));
+        }
+    }
+}
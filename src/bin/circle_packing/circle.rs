@@ -1,3 +1,4 @@
+use lib_plotings::pen::PenProfile;
 use log::trace;
 use nannou::prelude::*;
 
@@ -42,14 +43,12 @@ impl Circle {
         self.acceleration *= 0.0;
     }
 
-    pub fn draw(&self, draw: &Draw) {
+    pub fn draw(&self, draw: &Draw, pen: &PenProfile) {
         draw.ellipse()
             .xy(self.xy)
             .radius(self.radius)
-            // 0.3mm Marker
-            // .stroke_weight(1.0)
-            // Molotow Marker
-            .stroke_weight(12.0)
+            .stroke(pen.nannou_colour())
+            .stroke_weight(pen.stroke_weight())
             .no_fill();
     }
 }
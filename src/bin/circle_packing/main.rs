@@ -3,18 +3,31 @@ mod circle;
 mod packer;
 mod params;
 
-use std::{cell::RefCell, mem};
-
-use anyhow::Context;
-use chrono::Local;
-use log::{debug, error, info, trace, warn};
+use std::mem;
+
+use lib_plotings::camera::Camera;
+use lib_plotings::clock::SimulationClock;
+use lib_plotings::config::config;
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::{Drawing, Shape};
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::pen::{PenLibrary, PenProfile, PIXELS_PER_MM};
+use lib_plotings::preset::{draw_error_banner, PresetFile};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::seed::Seeds;
+use lib_plotings::svg::svg_window_transform;
+use lib_plotings::worker::{draw_progress_bar, Worker};
+use log::{error, info, trace, warn};
 use nannou::{prelude::*, ui::prelude::*};
 use packer::Packer;
-use params::{CircleParams, PackerParams};
-use rand::{prelude::StdRng, SeedableRng};
-use std::path::PathBuf;
+use params::{CircleParams, PackerParams, Preset};
 use svg::node::element::Ellipse;
 
+/// How often the packer steps at normal speed
+const STEPS_PER_SECOND: u32 = 60;
+
 fn main() {
     let res = dotenv::dotenv();
     env_logger::init();
@@ -28,24 +41,37 @@ fn main() {
 pub struct Model {
     ui: Ui,
     ids: Ids,
-    rng: RefCell<StdRng>,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<Preset>>,
+    clock: SimulationClock,
+    packing: Worker<Packer>,
     pub circle_params: CircleParams,
     pub packer_params: PackerParams,
-    pub show_viewbox: bool,
+    pub show_paper_preview: bool,
     pub packer: Packer,
+    pub pens: PenLibrary,
+    pub circle_pen: usize,
 }
 
 widget_ids! {
     struct Ids {
+        circle_pen,
         column_alignment,
         column_spacing,
+        export_dxf,
         export_svg,
         height,
         lines_per_column,
         noise_seed,
+        pause,
         number_of_columns,
         points_per_line,
-        toggle_viewbox,
+        seed,
+        single_step,
+        speed,
+        toggle_paper_preview,
         vertical_jitter,
         width,
         column_width,
@@ -53,10 +79,12 @@ widget_ids! {
 }
 
 fn model(app: &App) -> Model {
+    let (window_width, window_height) = config().window_size_or((1920, 1080));
     let window_id = app
         .new_window()
-        .size(1920, 1080)
+        .size(window_width, window_height)
         .view(view)
+        .event(event)
         .build()
         .expect("couldn't create a window");
 
@@ -65,37 +93,101 @@ fn model(app: &App) -> Model {
 
     // Generate some ids for our widgets.
     let ids = Ids::new(ui.widget_id_generator());
-    let rng: StdRng = SeedableRng::seed_from_u64(0);
-    let rng = RefCell::new(rng);
+    let pens = PenLibrary::load();
+    let circle_pen = pens.index_of_or_first("Molotow Marker");
 
     Model {
         ui,
         ids,
-        rng,
+        input: Input::new(KeyBindings::load()),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset: PresetFile::from_args(),
+        clock: SimulationClock::from_steps_per_second(STEPS_PER_SECOND),
+        packing: Worker::new(),
         packer: Default::default(),
         circle_params: Default::default(),
         packer_params: Default::default(),
-        show_viewbox: false,
+        show_paper_preview: false,
+        pens,
+        circle_pen,
     }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    handle_actions(app, model);
     update_ui(model);
 
-    if model.packer.is_empty() {
-        model.packer = Packer::new(&model);
+    if let Some(preset) = model.preset.as_mut().and_then(|preset| preset.poll()) {
+        model.circle_params = preset.circles;
+        model.packer_params = preset.packer;
+        start_packing(model);
     }
 
-    model
-        .packer
-        .update(model.packer_params.width, model.packer_params.height)
+    if model.packer.is_empty() && !model.packing.is_running() {
+        start_packing(model);
+    }
+
+    if let Some(packer) = model.packing.poll() {
+        model.packer = packer;
+        model.clock.reset();
+    }
+
+    for _ in 0..model.clock.advance(update.since_last) {
+        model
+            .packer
+            .update(model.packer_params.width, model.packer_params.height)
+    }
+}
+
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
+}
+
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => start_packing(model),
+            Action::NewSeed => {
+                model.packer_params.rng_seed = Seeds::random_master(0..=9999);
+                start_packing(model);
+            }
+            Action::ExportSvg => {
+                let circle_pen = model.pens.get(model.circle_pen);
+                if let Err(err) = export_as_svg(
+                    &model.circle_params,
+                    &model.packer_params,
+                    &model.packer,
+                    circle_pen,
+                ) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) =
+                    export_as_dxf(&model.circle_params, &model.packer_params, &model.packer)
+                {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => model.show_paper_preview = !model.show_paper_preview,
+            Action::FitToWindow => model.camera.fit(
+                Rect::from_w_h(model.packer_params.width, model.packer_params.height),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Export | Action::Undo | Action::Redo | Action::Custom(_) => (),
+        }
+    }
 }
 
 fn update_ui(model: &mut Model) {
     // Calling `set_widgets` allows us to instantiate some widgets.
     let mut ui_cell = model.ui.set_widgets();
     let ui = &mut ui_cell;
-    let should_refresh_packer = false;
+    let mut should_refresh_packer = false;
 
     // fn dialer(val: f32, min: f32, max: f32) -> widget::NumberDialer<'static, f32> {
     //     widget::NumberDialer::new(val, min, max, 0)
@@ -219,10 +311,83 @@ fn update_ui(model: &mut Model) {
         .rgb(0.3, 0.3, 0.3)
         .label_rgb(1.0, 1.0, 1.0)
         .border(0.0)
-        .label("Toggle Viewbox")
-        .set(model.ids.toggle_viewbox, ui)
+        .label("Paper Preview")
+        .set(model.ids.toggle_paper_preview, ui)
     {
-        model.show_viewbox = !model.show_viewbox;
+        model.show_paper_preview = !model.show_paper_preview;
+    }
+
+    if let Some(seed) =
+        widget::NumberDialer::new(model.packer_params.rng_seed as f32, 0.0, 9999.0, 0)
+            .down(10.0)
+            .w_h(300.0, 20.0)
+            .label_font_size(12)
+            .rgb(0.3, 0.3, 0.3)
+            .label_rgb(1.0, 1.0, 1.0)
+            .border(0.0)
+            .label("Seed")
+            .set(model.ids.seed, ui)
+    {
+        model.packer_params.rng_seed = seed as u64;
+        should_refresh_packer = true;
+    }
+
+    let pause_label = if model.clock.is_paused() {
+        "Resume"
+    } else {
+        "Pause"
+    };
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label(pause_label)
+        .set(model.ids.pause, ui)
+    {
+        model.clock.toggle_pause();
+    }
+
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Step")
+        .set(model.ids.single_step, ui)
+    {
+        model.clock.single_step();
+    }
+
+    for speed in widget::Slider::new(model.clock.speed() as f32, 0.1, 8.0)
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Speed")
+        .set(model.ids.speed, ui)
+    {
+        model.clock.set_speed(speed as f64);
+    }
+
+    let pen_names = model.pens.names();
+    for pen_index in widget::DropDownList::new(&pen_names, Some(model.circle_pen))
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Circle Pen")
+        .set(model.ids.circle_pen, ui)
+    {
+        model.circle_pen = pen_index;
     }
 
     for _click in widget::Button::new()
@@ -238,39 +403,70 @@ fn update_ui(model: &mut Model) {
         let circle_params = &model.circle_params;
         let packer_params = &model.packer_params;
         let packer = &model.packer;
-        if let Err(err) = export_as_svg(circle_params, packer_params, packer) {
+        let circle_pen = model.pens.get(model.circle_pen);
+        if let Err(err) = export_as_svg(circle_params, packer_params, packer, circle_pen) {
             error!("{}", err)
         }
     }
 
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Export DXF")
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.circle_params, &model.packer_params, &model.packer) {
+            error!("{:?}", err)
+        }
+    }
+
     mem::drop(ui_cell);
 
     if should_refresh_packer {
-        model.packer = Packer::new(&model);
-        trace!(
-            "should_refresh_packer=true, creating packer with {} lines",
-            model.packer.len()
-        );
+        trace!("should_refresh_packer=true, creating a new packer");
+        start_packing(model);
     }
 }
 
+/// Scatter new circles on the worker, the packer on screen keeps going until they're ready
+fn start_packing(model: &mut Model) {
+    let circle_params = model.circle_params.clone();
+    let packer_params = model.packer_params.clone();
+
+    model
+        .packing
+        .start(move |progress| Packer::new(&circle_params, &packer_params, progress));
+}
+
 // Draw the state of your `Model` into the given `Frame` here.
 fn view(app: &App, model: &Model, frame: Frame) {
     // Begin drawing
-    let draw = app.draw();
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
+
+    if model.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
 
-    draw.background().color(WHITE);
+        let drawing = build_drawing_from_model(&model.packer_params, &model.packer);
+        model
+            .paper
+            .draw(&draw, &drawing, |_| model.pens.get(model.circle_pen));
+    } else {
+        draw.background().color(WHITE);
 
-    model.packer.draw(&draw);
+        model.packer.draw(&draw, model.pens.get(model.circle_pen));
+    }
+
+    if let Some(fraction_done) = model.packing.progress() {
+        draw_progress_bar(&window_draw, app.window_rect(), fraction_done);
+    }
 
-    if model.show_viewbox {
-        draw.rect()
-            // we want the box centered on the screen, nannou places rectangle from their center
-            .x_y(0.0, 0.0)
-            .w_h(model.packer_params.width, model.packer_params.height)
-            .stroke(RED)
-            .stroke_weight(2.0)
-            .no_fill();
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
     }
 
     // Write the result of our drawing to the window's frame.
@@ -284,14 +480,18 @@ fn build_svg_document_from_model(
     _circle_params: &CircleParams,
     packer_params: &PackerParams,
     packer: &Packer,
+    circle_pen: &PenProfile,
 ) -> svg::Document {
     let doc =
         svg::Document::new().set("viewBox", (0, 0, packer_params.width, packer_params.height));
 
-    let mut group = svg::node::element::Group::new()
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", "0.3mm");
+    // the circles are packed around the middle of the window
+    let mut group = circle_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .set(
+            "transform",
+            svg_window_transform(packer_params.width, packer_params.height),
+        );
 
     for circle in packer.circles.iter() {
         let circle = circle.borrow();
@@ -306,52 +506,88 @@ fn build_svg_document_from_model(
 
     let bounding_rect = svg::node::element::Rectangle::new()
         .set("width", packer_params.width)
-        .set("height", packer_params.height)
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", "2mm");
+        .set("height", packer_params.height);
+    let border = circle_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
+
+    doc.add(group).add(border)
+}
+
+fn build_drawing_from_model(packer_params: &PackerParams, packer: &Packer) -> Drawing {
+    let mut drawing = Drawing::new(packer_params.width, packer_params.height);
+    let border = drawing.border();
+
+    let circles = drawing.layer_mut("circles");
+    for circle in packer.circles.iter() {
+        let circle = circle.borrow();
+        circles.push(Shape::Circle {
+            center: circle.xy,
+            radius: circle.radius,
+        });
+    }
+    drawing.move_from_window();
+
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(
+    circle_params: &CircleParams,
+    packer_params: &PackerParams,
+    packer: &Packer,
+) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let preset = Preset {
+        circles: circle_params.clone(),
+        packer: packer_params.clone(),
+    };
+    let drawing = build_drawing_from_model(packer_params, packer);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(packer_params.rng_seed),
+        &preset,
+        &drawing,
+    )?;
 
-    doc.add(group).add(bounding_rect)
+    Ok(())
 }
 
 fn export_as_svg(
     circle_params: &CircleParams,
     packer_params: &PackerParams,
     packer: &Packer,
+    circle_pen: &PenProfile,
 ) -> Result<(), anyhow::Error> {
     info!("exporting image as SVG...");
-    let document = build_svg_document_from_model(circle_params, packer_params, packer);
-    let base_path = std::env::var("SVG_EXPORT_DIRECTORY").context("setting 'SVG_EXPORT_DIRECTORY' is required, please set it to the directory you wish to export SVGs to")?;
-    let current_date = Local::today().format("%Y-%m-%d");
-    let svg_filename = format!("{}-plotling.svg", &current_date);
-    let mut svg_filepath: PathBuf = [base_path, svg_filename].iter().collect();
-
-    // I don't want to silently overwrite anything so I look for an unused filename,
-    // incrementing the counter until I find an unused number
-    // I could have also used a random string/number, I just like this better
-    if svg_filepath.exists() {
-        let mut counter = 1;
-
-        while svg_filepath.exists() {
-            if counter > 100 {
-                debug!(
-                    "export_as_svg counter has reached {}, you're not in an infinite loop are you?",
-                    counter
-                );
-            }
-
-            let _ = svg_filepath.pop();
-            let svg_filename = format!("{}-plotling-{}.svg", &current_date, &counter);
-            svg_filepath.push(svg_filename);
-            counter += 1;
-        }
-    }
+    let preset = Preset {
+        circles: circle_params.clone(),
+        packer: packer_params.clone(),
+    };
+    let document = build_svg_document_from_model(circle_params, packer_params, packer, circle_pen);
+    let svg_filepath = unused_export_path("svg")?;
 
     svg::save(&svg_filepath, &document)?;
     info!(
         "SVG successfully exported to {}",
         &svg_filepath.to_string_lossy()
     );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(packer_params.rng_seed),
+        &preset,
+        &build_drawing_from_model(packer_params, packer),
+    )?;
 
     Ok(())
 }
@@ -3,7 +3,12 @@ use std::{
     ops::{AddAssign, SubAssign},
 };
 
-use crate::{circle::Circle, Model};
+use crate::circle::Circle;
+use crate::params::{CircleParams, PackerParams};
+use lib_plotings::pen::PenProfile;
+use lib_plotings::seed::Seeds;
+use lib_plotings::spatial::{GridHash, SpatialIndex};
+use lib_plotings::worker::Progress;
 use log::trace;
 use nannou::prelude::*;
 use rand::Rng;
@@ -16,31 +21,38 @@ pub struct Packer {
 }
 
 impl Packer {
-    pub fn new(model: &Model) -> Self {
-        let circles = (0..=model.packer_params.circle_count)
-            .map(|_| {
-                let mut rng = model.rng.borrow_mut();
-                let x = rng.gen_range(
-                    -model.circle_params.start_position_variance
-                        ..model.circle_params.start_position_variance,
-                );
-                let y = rng.gen_range(
-                    -model.circle_params.start_position_variance
-                        ..model.circle_params.start_position_variance,
-                );
-                let xy = Vec2::new(x, y);
-                let radius =
-                    rng.gen_range(model.circle_params.min_radius..model.circle_params.max_radius);
-
-                RefCell::new(Circle::new(xy, radius))
-            })
-            .collect();
+    /// Scatter the circles to start packing, `None` if it's cancelled before they're all placed
+    pub fn new(
+        circle_params: &CircleParams,
+        packer_params: &PackerParams,
+        progress: &Progress,
+    ) -> Option<Self> {
+        let seeds = Seeds::new(packer_params.rng_seed);
+        let mut positions = seeds.rng("positions");
+        let mut radii = seeds.rng("radii");
+        let variance = circle_params.start_position_variance;
+        let count = packer_params.circle_count + 1;
+        let mut circles = Vec::with_capacity(count);
+
+        for index in 0..count {
+            if progress.is_cancelled() {
+                return None;
+            }
+
+            let x = positions.gen_range(-variance..variance);
+            let y = positions.gen_range(-variance..variance);
+            let xy = Vec2::new(x, y);
+            let radius = radii.gen_range(circle_params.min_radius..circle_params.max_radius);
+
+            circles.push(RefCell::new(Circle::new(xy, radius)));
+            progress.set(index + 1, count);
+        }
 
-        Self {
+        Some(Self {
             circles,
             max_force: 10.0,
             max_speed: 10.0,
-        }
+        })
     }
 
     pub fn add_circle(&mut self, circle: Circle) {
@@ -55,10 +67,10 @@ impl Packer {
         self.circles.len()
     }
 
-    pub fn draw(&self, draw: &Draw) {
+    pub fn draw(&self, draw: &Draw, pen: &PenProfile) {
         self.circles
             .iter()
-            .for_each(|circle| circle.borrow().draw(draw))
+            .for_each(|circle| circle.borrow().draw(draw, pen))
     }
 
     pub fn update(&mut self, width: f32, height: f32) {
@@ -74,13 +86,32 @@ impl Packer {
             .collect();
 
         let boundary = Rect::from_w_h(width, height);
+        let positions: Vec<Vec2> = self.circles.iter().map(|c| c.borrow().xy).collect();
+        let max_radius = self
+            .circles
+            .iter()
+            .map(|c| c.borrow().radius)
+            .fold(0.0, f32::max);
+        // Circles move during the update but the index isn't rebuilt, so searches reach further
+        // to cover how far two circles can have moved, no more than `max_speed` each
+        let neighbourhood = GridHash::new(&positions, (max_radius * 2.0).max(1.0));
 
         for circle_index in 0..self.circles.len() {
+            let search_radius =
+                self.circles[circle_index].borrow().radius + max_radius + self.max_speed * 2.0;
+            let mut neighbours: Vec<usize> = neighbourhood
+                .within_radius(positions[circle_index], search_radius)
+                .into_iter()
+                .filter(|j| *j > circle_index)
+                .collect();
+            neighbours.sort_unstable();
+
             check_borders(circle_index, &self.circles, &boundary);
-            check_circle_position(circle_index, &self.circles);
+            check_circle_position(circle_index, &self.circles, &neighbours);
             apply_separation_forces_to_circle(
                 circle_index,
                 &self.circles,
+                &neighbours,
                 &mut separate_forces,
                 &mut near_circles,
                 self.max_force,
@@ -116,11 +147,12 @@ pub fn check_borders(index: usize, circles: &[RefCell<Circle>], boundary: &Rect)
     }
 }
 
-pub fn check_circle_position(index: usize, circles: &[RefCell<Circle>]) {
+/// `neighbours` are the circles after this one that are close enough to overlap it
+pub fn check_circle_position(index: usize, circles: &[RefCell<Circle>], neighbours: &[usize]) {
     let mut circle_i = circles.get(index).unwrap().borrow_mut();
     let mut circle_has_a_neighbor = false;
 
-    for j in (index + 1)..circles.len() {
+    for &j in neighbours {
         let circle_j = circles.get(j).unwrap().borrow_mut();
 
         if circle_i.overlaps(&circle_j) {
@@ -137,9 +169,11 @@ pub fn check_circle_position(index: usize, circles: &[RefCell<Circle>]) {
     }
 }
 
+/// `neighbours` are the circles after this one that are close enough to push it away
 pub fn apply_separation_forces_to_circle(
     index: usize,
     circles: &[RefCell<Circle>],
+    neighbours: &[usize],
     separate_forces: &mut [RefCell<Vec2>],
     near_circles: &mut [usize],
     max_force: f32,
@@ -148,8 +182,7 @@ pub fn apply_separation_forces_to_circle(
     let mut separate_forces_i = separate_forces.get(index).unwrap().borrow_mut();
     let mut circle = circles.get(index).unwrap().borrow_mut();
 
-    for j in (index + 1)..circles.len() {
-        let j = if j == circles.len() { 0 } else { j };
+    for &j in neighbours {
         assert_ne!(index, j);
 
         let mut separate_forces_j = separate_forces.get(j).unwrap().borrow_mut();
@@ -1,3 +1,8 @@
+use lib_plotings::preset::{check_not_empty, check_range, Validate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CircleParams {
     pub min_radius: f32,
     pub max_radius: f32,
@@ -14,10 +19,13 @@ impl Default for CircleParams {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PackerParams {
     pub circle_count: usize,
     pub width: f32,
     pub height: f32,
+    pub rng_seed: u64,
 }
 
 impl Default for PackerParams {
@@ -26,6 +34,34 @@ impl Default for PackerParams {
             circle_count: 1000,
             width: 1000.0,
             height: 1000.0,
+            rng_seed: 0,
         }
     }
 }
+
+/// Everything a preset can set, in `[circles]` and `[packer]` tables
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    pub circles: CircleParams,
+    pub packer: PackerParams,
+}
+
+impl Validate for Preset {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        check_not_empty(
+            "circles.min_radius..circles.max_radius",
+            &(self.circles.min_radius..self.circles.max_radius),
+        )?;
+        check_range("circles.min_radius", self.circles.min_radius, 0.1..=1000.0)?;
+        // circles start somewhere in a square this far either side of the middle
+        check_range(
+            "circles.start_position_variance",
+            self.circles.start_position_variance,
+            0.001..=10_000.0,
+        )?;
+        check_range("packer.circle_count", self.packer.circle_count, 1..=100_000)?;
+        check_range("packer.width", self.packer.width, 10.0..=10_000.0)?;
+        check_range("packer.height", self.packer.height, 10.0..=10_000.0)
+    }
+}
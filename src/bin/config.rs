@@ -0,0 +1,15 @@
+/// Prints the settings the generators would run with and where each one came from, e.g.
+/// `cargo run --release --bin config -- --paper a3`
+/// Flags given to it are read the same way the generators read them.
+use lib_plotings::config::config;
+use log::warn;
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    print!("{}", config());
+}
@@ -1,11 +1,22 @@
 mod triangle;
 
 use crate::triangle::new_triangles_from_noise;
-use anyhow::Context;
-use chrono::Local;
-use log::{debug, error, info, trace, warn};
+use lib_plotings::camera::Camera;
+use lib_plotings::config::config;
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::Drawing;
+use lib_plotings::history::{History, Replace};
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::noise::NoiseKind;
+use lib_plotings::pen::{PenLibrary, PIXELS_PER_MM};
+use lib_plotings::preset::{draw_error_banner, set_loop_mode_for, PresetFile};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::seed::Seeds;
+use lib_plotings::svg::svg_window_transform;
+use log::{error, info, trace, warn};
 use nannou::{prelude::*, ui::prelude::*};
-use std::path::PathBuf;
 use triangle::{bounding_rect_from_triangles, TriangleParams, Triangles};
 
 fn main() {
@@ -22,63 +33,98 @@ fn main() {
 struct State {
     pub triangles: Triangles,
     pub triangle_params: TriangleParams,
+    /// Changes to `triangle_params`, for undo and redo
+    pub history: History<Replace<TriangleParams>>,
     pub height: f32,
     pub width: f32,
-    pub show_viewbox: bool,
+    pub show_paper_preview: bool,
     pub should_rebuild: bool,
-    pub stroke_weight: f32,
+    pub pens: PenLibrary,
+    pub triangle_pen: usize,
 }
 
 impl State {
     fn new(width: f32, height: f32) -> Self {
+        let pens = PenLibrary::load();
+
         Self {
             triangles: Default::default(),
             triangle_params: Default::default(),
+            history: History::new(),
             height,
             width,
-            show_viewbox: false,
+            show_paper_preview: false,
             should_rebuild: true,
-            stroke_weight: 3.0,
+            triangle_pen: pens.index_of_or_first("1mm Marker"),
+            pens,
         }
     }
 
     fn update(&mut self) {
         // do nothing
     }
+
+    /// Remember that the params have changed from `before` and rebuild the dune. Changes that
+    /// `can_merge`, the steps of a slider drag, are undone together.
+    fn params_changed(&mut self, before: TriangleParams, can_merge: bool) {
+        if before != self.triangle_params {
+            let edit = Replace {
+                before,
+                after: self.triangle_params.clone(),
+            };
+
+            if can_merge {
+                self.history.record(edit);
+            } else {
+                self.history.record_alone(edit);
+            }
+            self.should_rebuild = true;
+        }
+    }
 }
 
 struct Model {
     ui: Ui,
     ids: Ids,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<TriangleParams>>,
     pub state: State,
 }
 
 widget_ids! {
     struct Ids {
+        export_dxf,
         export_svg,
         max_height,
         min_height,
+        noise_kind,
+        noise_octaves,
         noise_scale,
         noise_seed,
         param_title_text,
         skew,
-        stroke_weight,
-        toggle_viewbox,
+        toggle_paper_preview,
         triangle_count,
+        triangle_pen,
         vertical_spacing,
         wh_ratio,
     }
 }
 
 fn model(app: &App) -> Model {
-    // Set the loop mode to wait for events, an energy-efficient option for pure-GUI apps.
-    app.set_loop_mode(LoopMode::Wait);
+    let preset = PresetFile::from_args();
+    set_loop_mode_for(app, &preset);
     let (width, height) = (850, 1100);
 
+    let (window_width, window_height) = config().window_size_or((width, height));
+
     let window_id = app
         .new_window()
-        .size(width, height)
+        .size(window_width, window_height)
         .view(view)
+        .event(event)
         .build()
         .expect("couldn't create a window");
 
@@ -89,11 +135,27 @@ fn model(app: &App) -> Model {
     let ids = Ids::new(ui.widget_id_generator());
     let state = State::new(width as f32, height as f32);
 
-    Model { ui, ids, state }
+    Model {
+        ui,
+        ids,
+        input: Input::new(KeyBindings::load()),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset,
+        state,
+    }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    if model.input.button(MouseButton::Left).is_just_released() {
+        // each drag of a slider is undone on its own
+        model.state.history.seal();
+    }
+    handle_actions(app, model);
     update_ui(model);
+    reload_preset(model);
     model.state.update();
 
     if model.state.should_rebuild {
@@ -103,6 +165,60 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
     }
 }
 
+/// Take on the preset's params whenever it's saved, a reload can be undone like any other change
+fn reload_preset(model: &mut Model) {
+    if let Some(params) = model.preset.as_mut().and_then(|preset| preset.poll()) {
+        let before = std::mem::replace(&mut model.state.triangle_params, params);
+        model.state.params_changed(before, false);
+    }
+}
+
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
+}
+
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => model.state.should_rebuild = true,
+            Action::NewSeed => {
+                let before = model.state.triangle_params.clone();
+                model.state.triangle_params.noise.seed = Seeds::random_master(0..=100) as u32;
+                model.state.params_changed(before, false);
+            }
+            Action::ExportSvg => {
+                if let Err(err) = export_as_svg(&model.state) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) = export_as_dxf(&model.state) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => {
+                model.state.show_paper_preview = !model.state.show_paper_preview
+            }
+            Action::FitToWindow => model.camera.fit(
+                dune_rect(&model.state, app.window_rect()),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Undo => {
+                if model.state.history.undo(&mut model.state.triangle_params) {
+                    model.state.should_rebuild = true;
+                }
+            }
+            Action::Redo => {
+                if model.state.history.redo(&mut model.state.triangle_params) {
+                    model.state.should_rebuild = true;
+                }
+            }
+            Action::Export | Action::Custom(_) => (),
+        }
+    }
+}
+
 const SETTING_WIDTH: f64 = 300.0;
 const SETTING_HEIGHT: f64 = 30.0;
 const SETTING_MARGIN: f64 = 10.0;
@@ -130,9 +246,20 @@ fn slider(val: f32, min: f32, max: f32) -> widget::Slider<'static, f32> {
         .border(0.0)
 }
 
+fn drop_down_list(names: &[String], selected: usize) -> widget::DropDownList<'_, String> {
+    use nannou::ui::color::*;
+    widget::DropDownList::new(names, Some(selected))
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+}
+
 fn update_ui(model: &mut Model) {
     let ui = &mut model.ui.set_widgets();
     let mut should_refresh_model = false;
+    let params_before = model.state.triangle_params.clone();
 
     widget::Text::new("Parameters")
         .top_left_with_margin(20.0)
@@ -176,30 +303,50 @@ fn update_ui(model: &mut Model) {
         should_refresh_model = true;
     }
 
-    for noise_seed in slider(model.state.triangle_params.noise_seed as f32, 0.0, 5.0)
+    let noise_kinds = NoiseKind::names();
+    for kind_index in drop_down_list(&noise_kinds, model.state.triangle_params.noise.kind.index())
         .down(SETTING_MARGIN)
-        .label("Noise")
+        .label("Noise Kind")
+        .set(model.ids.noise_kind, ui)
+    {
+        model.state.triangle_params.noise.kind = NoiseKind::ALL[kind_index];
+        should_refresh_model = true;
+    }
+
+    for noise_seed in slider(model.state.triangle_params.noise.seed as f32, 0.0, 100.0)
+        .down(SETTING_MARGIN)
+        .label("Noise Seed")
         .set(model.ids.noise_seed, ui)
     {
-        model.state.triangle_params.noise_seed = noise_seed as f64;
+        model.state.triangle_params.noise.seed = noise_seed as u32;
         should_refresh_model = true;
     }
 
-    for noise_scale in slider(model.state.triangle_params.noise_scale as f32, -0.06, 0.06)
+    for noise_scale in slider(model.state.triangle_params.noise.scale as f32, -0.06, 0.06)
         .down(SETTING_MARGIN)
         .label("Noise Scale")
         .set(model.ids.noise_scale, ui)
     {
-        model.state.triangle_params.noise_scale = noise_scale as f64;
+        model.state.triangle_params.noise.scale = noise_scale as f64;
+        should_refresh_model = true;
+    }
+
+    for octaves in dialer(model.state.triangle_params.noise.octaves as f32, 1.0, 8.0)
+        .down(SETTING_MARGIN)
+        .label("Noise Octaves")
+        .set(model.ids.noise_octaves, ui)
+    {
+        model.state.triangle_params.noise.octaves = octaves as u8;
         should_refresh_model = true;
     }
 
-    for stroke_weight in slider(model.state.stroke_weight, 1.0, 12.0)
+    let pen_names = model.state.pens.names();
+    for pen_index in drop_down_list(&pen_names, model.state.triangle_pen)
         .down(SETTING_MARGIN)
-        .label("Stroke Weight (px)")
-        .set(model.ids.stroke_weight, ui)
+        .label("Triangle Pen")
+        .set(model.ids.triangle_pen, ui)
     {
-        model.state.stroke_weight = stroke_weight;
+        model.state.triangle_pen = pen_index;
         should_refresh_model = true;
     }
 
@@ -210,10 +357,10 @@ fn update_ui(model: &mut Model) {
         .color(FILL)
         .label_color(LABEL_COLOR)
         .border(0.0)
-        .label("Toggle Viewbox")
-        .set(model.ids.toggle_viewbox, ui)
+        .label("Paper Preview")
+        .set(model.ids.toggle_paper_preview, ui)
     {
-        model.state.show_viewbox = !model.state.show_viewbox;
+        model.state.show_paper_preview = !model.state.show_paper_preview;
         should_refresh_model = true;
     }
 
@@ -232,6 +379,23 @@ fn update_ui(model: &mut Model) {
         }
     }
 
+    for _click in widget::Button::new()
+        .down(SETTING_MARGIN)
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+        .label("Export DXF")
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.state) {
+            error!("{:?}", err)
+        }
+    }
+
+    model.state.params_changed(params_before, true);
+
     if should_refresh_model {
         model.state.should_rebuild = true;
         trace!("refresh model called");
@@ -240,31 +404,32 @@ fn update_ui(model: &mut Model) {
 
 // Draw the state of your `Model` into the given `Frame` here.
 fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
-
-    draw.background().color(WHITE);
-
-    let dune_bounding_rect = bounding_rect_from_triangles(&model.state.triangles)
-        .align_middle_x_of(app.window_rect())
-        .align_middle_y_of(app.window_rect())
-        .shift_y(model.state.height / 4.0);
-
-    if model.state.show_viewbox {
-        draw.rect()
-            .xy(dune_bounding_rect.xy())
-            .wh(dune_bounding_rect.wh())
-            .stroke(RED)
-            .stroke_weight(2.0)
-            .no_fill();
-    }
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
+
+    let pen = model.state.pens.get(model.state.triangle_pen);
+
+    if model.state.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
+
+        let drawing = build_drawing_from_state(&model.state);
+        model.paper.draw(&draw, &drawing, |_| pen);
+    } else {
+        draw.background().color(WHITE);
+
+        let dune_bounding_rect = dune_rect(&model.state, app.window_rect());
+        let triangle_draw = draw.translate(dune_bounding_rect.xy().extend(0.0));
 
-    let triangle_draw = draw.translate(dune_bounding_rect.xy().extend(0.0));
+        model
+            .state
+            .triangles
+            .iter()
+            .for_each(|triangle| triangle.draw(&triangle_draw, pen));
+    }
 
-    model
-        .state
-        .triangles
-        .iter()
-        .for_each(|triangle| triangle.draw(&triangle_draw, model.state.stroke_weight));
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
+    }
 
     // Write the result of our drawing to the window's frame.
     draw.to_frame(app, &frame).unwrap();
@@ -273,13 +438,30 @@ fn view(app: &App, model: &Model, frame: Frame) {
     model.ui.draw_to_frame(app, &frame).unwrap();
 }
 
+/// Where the dune is drawn in the window, before the camera moves it
+fn dune_rect(state: &State, window_rect: Rect) -> Rect {
+    bounding_rect_from_triangles(&state.triangles)
+        .align_middle_x_of(window_rect)
+        .align_middle_y_of(window_rect)
+        .shift_y(state.height / 4.0)
+}
+
 fn build_svg_document_from_state(state: &State) -> svg::Document {
     let doc = svg::Document::new().set("viewBox", (0, 0, state.width, state.height));
 
-    let mut group = svg::node::element::Group::new()
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", 1);
+    let triangle_pen = state.pens.get(state.triangle_pen);
+    let dune_center = bounding_rect_from_triangles(&state.triangles).xy();
+    let mut group = triangle_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .set(
+            "transform",
+            format!(
+                "{} translate({} {})",
+                svg_window_transform(state.width, state.height),
+                -dune_center.x,
+                -dune_center.y
+            ),
+        );
 
     for triangle in state.triangles.iter() {
         let path = triangle.as_svg();
@@ -289,48 +471,89 @@ fn build_svg_document_from_state(state: &State) -> svg::Document {
 
     let bounding_rect = svg::node::element::Rectangle::new()
         .set("width", state.width)
-        .set("height", state.height)
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", 1);
+        .set("height", state.height);
+    let border = triangle_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
 
-    doc.add(group).add(bounding_rect)
+    doc.add(group).add(border)
+}
+
+fn build_drawing_from_state(state: &State) -> Drawing {
+    let mut drawing = Drawing::new(state.width, state.height);
+    let border = drawing.border();
+
+    // the dune is built around its own origin with y pointing up, centring it in the window
+    // first puts it in the middle of the page
+    let dune_center = bounding_rect_from_triangles(&state.triangles).xy();
+    let triangles = drawing.layer_mut("triangles");
+    for triangle in state.triangles.iter() {
+        triangles.push(triangle.shape().transformed(1.0, -dune_center));
+    }
+    drawing.move_from_window();
+
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(state: &State) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let drawing = build_drawing_from_state(state);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(state.triangle_params.noise.seed as u64),
+        &state.triangle_params,
+        &drawing,
+    )?;
+
+    Ok(())
 }
 
 fn export_as_svg(state: &State) -> Result<(), anyhow::Error> {
     info!("exporting image as SVG...");
     let document = build_svg_document_from_state(state);
-    let base_path = std::env::var("SVG_EXPORT_DIRECTORY").context("setting 'SVG_EXPORT_DIRECTORY' is required, please set it to the directory you wish to export SVGs to")?;
-    let current_date = Local::today().format("%Y-%m-%d");
-    let svg_filename = format!("{}-plotling.svg", &current_date);
-    let mut svg_filepath: PathBuf = [base_path, svg_filename].iter().collect();
-
-    // I don't want to silently overwrite anything so I look for an unused filename,
-    // incrementing the counter until I find an unused number
-    // I could have also used a random string/number, I just like this better
-    if svg_filepath.exists() {
-        let mut counter = 1;
-
-        while svg_filepath.exists() {
-            if counter > 100 {
-                debug!(
-                    "export_as_svg counter has reached {}, you're not in an infinite loop are you?",
-                    counter
-                );
-            }
-
-            let _ = svg_filepath.pop();
-            let svg_filename = format!("{}-plotling-{}.svg", &current_date, &counter);
-            svg_filepath.push(svg_filename);
-            counter += 1;
-        }
-    }
+    let svg_filepath = unused_export_path("svg")?;
 
     svg::save(&svg_filepath, &document)?;
     info!(
         "SVG successfully exported to {}",
         &svg_filepath.to_string_lossy()
     );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(state.triangle_params.noise.seed as u64),
+        &state.triangle_params,
+        &build_drawing_from_state(state),
+    )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_the_default_dune_is_on_the_page() {
+        let mut state = State::new(1920.0, 1080.0);
+        state.triangles = new_triangles_from_noise(&state.triangle_params);
+        let drawing = build_drawing_from_state(&state);
+        let placement = PaperPreview::default().placement(&drawing);
+        let pen = state.pens.get(state.triangle_pen);
+
+        assert!(drawing.layers[0]
+            .shapes
+            .iter()
+            .all(|shape| placement.is_printable(shape, pen)));
+    }
+}
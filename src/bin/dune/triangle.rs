@@ -1,5 +1,9 @@
+use lib_plotings::geometry::Shape;
+use lib_plotings::noise::{NoiseField, NoiseParams};
+use lib_plotings::pen::PenProfile;
+use lib_plotings::preset::{check_finite, check_range, Validate};
 use nannou::prelude::*;
-use noise::{NoiseFn, SuperSimplex};
+use serde::{Deserialize, Serialize};
 use svg::node::element::Polyline;
 
 pub struct Triangle {
@@ -29,11 +33,12 @@ impl Triangle {
         Self { points }
     }
 
-    pub fn draw(&self, draw: &Draw, stroke_weight: f32) {
+    pub fn draw(&self, draw: &Draw, pen: &PenProfile) {
         let points = self.points.iter().cloned();
 
         draw.polyline()
-            .weight(stroke_weight)
+            .weight(pen.stroke_weight())
+            .color(pen.nannou_colour())
             .caps_round()
             .points_closed(points);
     }
@@ -77,6 +82,10 @@ impl Triangle {
 
         Polyline::new().set("points", data.join(" "))
     }
+
+    pub fn shape(&self) -> Shape {
+        Shape::Polygon(self.points.to_vec())
+    }
 }
 
 fn generate_triangle_points_from_attributes(
@@ -128,7 +137,7 @@ pub fn bounding_rect_from_triangles(triangles: &[Triangle]) -> nannou::geom::Rec
 }
 
 pub fn new_triangles_from_noise(params: &TriangleParams) -> Triangles {
-    let noise_fn = SuperSimplex::new();
+    let noise_field = NoiseField::new(params.noise);
     let halfway_point = (params.count / 2) as f32;
     (0..=params.count)
         .map(|index| {
@@ -139,8 +148,7 @@ pub fn new_triangles_from_noise(params: &TriangleParams) -> Triangles {
             } else {
                 ((count - index) / count) * 2.0
             };
-            let x = (noise_fn.get([params.noise_seed, index as f64 * params.noise_scale]) * 200.0)
-                as f32;
+            let x = noise_field.get(vec2(index, 0.0)) * 200.0;
             let xy = pt2(x, index * -params.vertical_spacing);
             let height = map_range(t, 0.0, 1.0, params.min_height, params.max_height);
             let width = height * params.wh_ratio;
@@ -190,7 +198,8 @@ pub fn new_triangles_from_noise(params: &TriangleParams) -> Triangles {
 //     (x1, x2)
 // }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TriangleParams {
     pub count: u32,
     pub wh_ratio: f32,
@@ -198,8 +207,7 @@ pub struct TriangleParams {
     pub vertical_spacing: f32,
     pub min_height: f32,
     pub max_height: f32,
-    pub noise_seed: f64,
-    pub noise_scale: f64,
+    pub noise: NoiseParams,
 }
 
 impl Default for TriangleParams {
@@ -211,10 +219,24 @@ impl Default for TriangleParams {
             max_height: 200.0,
             min_height: 10.0,
             wh_ratio: 1.61803398875,
-            noise_seed: 0.0,
-            noise_scale: 0.02,
+            noise: NoiseParams {
+                scale: 0.02,
+                ..Default::default()
+            },
         }
     }
 }
 
+impl Validate for TriangleParams {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        self.noise.check(-0.06..=0.06)?;
+        check_range("count", self.count, 1..=512)?;
+        check_range("skew", self.skew, 0.0..=1.0)?;
+        check_range("vertical_spacing", self.vertical_spacing, 1.0..=8.0)?;
+        check_range("wh_ratio", self.wh_ratio, 0.5..=3.14)?;
+        check_range("min_height", self.min_height, 0.0..=self.max_height)?;
+        check_finite("max_height", self.max_height)
+    }
+}
+
 // <polygon points="0,100 50,25 50,75 100,0" />
@@ -0,0 +1,582 @@
+/// Long, evenly spaced lines following a flow field made of noise. The lines are streamlines,
+/// traced through the field with a fixed gap kept between neighbours, see [`streamlines`].
+mod streamlines;
+
+use anyhow::Context;
+use lib_plotings::camera::Camera;
+use lib_plotings::config::config;
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::{Drawing, Shape};
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::noise::{NoiseField, NoiseKind, NoiseParams};
+use lib_plotings::pen::{PenLibrary, PIXELS_PER_MM};
+use lib_plotings::preset::{
+    check_range, draw_error_banner, set_loop_mode_for, PresetFile, Validate,
+};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::seed::Seeds;
+use log::{error, info, trace, warn};
+use nannou::{geom::Rect, prelude::*, ui::prelude::*};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use streamlines::{trace_streamlines, StreamlineParams};
+use svg::node::element::Group;
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    nannou::app(model).update(update).run();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FieldKind {
+    /// Lines swirl around the hills and valleys of the noise, never meeting or spreading apart
+    Curl,
+    /// Lines head in the direction the noise's value points them in, which lets them bunch up
+    Angle,
+}
+
+impl FieldKind {
+    fn direction(&self, noise_field: &NoiseField, xy: Point2) -> Vec2 {
+        match self {
+            FieldKind::Curl => noise_field.curl(xy),
+            FieldKind::Angle => {
+                let angle = noise_field.angle(xy);
+                vec2(angle.cos(), angle.sin())
+            }
+        }
+    }
+}
+
+/// The params that shape the field and its lines, everything a preset can set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Params {
+    pub field_kind: FieldKind,
+    pub noise: NoiseParams,
+    pub seed: u64,
+    pub streamlines: StreamlineParams,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            field_kind: FieldKind::Curl,
+            noise: NoiseParams {
+                scale: 0.004,
+                octaves: 2,
+                ..Default::default()
+            },
+            seed: 0,
+            streamlines: StreamlineParams::default(),
+        }
+    }
+}
+
+impl Validate for Params {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        self.noise.check(0.0005..=0.02)?;
+        check_range("streamlines.step", self.streamlines.step, 0.5..=10.0)?;
+        check_range(
+            "streamlines.separation",
+            self.streamlines.separation,
+            2.0..=50.0,
+        )?;
+        check_range(
+            "streamlines.max_length",
+            self.streamlines.max_length,
+            50.0..=5000.0,
+        )
+    }
+}
+
+pub struct State {
+    pub width: f32,
+    pub height: f32,
+    pub lines: Vec<Vec<Point2>>,
+    pub params: Params,
+    pub should_rebuild: bool,
+    pub show_paper_preview: bool,
+    pub pens: PenLibrary,
+    pub line_pen: usize,
+}
+
+impl State {
+    fn new(width: f32, height: f32) -> Self {
+        let pens = PenLibrary::load();
+
+        Self {
+            width,
+            height,
+            lines: Vec::new(),
+            params: Params::default(),
+            should_rebuild: true,
+            show_paper_preview: false,
+            line_pen: pens.index_of_or_first("0.3mm Marker"),
+            pens,
+        }
+    }
+
+    fn update(&mut self) {
+        if self.should_rebuild {
+            let bounds = Rect::from_corners(pt2(0.0, 0.0), pt2(self.width, self.height));
+            let seeds = Seeds::new(self.params.seed);
+            let noise_field = NoiseField::new(NoiseParams {
+                seed: seeds.noise_seed("field"),
+                ..self.params.noise
+            });
+            let mut rng = seeds.rng("first line");
+            let first_seed = pt2(
+                rng.gen_range(0.0..self.width),
+                rng.gen_range(0.0..self.height),
+            );
+            let field_kind = self.params.field_kind;
+
+            self.lines = trace_streamlines(bounds, self.params.streamlines, first_seed, |xy| {
+                field_kind.direction(&noise_field, xy)
+            });
+            self.should_rebuild = false;
+            trace!("traced {} streamlines", self.lines.len());
+        }
+    }
+}
+
+struct Model {
+    ui: Ui,
+    ids: Ids,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<Params>>,
+    pub state: State,
+}
+
+widget_ids! {
+    struct Ids {
+        export_dxf,
+        export_svg,
+        field_kind,
+        height,
+        line_pen,
+        max_length,
+        noise_kind,
+        noise_octaves,
+        noise_scale,
+        param_title_text,
+        seed,
+        separation,
+        step,
+        toggle_paper_preview,
+        width,
+    }
+}
+
+fn model(app: &App) -> Model {
+    let preset = PresetFile::from_args();
+    set_loop_mode_for(app, &preset);
+    let (width, height) = (800, 800);
+
+    let (window_width, window_height) = config().window_size_or((1280, 900));
+
+    let window_id = app
+        .new_window()
+        .size(window_width, window_height)
+        .view(view)
+        .event(event)
+        .build()
+        .expect("couldn't create a window");
+
+    // Create the UI.
+    let mut ui = app.new_ui().window(window_id).build().unwrap();
+
+    // Generate some ids for our widgets.
+    let ids = Ids::new(ui.widget_id_generator());
+    let state = State::new(width as f32, height as f32);
+
+    Model {
+        ui,
+        ids,
+        input: Input::new(KeyBindings::load()),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset,
+        state,
+    }
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    handle_actions(app, model);
+    update_ui(model);
+
+    if let Some(params) = model.preset.as_mut().and_then(|preset| preset.poll()) {
+        model.state.params = params;
+        model.state.should_rebuild = true;
+    }
+
+    model.state.update();
+}
+
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
+}
+
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => model.state.should_rebuild = true,
+            Action::NewSeed => {
+                model.state.params.seed = Seeds::random_master(0..=100);
+                model.state.should_rebuild = true;
+            }
+            Action::ExportSvg => {
+                if let Err(err) = export_as_svg(&model.state) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) = export_as_dxf(&model.state) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => {
+                model.state.show_paper_preview = !model.state.show_paper_preview
+            }
+            Action::FitToWindow => model.camera.fit(
+                Rect::from_w_h(model.state.width, model.state.height),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Export | Action::Undo | Action::Redo | Action::Custom(_) => (),
+        }
+    }
+}
+
+const SETTING_WIDTH: f64 = 300.0;
+const SETTING_HEIGHT: f64 = 30.0;
+const SETTING_MARGIN: f64 = 10.0;
+const LABEL_COLOR: nannou::ui::color::Color = nannou::ui::color::WHITE;
+const FILL: nannou::ui::color::Color = nannou::ui::color::DARK_BLUE;
+const FONT_SIZE: u32 = 12;
+
+fn dialer(val: f32, min: f32, max: f32) -> widget::NumberDialer<'static, f32> {
+    use nannou::ui::color::*;
+    widget::NumberDialer::new(val, min, max, 0)
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+}
+
+fn slider(val: f32, min: f32, max: f32) -> widget::Slider<'static, f32> {
+    use nannou::ui::color::*;
+    widget::Slider::new(val, min, max)
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+}
+
+fn drop_down_list(names: &[String], selected: usize) -> widget::DropDownList<'_, String> {
+    use nannou::ui::color::*;
+    widget::DropDownList::new(names, Some(selected))
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+}
+
+fn button(label: &str) -> widget::Button<'_, widget::button::Flat> {
+    use nannou::ui::color::*;
+    widget::Button::new()
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+        .label(label)
+}
+
+fn update_ui(model: &mut Model) {
+    let ui = &mut model.ui.set_widgets();
+    let mut should_refresh_model = false;
+
+    widget::Text::new("Parameters")
+        .top_left_with_margin(20.0)
+        .color(LABEL_COLOR)
+        .font_size(20)
+        .set(model.ids.param_title_text, ui);
+
+    for height in dialer(model.state.height, 10.0, 2160.0)
+        .down(SETTING_MARGIN)
+        .label("Height")
+        .set(model.ids.height, ui)
+    {
+        model.state.height = height.floor();
+        should_refresh_model = true;
+    }
+
+    for width in dialer(model.state.width, 10.0, 3840.0)
+        .down(SETTING_MARGIN)
+        .label("Width")
+        .set(model.ids.width, ui)
+    {
+        model.state.width = width.floor();
+        should_refresh_model = true;
+    }
+
+    for seed in slider(model.state.params.seed as f32, 0.0, 100.0)
+        .down(SETTING_MARGIN)
+        .label("Seed")
+        .set(model.ids.seed, ui)
+    {
+        model.state.params.seed = seed as u64;
+        should_refresh_model = true;
+    }
+
+    let field_kinds = vec!["Curl".to_owned(), "Angle".to_owned()];
+    let selected_field_kind = match model.state.params.field_kind {
+        FieldKind::Curl => 0,
+        FieldKind::Angle => 1,
+    };
+    for field_kind_index in drop_down_list(&field_kinds, selected_field_kind)
+        .down(SETTING_MARGIN)
+        .label("Field")
+        .set(model.ids.field_kind, ui)
+    {
+        model.state.params.field_kind = if field_kind_index == 1 {
+            FieldKind::Angle
+        } else {
+            FieldKind::Curl
+        };
+        should_refresh_model = true;
+    }
+
+    let noise_kind_names = NoiseKind::names();
+    for noise_kind_index in drop_down_list(&noise_kind_names, model.state.params.noise.kind.index())
+        .down(SETTING_MARGIN)
+        .label("Noise")
+        .set(model.ids.noise_kind, ui)
+    {
+        model.state.params.noise.kind = NoiseKind::ALL[noise_kind_index];
+        should_refresh_model = true;
+    }
+
+    for noise_scale in slider(model.state.params.noise.scale as f32, 0.0005, 0.02)
+        .down(SETTING_MARGIN)
+        .label("Noise Scale")
+        .set(model.ids.noise_scale, ui)
+    {
+        model.state.params.noise.scale = noise_scale as f64;
+        should_refresh_model = true;
+    }
+
+    for octaves in slider(model.state.params.noise.octaves as f32, 1.0, 8.0)
+        .down(SETTING_MARGIN)
+        .label("Noise Octaves")
+        .set(model.ids.noise_octaves, ui)
+    {
+        model.state.params.noise.octaves = octaves as u8;
+        should_refresh_model = true;
+    }
+
+    for step in slider(model.state.params.streamlines.step, 0.5, 10.0)
+        .down(SETTING_MARGIN)
+        .label("Step Size")
+        .set(model.ids.step, ui)
+    {
+        model.state.params.streamlines.step = step;
+        should_refresh_model = true;
+    }
+
+    for separation in slider(model.state.params.streamlines.separation, 2.0, 50.0)
+        .down(SETTING_MARGIN)
+        .label("Separation")
+        .set(model.ids.separation, ui)
+    {
+        model.state.params.streamlines.separation = separation;
+        should_refresh_model = true;
+    }
+
+    for max_length in slider(model.state.params.streamlines.max_length, 50.0, 5000.0)
+        .down(SETTING_MARGIN)
+        .label("Max Line Length")
+        .set(model.ids.max_length, ui)
+    {
+        model.state.params.streamlines.max_length = max_length;
+        should_refresh_model = true;
+    }
+
+    let pen_names = model.state.pens.names();
+    for pen_index in drop_down_list(&pen_names, model.state.line_pen)
+        .down(SETTING_MARGIN)
+        .label("Line Pen")
+        .set(model.ids.line_pen, ui)
+    {
+        model.state.line_pen = pen_index;
+    }
+
+    for _click in button("Paper Preview")
+        .down(SETTING_MARGIN)
+        .set(model.ids.toggle_paper_preview, ui)
+    {
+        model.state.show_paper_preview = !model.state.show_paper_preview;
+    }
+
+    for _click in button("Export SVG")
+        .down(SETTING_MARGIN)
+        .set(model.ids.export_svg, ui)
+    {
+        if let Err(err) = export_as_svg(&model.state) {
+            error!("{:?}", err)
+        }
+    }
+
+    for _click in button("Export DXF")
+        .down(SETTING_MARGIN)
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.state) {
+            error!("{:?}", err)
+        }
+    }
+
+    if should_refresh_model {
+        model.state.should_rebuild = true;
+        trace!("refresh model called");
+    }
+}
+
+// Draw the state of your `Model` into the given `Frame` here.
+fn view(app: &App, model: &Model, frame: Frame) {
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
+    let state = &model.state;
+
+    let pen = state.pens.get(state.line_pen);
+
+    if state.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
+
+        let drawing = build_drawing_from_state(state);
+        model.paper.draw(&draw, &drawing, |_| pen);
+    } else {
+        draw.background().color(WHITE);
+
+        let bounding_rect = Rect::from_w_h(state.width, state.height)
+            .align_middle_x_of(app.window_rect())
+            .align_middle_y_of(app.window_rect());
+
+        for line in state.lines.iter() {
+            // drawing coordinates have y pointing down the page, the window has it pointing up
+            let points = line
+                .iter()
+                .map(|xy| pt2(bounding_rect.left() + xy.x, bounding_rect.top() - xy.y));
+
+            draw.polyline()
+                .stroke_weight(pen.stroke_weight())
+                .color(pen.nannou_colour())
+                .points(points);
+        }
+    }
+
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
+    }
+
+    // Write the result of our drawing to the window's frame.
+    draw.to_frame(app, &frame).unwrap();
+
+    // Draw the state of the `Ui` to the frame.
+    model.ui.draw_to_frame(app, &frame).unwrap();
+}
+
+fn build_svg_document_from_state(state: &State) -> svg::Document {
+    let doc = svg::Document::new().set("viewBox", (0, 0, state.width, state.height));
+
+    let line_pen = state.pens.get(state.line_pen);
+    let mut group = line_pen
+        .style_svg_group(Group::new())
+        .set("stroke-linecap", "round")
+        .set("stroke-linejoin", "round");
+
+    for line in state.lines.iter() {
+        group = Shape::Polyline(line.clone()).add_to_svg_group(group);
+    }
+
+    let bounding_rect = svg::node::element::Rectangle::new()
+        .set("width", state.width)
+        .set("height", state.height);
+    let border = line_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
+
+    doc.add(group).add(border)
+}
+
+fn build_drawing_from_state(state: &State) -> Drawing {
+    let mut drawing = Drawing::new(state.width, state.height);
+    let border = drawing.border();
+
+    let lines = drawing.layer_mut("streamlines");
+    for line in state.lines.iter() {
+        lines.push(Shape::Polyline(line.clone()));
+    }
+
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(state: &State) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let drawing = build_drawing_from_state(state);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(state.params.seed),
+        &state.params,
+        &drawing,
+    )?;
+
+    Ok(())
+}
+
+fn export_as_svg(state: &State) -> Result<(), anyhow::Error> {
+    info!("exporting image as SVG...");
+    let document = build_svg_document_from_state(state);
+    let svg_filepath = unused_export_path("svg")?;
+
+    svg::save(&svg_filepath, &document)
+        .with_context(|| format!("couldn't write SVG to '{}'", svg_filepath.to_string_lossy()))?;
+    info!(
+        "SVG successfully exported to {}",
+        &svg_filepath.to_string_lossy()
+    );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(state.params.seed),
+        &state.params,
+        &build_drawing_from_state(state),
+    )?;
+
+    Ok(())
+}
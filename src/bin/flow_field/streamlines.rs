@@ -0,0 +1,305 @@
+//! Evenly spaced streamlines, after Jobard and Lefer's "Creating Evenly-Spaced Streamlines of
+//! Arbitrary Density". Each line is traced both ways from a seed until it leaves the drawing,
+//! gets too close to a line that's already been drawn or runs out of length. New seeds are tried
+//! a separation distance to either side of every finished line, so the lines grow outwards from
+//! the first one with even gaps between them.
+
+use lib_plotings::spatial::{GridHash, SpatialIndex};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// How close, as a fraction of the separation, a line can get to another before it's stopped.
+/// Lines are seeded further apart than they're allowed to end up so they have room to converge.
+const TEST_RATIO: f32 = 0.5;
+/// Lines shorter than this many separations are thrown away, they read as specks
+const MIN_LENGTH_RATIO: f32 = 2.0;
+/// How far back along a line, in separations, it has to have come before it's allowed to run
+/// into itself. Without this a line would stop at the point it just left.
+const SELF_LOOKBACK_RATIO: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamlineParams {
+    /// How far a line moves between points
+    pub step: f32,
+    /// How far apart neighbouring lines are seeded
+    pub separation: f32,
+    pub max_length: f32,
+}
+
+impl Default for StreamlineParams {
+    fn default() -> Self {
+        Self {
+            step: 2.0,
+            separation: 12.0,
+            max_length: 1500.0,
+        }
+    }
+}
+
+/// Streamlines following `direction` over `bounds`, starting from `first_seed`. `direction`
+/// doesn't have to return unit vectors, but lines stop where it returns zero. There are no lines
+/// unless the step and separation are more than zero and the maximum length is finite.
+pub fn trace_streamlines(
+    bounds: Rect,
+    params: StreamlineParams,
+    first_seed: Point2,
+    direction: impl Fn(Point2) -> Vec2,
+) -> Vec<Vec<Point2>> {
+    // a line that doesn't step never reaches its length, and lines with no gap between them
+    // can't be kept apart
+    let is_positive = |value: f32| value > 0.0 && value.is_finite();
+    if !is_positive(params.step)
+        || !is_positive(params.separation)
+        || !params.max_length.is_finite()
+    {
+        return Vec::new();
+    }
+
+    let mut tracer = Tracer {
+        bounds,
+        params,
+        direction,
+        taken: GridHash::new(&[], params.separation),
+        lines: Vec::new(),
+    };
+
+    // once the lines can't grow outwards any further, the rest of the drawing is swept for gaps
+    // big enough to start again in
+    let columns = (bounds.w() / params.separation).ceil() as usize;
+    let rows = (bounds.h() / params.separation).ceil() as usize;
+    let sweep = (0..rows).flat_map(|y| {
+        (0..columns).map(move |x| {
+            pt2(
+                bounds.left() + (x as f32 + 0.5) * params.separation,
+                bounds.bottom() + (y as f32 + 0.5) * params.separation,
+            )
+        })
+    });
+    let mut unseeded = VecDeque::new();
+
+    for seed in std::iter::once(first_seed).chain(sweep) {
+        if let Some(index) = tracer.try_seed(seed) {
+            unseeded.push_back(index);
+        }
+
+        while let Some(index) = unseeded.pop_front() {
+            for seed in side_seeds(&tracer.lines[index], params.separation) {
+                if let Some(index) = tracer.try_seed(seed) {
+                    unseeded.push_back(index);
+                }
+            }
+        }
+    }
+
+    tracer.lines
+}
+
+struct Tracer<F> {
+    bounds: Rect,
+    params: StreamlineParams,
+    direction: F,
+    /// Every point of every finished line
+    taken: GridHash,
+    lines: Vec<Vec<Point2>>,
+}
+
+impl<F: Fn(Point2) -> Vec2> Tracer<F> {
+    /// Trace a line from `seed` if there's room for one, returning its index in `lines`
+    fn try_seed(&mut self, seed: Point2) -> Option<usize> {
+        if !self.bounds.contains(seed)
+            || !self
+                .taken
+                .within_radius(seed, self.params.separation)
+                .is_empty()
+        {
+            return None;
+        }
+
+        let line = self.trace(seed);
+        let length: f32 = line.windows(2).map(|pair| pair[0].distance(pair[1])).sum();
+        if length < self.params.separation * MIN_LENGTH_RATIO {
+            return None;
+        }
+
+        for xy in line.iter() {
+            self.taken.insert(*xy);
+        }
+        self.lines.push(line);
+
+        Some(self.lines.len() - 1)
+    }
+
+    /// Follow the field forwards from `seed` for up to half the maximum length, then backwards
+    /// for whatever length's left
+    fn trace(&self, seed: Point2) -> Vec<Point2> {
+        let mut own = OwnPoints::new(self.params.separation * TEST_RATIO);
+        own.insert(seed, 0.0);
+
+        let forwards = self.follow(seed, 1.0, self.params.max_length / 2.0, &mut own);
+        let forwards_length = forwards.len() as f32 * self.params.step;
+        let backwards = self.follow(
+            seed,
+            -1.0,
+            self.params.max_length - forwards_length,
+            &mut own,
+        );
+
+        backwards
+            .into_iter()
+            .rev()
+            .chain(std::iter::once(seed))
+            .chain(forwards)
+            .collect()
+    }
+
+    /// The points after `seed` going with (`sign` 1) or against (`sign` -1) the field
+    fn follow(&self, seed: Point2, sign: f32, max_length: f32, own: &mut OwnPoints) -> Vec<Point2> {
+        let min_gap = self.params.separation * TEST_RATIO;
+        let lookback = self.params.separation * SELF_LOOKBACK_RATIO;
+        let mut points = Vec::new();
+        let mut xy = seed;
+        let mut length = 0.0;
+
+        while length + self.params.step <= max_length {
+            // midpoint method, a half step to find the direction for the whole step. It keeps
+            // lines from spiralling outwards where they should go round in circles.
+            let next = match self
+                .unit_direction(xy)
+                .map(|direction| xy + direction * sign * self.params.step / 2.0)
+                .and_then(|midpoint| self.unit_direction(midpoint))
+            {
+                Some(direction) => xy + direction * sign * self.params.step,
+                None => break,
+            };
+            length += self.params.step;
+
+            if !self.bounds.contains(next)
+                || !self.taken.within_radius(next, min_gap).is_empty()
+                || own.is_near_earlier(next, sign * length, lookback)
+            {
+                break;
+            }
+
+            own.insert(next, sign * length);
+            points.push(next);
+            xy = next;
+        }
+
+        points
+    }
+
+    fn unit_direction(&self, xy: Point2) -> Option<Vec2> {
+        let direction = (self.direction)(xy);
+        let length = direction.length();
+
+        if length > f32::EPSILON && length.is_finite() {
+            Some(direction / length)
+        } else {
+            None
+        }
+    }
+}
+
+/// The points of the line being traced, along with how far along the line each one is from its
+/// seed, negative for the backwards half
+struct OwnPoints {
+    min_gap: f32,
+    grid: GridHash,
+    distances: Vec<f32>,
+}
+
+impl OwnPoints {
+    fn new(min_gap: f32) -> Self {
+        Self {
+            min_gap,
+            grid: GridHash::new(&[], min_gap),
+            distances: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, xy: Point2, distance: f32) {
+        self.grid.insert(xy);
+        self.distances.push(distance);
+    }
+
+    /// Whether `xy` comes close to a part of the line more than `lookback` away along it, i.e.
+    /// the line has looped back on itself
+    fn is_near_earlier(&self, xy: Point2, distance: f32, lookback: f32) -> bool {
+        self.grid
+            .within_radius(xy, self.min_gap)
+            .into_iter()
+            .any(|index| (distance - self.distances[index]).abs() > lookback)
+    }
+}
+
+/// Candidate seeds a separation to the left and right of every point on a line
+fn side_seeds(line: &[Point2], separation: f32) -> Vec<Point2> {
+    let mut seeds = Vec::with_capacity(line.len() * 2);
+
+    for index in 0..line.len() {
+        let before = line[index.saturating_sub(1)];
+        let after = line[(index + 1).min(line.len() - 1)];
+        let tangent = after - before;
+        if tangent.length() <= f32::EPSILON {
+            continue;
+        }
+
+        let normal = vec2(-tangent.y, tangent.x) / tangent.length();
+        seeds.push(line[index] + normal * separation);
+        seeds.push(line[index] - normal * separation);
+    }
+
+    seeds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_fill_the_bounds_without_touching() {
+        let bounds = Rect::from_corners(pt2(0.0, 0.0), pt2(200.0, 100.0));
+        let params = StreamlineParams {
+            step: 1.0,
+            separation: 10.0,
+            max_length: 1000.0,
+        };
+        // circles around the middle of the bounds
+        let lines = trace_streamlines(bounds, params, pt2(30.0, 50.0), |xy| {
+            let offset = xy - bounds.xy();
+            vec2(-offset.y, offset.x)
+        });
+
+        assert!(lines.len() > 5);
+
+        for (index, line) in lines.iter().enumerate() {
+            assert!(line.iter().all(|xy| bounds.contains(*xy)));
+
+            for other in lines[index + 1..].iter() {
+                for a in line.iter() {
+                    for b in other.iter() {
+                        assert!(a.distance(*b) >= params.separation * TEST_RATIO - 1e-3);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_steps_and_separations_that_would_never_finish_give_no_lines() {
+        let bounds = Rect::from_corners(pt2(0.0, 0.0), pt2(200.0, 100.0));
+        let rightwards = |_| vec2(1.0, 0.0);
+
+        for (step, separation) in [(0.0, 10.0), (2.0, 0.0), (-1.0, 10.0), (f32::NAN, 10.0)] {
+            let params = StreamlineParams {
+                step,
+                separation,
+                ..StreamlineParams::default()
+            };
+
+            assert!(trace_streamlines(bounds, params, bounds.xy(), rightwards).is_empty());
+        }
+    }
+}
@@ -0,0 +1,51 @@
+/// Builds a static HTML gallery of everything in the export journal, with a thumbnail, the params
+/// and the command that opens each piece again, e.g.
+/// `cargo run --release --bin gallery -- [journal.jsonl] [gallery.html]`
+/// Both default to files in the export directory.
+use anyhow::Context;
+use lib_plotings::config::positional_args;
+use lib_plotings::gallery;
+use lib_plotings::journal::{self, journal_path};
+use log::{error, info, warn};
+use std::path::{Path, PathBuf};
+
+const GALLERY_FILENAME: &str = "gallery.html";
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    let mut args = positional_args().into_iter().map(PathBuf::from);
+
+    if let Err(err) = run(args.next(), args.next()) {
+        error!("{:?}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(journal: Option<PathBuf>, output: Option<PathBuf>) -> Result<(), anyhow::Error> {
+    let journal = journal.unwrap_or_else(journal_path);
+    let output = output.unwrap_or_else(|| {
+        journal
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(GALLERY_FILENAME)
+    });
+
+    let entries = journal::read(&journal)?;
+    let gallery_dir = output.parent().unwrap_or_else(|| Path::new("."));
+    let html = gallery::render(&entries, gallery_dir);
+
+    std::fs::write(&output, html)
+        .with_context(|| format!("couldn't write the gallery '{}'", output.to_string_lossy()))?;
+    info!(
+        "gallery of {} exports written to {}",
+        entries.len(),
+        output.to_string_lossy()
+    );
+
+    Ok(())
+}
@@ -1,10 +1,23 @@
-use anyhow::Context;
-use chrono::Local;
-use lib_plotings::Interpolate;
-use log::{debug, error, info, trace, warn};
+use lib_plotings::camera::Camera;
+use lib_plotings::config::config;
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::{Drawing, Shape};
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::pen::{PenLibrary, PenProfile, PIXELS_PER_MM};
+use lib_plotings::preset::{
+    check_range, draw_error_banner, set_loop_mode_for, PresetFile, Validate,
+};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::seed::Seeds;
+use lib_plotings::smoothing::Smoothing;
+use lib_plotings::svg::svg_window_transform;
+use lib_plotings::worker::{draw_progress_bar, keep_updating_while, Progress, Worker};
+use log::{error, info, trace, warn};
 use nannou::{prelude::*, ui::prelude::*};
 use rand::{prelude::StdRng, Rng, SeedableRng};
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
 use svg::node::element::Polyline;
 
 fn main() {
@@ -19,11 +32,12 @@ fn main() {
 
 type PointColumns = Vec<Vec<Vec2>>;
 
-#[derive(Debug, PartialEq, Clone)]
+/// How far apart the points on smoothed lines are
+const SMOOTHED_POINT_SPACING: f32 = 2.0;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
 struct PointColumnParams {
-    pub chaikin_smoothing: bool,
-    pub chaikin_smoothing_iterations: u8,
-    pub chaikin_smoothing_ratio: f32,
     pub column_alignment: f32,
     pub column_spacing: f32,
     pub column_width: f32,
@@ -32,6 +46,7 @@ struct PointColumnParams {
     pub noise_seed: u64,
     pub number_of_columns: usize,
     pub points_per_line: usize,
+    pub smoothing: Smoothing,
     pub vertical_jitter: f32,
     pub width: f32,
 }
@@ -39,9 +54,6 @@ struct PointColumnParams {
 impl Default for PointColumnParams {
     fn default() -> Self {
         Self {
-            chaikin_smoothing: false,
-            chaikin_smoothing_iterations: 4,
-            chaikin_smoothing_ratio: 0.25,
             column_alignment: 0.0,
             column_spacing: 80.0,
             column_width: 125.0,
@@ -50,47 +62,75 @@ impl Default for PointColumnParams {
             noise_seed: 0,
             number_of_columns: 8,
             points_per_line: 16,
+            smoothing: Smoothing::None,
             vertical_jitter: 0.0,
             width: 1000.0,
         }
     }
 }
 
+impl Validate for PointColumnParams {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        self.smoothing.validate()?;
+        check_range("column_alignment", self.column_alignment, -1.0..=1.0)?;
+        check_range("column_spacing", self.column_spacing, 0.0..=10_000.0)?;
+        check_range("column_width", self.column_width, 0.0..=1000.0)?;
+        check_range("height", self.height, 0.0..=10_000.0)?;
+        check_range("lines_per_column", self.lines_per_column, 1..=100)?;
+        check_range("number_of_columns", self.number_of_columns, 1..=999)?;
+        check_range("points_per_line", self.points_per_line, 2..=200)?;
+        check_range("vertical_jitter", self.vertical_jitter, 0.0..=100.0)?;
+        check_range("width", self.width, 0.0..=10_000.0)
+    }
+}
+
 struct Model {
     ui: Ui,
     ids: Ids,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<PointColumnParams>>,
+    generator: Worker<PointColumns>,
     pub point_columns: PointColumns,
     pub point_column_params: PointColumnParams,
-    pub show_viewbox: bool,
+    pub show_paper_preview: bool,
+    pub pens: PenLibrary,
+    pub line_pen: usize,
 }
 
 widget_ids! {
     struct Ids {
-        chaikin_smoothing,
         chaikin_smoothing_ratio,
         column_alignment,
         column_spacing,
         column_width,
+        export_dxf,
         export_svg,
         height,
+        line_pen,
         lines_per_column,
         noise_seed,
         number_of_columns,
         points_per_line,
-        toggle_viewbox,
+        smoothing,
+        toggle_paper_preview,
         vertical_jitter,
         width,
     }
 }
 
 fn model(app: &App) -> Model {
-    // Set the loop mode to wait for events, an energy-efficient option for pure-GUI apps.
-    app.set_loop_mode(LoopMode::Wait);
+    let preset = PresetFile::from_args();
+    set_loop_mode_for(app, &preset);
+
+    let (window_width, window_height) = config().window_size_or((1920, 1080));
 
     let window_id = app
         .new_window()
-        .size(1920, 1080)
+        .size(window_width, window_height)
         .view(view)
+        .event(event)
         .build()
         .expect("couldn't create a window");
 
@@ -99,25 +139,96 @@ fn model(app: &App) -> Model {
 
     // Generate some ids for our widgets.
     let ids = Ids::new(ui.widget_id_generator());
+    let pens = PenLibrary::load();
+    let line_pen = pens.index_of_or_first("1mm Marker");
 
     Model {
         ui,
         ids,
+        input: Input::new(KeyBindings::load()),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset,
+        generator: Worker::new(),
         point_columns: Vec::new(),
         point_column_params: Default::default(),
-        show_viewbox: false,
+        show_paper_preview: false,
+        pens,
+        line_pen,
     }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    handle_actions(app, model);
     update_ui(model);
 
-    if model.point_columns.is_empty() {
-        model.point_columns = generate_point_columns(&model.point_column_params);
+    if let Some(params) = model.preset.as_mut().and_then(|preset| preset.poll()) {
+        model.point_column_params = params;
+        start_generating(&mut model.generator, &model.point_column_params);
+    }
+
+    if model.point_columns.is_empty() && !model.generator.is_running() {
+        start_generating(&mut model.generator, &model.point_column_params);
+    }
+
+    if let Some(point_columns) = model.generator.poll() {
+        trace!("generated {} lines", point_columns.len());
+        model.point_columns = point_columns;
+    }
+
+    keep_updating_while(app, model.preset.is_some() || model.generator.is_running());
+}
+
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
+}
+
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => {
+                start_generating(&mut model.generator, &model.point_column_params)
+            }
+            Action::NewSeed => {
+                model.point_column_params.noise_seed = Seeds::random_master(0..=9999);
+                start_generating(&mut model.generator, &model.point_column_params);
+            }
+            Action::ExportSvg => {
+                let line_pen = model.pens.get(model.line_pen);
+                if let Err(err) =
+                    export_as_svg(&model.point_columns, &model.point_column_params, line_pen)
+                {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) = export_as_dxf(&model.point_columns, &model.point_column_params) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => model.show_paper_preview = !model.show_paper_preview,
+            Action::FitToWindow => model.camera.fit(
+                Rect::from_w_h(
+                    model.point_column_params.width,
+                    model.point_column_params.height,
+                ),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Export | Action::Undo | Action::Redo | Action::Custom(_) => (),
+        }
     }
 }
 
-fn generate_point_columns(params: &PointColumnParams) -> PointColumns {
+/// Generate the columns on the worker, the ones on screen are swapped out once they're done
+fn start_generating(generator: &mut Worker<PointColumns>, params: &PointColumnParams) {
+    let params = params.clone();
+    generator.start(move |progress| generate_point_columns(&params, progress));
+}
+
+fn generate_point_columns(params: &PointColumnParams, progress: &Progress) -> Option<PointColumns> {
     let mut lines = Vec::new();
     let mut rng: StdRng = SeedableRng::seed_from_u64(params.noise_seed);
     let (origin_x, origin_y) = (params.width * -0.5, params.height * -0.5);
@@ -128,13 +239,18 @@ fn generate_point_columns(params: &PointColumnParams) -> PointColumns {
 
     let width_of_a_column_and_a_space = column_width + params.column_spacing;
     for c in 0..params.number_of_columns {
+        if progress.is_cancelled() {
+            return None;
+        }
+
         let origin_x = c as f32 * width_of_a_column_and_a_space + origin_x;
         lines.append(&mut generate_point_column(
             origin_x, origin_y, params, &mut rng,
         ));
+        progress.set(c + 1, params.number_of_columns);
     }
 
-    lines
+    Some(lines)
 }
 
 fn generate_point_column(
@@ -144,8 +260,6 @@ fn generate_point_column(
     rng: &mut impl Rng,
 ) -> PointColumns {
     let vertical_spacing = params.height / params.points_per_line as f32;
-    let chaikin_smoothing_iterations = params.chaikin_smoothing_iterations;
-    let chaikin_smoothing_ratio = params.chaikin_smoothing_ratio;
     let mut column_section_widths = Vec::new();
 
     for _ in 0..params.points_per_line {
@@ -157,7 +271,7 @@ fn generate_point_column(
     let mut lines = Vec::new();
 
     for line_index in 0..params.lines_per_column {
-        let mut line = generate_line(
+        let line = generate_line(
             &column_section_widths,
             line_index,
             origin_x,
@@ -166,21 +280,11 @@ fn generate_point_column(
             params,
         );
 
-        if params.chaikin_smoothing {
-            line = generate_smooth_line(
-                line,
-                &column_section_widths,
-                line_index,
-                origin_x,
-                origin_y,
-                vertical_spacing,
-                params,
-                chaikin_smoothing_iterations,
-                chaikin_smoothing_ratio,
-            );
-        };
-
-        lines.push(line);
+        lines.push(
+            params
+                .smoothing
+                .smooth(&line, false, SMOOTHED_POINT_SPACING),
+        );
     }
 
     lines
@@ -209,85 +313,6 @@ fn generate_line(
     line
 }
 
-fn generate_smooth_line(
-    line: Vec<Vec2>,
-    column_section_widths: &[f32],
-    line_index: usize,
-    origin_x: f32,
-    origin_y: f32,
-    vertical_spacing: f32,
-    params: &PointColumnParams,
-    iterations: u8,
-    ratio: f32,
-) -> Vec<Vec2> {
-    if iterations == 0 {
-        return line;
-    }
-
-    let num_corners = line.len() - 1;
-
-    let mut smoothed_line = Vec::with_capacity(line.len() * 2);
-
-    for i in 0..num_corners {
-        // Get the i'th and (i+1)'th vertex to work on that edge.
-        let a = line.get(i).unwrap();
-        let b = line.get(i + 1).or_else(|| line.get(1)).unwrap();
-
-        // Step 3: Break it using our chaikin_cut() function
-        let (n1, n2) = chaikin_cut(a, b, ratio);
-
-        if i == 0 {
-            // For the first point of open shapes, ignore vertex A
-            smoothed_line.push(Vec2::new(a.x, a.y));
-            smoothed_line.push(Vec2::new(n2.x, n2.y));
-        } else if i == num_corners - 1 {
-            // For the last point of open shapes, ignore vertex B
-            smoothed_line.push(Vec2::new(n1.x, n1.y));
-            smoothed_line.push(Vec2::new(b.x, b.y));
-        } else {
-            // For all other cases (i.e. interior edges of open
-            // shapes or edges of closed shapes), add both vertices
-            // returned by our chaikin_cut() method
-            smoothed_line.push(Vec2::new(n1.x, n1.y));
-            smoothed_line.push(Vec2::new(n2.x, n2.y));
-        }
-    }
-
-    generate_smooth_line(
-        smoothed_line,
-        column_section_widths,
-        line_index,
-        origin_x,
-        origin_y,
-        vertical_spacing,
-        params,
-        iterations - 1,
-        ratio,
-    )
-}
-
-fn chaikin_cut(a: &Vec2, b: &Vec2, mut ratio: f32) -> (Vec2, Vec2) {
-    // If ratio is greater than 0.5 flip it so we avoid cutting across
-    // the midpoint of the line.
-    if ratio > 0.5 {
-        ratio = 1.0 - ratio;
-    }
-
-    // Find point at a given ratio going from A to B */
-    let xy_1 = Vec2::new(
-        f32::interpolate(a.x, b.x, ratio),
-        f32::interpolate(a.y, b.y, ratio),
-    );
-
-    // Find point at a given ratio going from B to A */
-    let xy_2 = Vec2::new(
-        f32::interpolate(b.x, a.x, ratio),
-        f32::interpolate(b.y, a.y, ratio),
-    );
-
-    return (xy_1, xy_2);
-}
-
 fn dialer(val: f32, min: f32, max: f32) -> widget::NumberDialer<'static, f32> {
     widget::NumberDialer::new(val, min, max, 0)
         .w_h(300.0, 20.0)
@@ -407,48 +432,65 @@ fn update_ui(model: &mut Model) {
         should_refresh_point_columns = true;
     }
 
-    for is_toggled in widget::Toggle::new(model.point_column_params.chaikin_smoothing)
-        .down(10.0)
-        .w_h(300.0, 20.0)
-        .label_font_size(12)
-        .rgb(0.3, 0.3, 0.3)
-        .label_rgb(1.0, 1.0, 1.0)
-        .border(0.0)
-        .label("Toggle Chaikin Smoothing")
-        .set(model.ids.chaikin_smoothing, ui)
+    let smoothing_names = Smoothing::names();
+    for smoothing_index in widget::DropDownList::new(
+        &smoothing_names,
+        Some(model.point_column_params.smoothing.index()),
+    )
+    .down(10.0)
+    .w_h(300.0, 20.0)
+    .label_font_size(12)
+    .rgb(0.3, 0.3, 0.3)
+    .label_rgb(1.0, 1.0, 1.0)
+    .border(0.0)
+    .label("Smoothing")
+    .set(model.ids.smoothing, ui)
     {
-        model.point_column_params.chaikin_smoothing = is_toggled;
+        model.point_column_params.smoothing = Smoothing::ALL[smoothing_index];
         should_refresh_point_columns = true;
     }
 
-    if model.point_column_params.chaikin_smoothing {
-        for ratio in
-            widget::Slider::new(model.point_column_params.chaikin_smoothing_ratio, 0.0, 0.5)
-                .down(10.0)
-                .w_h(300.0, 20.0)
-                .label_font_size(12)
-                .rgb(0.3, 0.3, 0.3)
-                .label_rgb(1.0, 1.0, 1.0)
-                .border(0.0)
-                .label("Smoothing Ratio")
-                .set(model.ids.chaikin_smoothing_ratio, ui)
+    if let Smoothing::Chaikin { iterations, ratio } = model.point_column_params.smoothing {
+        for ratio in widget::Slider::new(ratio, 0.0, 0.5)
+            .down(10.0)
+            .w_h(300.0, 20.0)
+            .label_font_size(12)
+            .rgb(0.3, 0.3, 0.3)
+            .label_rgb(1.0, 1.0, 1.0)
+            .border(0.0)
+            .label("Smoothing Ratio")
+            .set(model.ids.chaikin_smoothing_ratio, ui)
         {
-            model.point_column_params.chaikin_smoothing_ratio = ratio;
+            model.point_column_params.smoothing = Smoothing::Chaikin { iterations, ratio };
             should_refresh_point_columns = true;
         }
     }
 
-    for is_toggled in widget::Toggle::new(model.show_viewbox)
+    let pen_names = model.pens.names();
+    for pen_index in widget::DropDownList::new(&pen_names, Some(model.line_pen))
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Line Pen")
+        .set(model.ids.line_pen, ui)
+    {
+        model.line_pen = pen_index;
+    }
+
+    for is_toggled in widget::Toggle::new(model.show_paper_preview)
         .down(10.0)
         .w_h(300.0, 20.0)
         .label_font_size(12)
         .rgb(0.3, 0.3, 0.3)
         .label_rgb(1.0, 1.0, 1.0)
         .border(0.0)
-        .label("Toggle Viewbox")
-        .set(model.ids.toggle_viewbox, ui)
+        .label("Paper Preview")
+        .set(model.ids.toggle_paper_preview, ui)
     {
-        model.show_viewbox = is_toggled;
+        model.show_paper_preview = is_toggled;
     }
 
     for _click in widget::Button::new()
@@ -463,47 +505,64 @@ fn update_ui(model: &mut Model) {
     {
         let point_columns = &model.point_columns;
         let point_column_params = &model.point_column_params;
-        if let Err(err) = export_as_svg(point_columns, point_column_params) {
+        let line_pen = model.pens.get(model.line_pen);
+        if let Err(err) = export_as_svg(point_columns, point_column_params, line_pen) {
             error!("{}", err)
         }
     }
 
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Export DXF")
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.point_columns, &model.point_column_params) {
+            error!("{:?}", err)
+        }
+    }
+
     if should_refresh_point_columns {
-        model.point_columns = generate_point_columns(&model.point_column_params);
-        trace!(
-            "refresh point columns called, generated {} lines",
-            model.point_columns.len()
-        );
+        start_generating(&mut model.generator, &model.point_column_params);
     }
 }
 
 // Draw the state of your `Model` into the given `Frame` here.
 fn view(app: &App, model: &Model, frame: Frame) {
     // Begin drawing
-    let draw = app.draw();
-
-    draw.background().color(WHITE);
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
+
+    let line_pen = model.pens.get(model.line_pen);
+
+    if model.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
+
+        let drawing = build_drawing_from_model(&model.point_columns, &model.point_column_params);
+        model.paper.draw(&draw, &drawing, |_| line_pen);
+    } else {
+        draw.background().color(WHITE);
+
+        for column in &model.point_columns {
+            draw.polyline()
+                .weight(line_pen.stroke_weight())
+                .color(line_pen.nannou_colour())
+                .join_round()
+                // do I really have to clone here?
+                .points(column.to_owned());
+        }
+    }
 
-    for column in &model.point_columns {
-        draw.polyline()
-            .weight(3.0)
-            .color(BLACK)
-            .join_round()
-            // do I really have to clone here?
-            .points(column.to_owned());
+    if let Some(fraction_done) = model.generator.progress() {
+        draw_progress_bar(&window_draw, app.window_rect(), fraction_done);
     }
 
-    if model.show_viewbox {
-        draw.rect()
-            // we want the box centered on the screen, nannou places rectangle from their center
-            .x_y(0.0, 0.0)
-            .w_h(
-                model.point_column_params.width,
-                model.point_column_params.height,
-            )
-            .stroke(RED)
-            .stroke_weight(2.0)
-            .no_fill();
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
     }
 
     // Write the result of our drawing to the window's frame.
@@ -516,16 +575,20 @@ fn view(app: &App, model: &Model, frame: Frame) {
 fn build_svg_document_from_model(
     point_columns: &PointColumns,
     point_column_params: &PointColumnParams,
+    line_pen: &PenProfile,
 ) -> svg::Document {
     let doc = svg::Document::new().set(
         "viewBox",
         (0, 0, point_column_params.width, point_column_params.height),
     );
 
-    let mut group = svg::node::element::Group::new()
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", "1mm");
+    // the columns are laid out around the middle of the window
+    let mut group = line_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .set(
+            "transform",
+            svg_window_transform(point_column_params.width, point_column_params.height),
+        );
 
     for line in point_columns.iter() {
         let data: Vec<_> = line
@@ -540,51 +603,77 @@ fn build_svg_document_from_model(
 
     let bounding_rect = svg::node::element::Rectangle::new()
         .set("width", point_column_params.width)
-        .set("height", point_column_params.height)
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", "2mm");
+        .set("height", point_column_params.height);
+    let border = line_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
+
+    doc.add(group).add(border)
+}
+
+fn build_drawing_from_model(
+    point_columns: &PointColumns,
+    point_column_params: &PointColumnParams,
+) -> Drawing {
+    let mut drawing = Drawing::new(point_column_params.width, point_column_params.height);
+    let border = drawing.border();
 
-    doc.add(group).add(bounding_rect)
+    let lines = drawing.layer_mut("lines");
+    for line in point_columns.iter() {
+        lines.push(Shape::Polyline(line.clone()));
+    }
+    drawing.move_from_window();
+
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(
+    point_columns: &PointColumns,
+    point_column_params: &PointColumnParams,
+) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let drawing = build_drawing_from_model(point_columns, point_column_params);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(point_column_params.noise_seed),
+        point_column_params,
+        &drawing,
+    )?;
+
+    Ok(())
 }
 
 fn export_as_svg(
     point_columns: &PointColumns,
     point_column_params: &PointColumnParams,
+    line_pen: &PenProfile,
 ) -> Result<(), anyhow::Error> {
     info!("exporting image as SVG...");
-    let document = build_svg_document_from_model(point_columns, point_column_params);
-    let base_path = std::env::var("SVG_EXPORT_DIRECTORY").context("setting 'SVG_EXPORT_DIRECTORY' is required, please set it to the directory you wish to export SVGs to")?;
-    let current_date = Local::today().format("%Y-%m-%d");
-    let svg_filename = format!("{}-plotling.svg", &current_date);
-    let mut svg_filepath: PathBuf = [base_path, svg_filename].iter().collect();
-
-    // I don't want to silently overwrite anything so I look for an unused filename,
-    // incrementing the counter until I find an unused number
-    // I could have also used a random string/number, I just like this better
-    if svg_filepath.exists() {
-        let mut counter = 1;
-
-        while svg_filepath.exists() {
-            if counter > 100 {
-                debug!(
-                    "export_as_svg counter has reached {}, you're not in an infinite loop are you?",
-                    counter
-                );
-            }
-
-            let _ = svg_filepath.pop();
-            let svg_filename = format!("{}-plotling-{}.svg", &current_date, &counter);
-            svg_filepath.push(svg_filename);
-            counter += 1;
-        }
-    }
+    let document = build_svg_document_from_model(point_columns, point_column_params, line_pen);
+    let svg_filepath = unused_export_path("svg")?;
 
     svg::save(&svg_filepath, &document)?;
     info!(
         "SVG successfully exported to {}",
         &svg_filepath.to_string_lossy()
     );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(point_column_params.noise_seed),
+        point_column_params,
+        &build_drawing_from_model(point_columns, point_column_params),
+    )?;
 
     Ok(())
 }
@@ -1,11 +1,29 @@
 mod pasta;
 
-use anyhow::Context;
-use chrono::Local;
-use log::{debug, error, info, trace, warn};
+use lib_plotings::camera::Camera;
+use lib_plotings::config::config;
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::Drawing;
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::noise::{NoiseKind, NoiseParams};
+use lib_plotings::pen::{PenLibrary, PIXELS_PER_MM};
+use lib_plotings::preset::{
+    check_range, draw_error_banner, set_loop_mode_for, PresetFile, Validate,
+};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::seed::Seeds;
+use lib_plotings::smoothing::Smoothing;
+use lib_plotings::worker::{draw_progress_bar, keep_updating_while, Worker};
+use log::{error, info, trace, warn};
 use nannou::{geom::Rect, prelude::*, ui::prelude::*};
-use pasta::{new_lines_from_noise, Line};
-use std::path::PathBuf;
+use pasta::{new_lines_from_noise, Line, Lines};
+use serde::{Deserialize, Serialize};
+
+/// Names of the actions for this generator's own keys
+const LONGER_LINES: &str = "longer lines";
+const SHORTER_LINES: &str = "shorter lines";
 
 fn main() {
     let res = dotenv::dotenv();
@@ -17,78 +35,131 @@ fn main() {
     nannou::app(model).update(update).run();
 }
 
+/// The params that shape the lines, everything a preset can set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Params {
+    pub noise: NoiseParams,
+    /// Seeds the noise and the jitter, `noise.seed` is ignored
+    pub seed: u64,
+    pub line_count: usize,
+    pub smoothing: Smoothing,
+    pub vertical_jitter: f32,
+    pub min_line_length: f32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            noise: NoiseParams {
+                scale: 0.01,
+                ..Default::default()
+            },
+            seed: 0,
+            line_count: 200,
+            smoothing: Smoothing::None,
+            vertical_jitter: 0.001,
+            min_line_length: 10.0,
+        }
+    }
+}
+
+impl Validate for Params {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        self.noise.check(0.001..=0.01)?;
+        self.smoothing.validate()?;
+        check_range("line_count", self.line_count, 1..=512)?;
+        check_range("vertical_jitter", self.vertical_jitter, 0.001..=100.0)?;
+        check_range("min_line_length", self.min_line_length, 1.0..=100.0)
+    }
+}
+
 #[derive(Default)]
 pub struct State {
+    generator: Worker<Lines>,
     pub height: f32,
     pub lines: Vec<Line>,
-    pub noise_seed: u64,
-    pub noise_scale: f64,
-    pub line_count: usize,
+    pub params: Params,
     pub should_rebuild: bool,
-    pub show_viewbox: bool,
-    pub stroke_weight: f32,
-    pub vertical_jitter: f32,
+    pub show_paper_preview: bool,
+    pub pens: PenLibrary,
+    pub line_pen: usize,
     pub width: f32,
-    pub min_line_length: f32,
 }
 
 impl State {
     fn new(width: f32, height: f32) -> Self {
+        let pens = PenLibrary::load();
+
         Self {
+            generator: Worker::new(),
             height,
             width,
-            show_viewbox: false,
+            show_paper_preview: false,
             should_rebuild: true,
-            stroke_weight: 3.0,
+            line_pen: pens.index_of_or_first("1mm Marker"),
+            pens,
             lines: Vec::new(),
-            line_count: 200,
-            min_line_length: 10.0,
-            vertical_jitter: 0.001,
-            noise_seed: 0,
-            noise_scale: 0.01,
+            params: Params::default(),
         }
     }
 
     fn update(&mut self) {
         if self.should_rebuild {
-            self.lines = new_lines_from_noise(&self);
+            let (params, width, height) = (self.params.clone(), self.width, self.height);
+            self.generator
+                .start(move |progress| new_lines_from_noise(&params, width, height, progress));
             self.should_rebuild = false;
         }
+
+        if let Some(lines) = self.generator.poll() {
+            self.lines = lines;
+        }
     }
 }
 
 struct Model {
     ui: Ui,
     ids: Ids,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<Params>>,
     pub state: State,
 }
 
 widget_ids! {
     struct Ids {
+        export_dxf,
         export_svg,
         height,
         line_count,
+        line_pen,
         min_line_length,
+        noise_kind,
+        noise_octaves,
         noise_scale,
-        noise_seed,
         param_title_text,
-        stroke_weight,
-        toggle_viewbox,
+        seed,
+        smoothing,
+        toggle_paper_preview,
         vertical_jitter,
         width,
     }
 }
 
 fn model(app: &App) -> Model {
-    // Set the loop mode to wait for events, an energy-efficient option for pure-GUI apps.
-    app.set_loop_mode(LoopMode::Wait);
+    let preset = PresetFile::from_args();
+    set_loop_mode_for(app, &preset);
     let (width, height) = (640, 480);
 
+    let (window_width, window_height) = config().window_size_or((width, height));
+
     let window_id = app
         .new_window()
-        .size(width, height)
+        .size(window_width, window_height)
         .view(view)
-        .key_pressed(key_pressed)
+        .event(event)
         .build()
         .expect("couldn't create a window");
 
@@ -99,29 +170,85 @@ fn model(app: &App) -> Model {
     let ids = Ids::new(ui.widget_id_generator());
     let state = State::new(width as f32, height as f32);
 
-    Model { ui, ids, state }
+    Model {
+        ui,
+        ids,
+        input: Input::new(
+            KeyBindings::default()
+                .with(Key::T, Action::custom(LONGER_LINES))
+                .with(Key::H, Action::custom(SHORTER_LINES))
+                .load_overrides(),
+        ),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset,
+        state,
+    }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    handle_actions(app, model);
     update_ui(model);
+
+    if let Some(params) = model.preset.as_mut().and_then(|preset| preset.poll()) {
+        model.state.params = params;
+        model.state.should_rebuild = true;
+    }
+
     model.state.update();
+
+    keep_updating_while(
+        app,
+        model.preset.is_some() || model.state.generator.is_running(),
+    );
 }
 
-fn key_pressed(_app: &App, model: &mut Model, key: Key) {
-    match key {
-        Key::T => {
-            if model.state.min_line_length < 100.0 {
-                model.state.min_line_length += 5.0;
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
+}
+
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => model.state.should_rebuild = true,
+            Action::NewSeed => {
+                model.state.params.seed = Seeds::random_master(0..=100);
                 model.state.should_rebuild = true;
             }
-        }
-        Key::H => {
-            if model.state.min_line_length > 0.0 {
-                model.state.min_line_length -= 5.0;
-                model.state.should_rebuild = true;
+            Action::ExportSvg => {
+                if let Err(err) = export_as_svg(&model.state) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) = export_as_dxf(&model.state) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => {
+                model.state.show_paper_preview = !model.state.show_paper_preview
             }
+            Action::Custom(name) if name == LONGER_LINES => {
+                if model.state.params.min_line_length < 100.0 {
+                    model.state.params.min_line_length += 5.0;
+                    model.state.should_rebuild = true;
+                }
+            }
+            Action::Custom(name) if name == SHORTER_LINES => {
+                if model.state.params.min_line_length > 0.0 {
+                    model.state.params.min_line_length -= 5.0;
+                    model.state.should_rebuild = true;
+                }
+            }
+            Action::FitToWindow => model.camera.fit(
+                Rect::from_w_h(model.state.width, model.state.height),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Export | Action::Undo | Action::Redo | Action::Custom(_) => (),
         }
-        _ => (),
     }
 }
 
@@ -152,6 +279,16 @@ fn slider(val: f32, min: f32, max: f32) -> widget::Slider<'static, f32> {
         .border(0.0)
 }
 
+fn drop_down_list(names: &[String], selected: usize) -> widget::DropDownList<'_, String> {
+    use nannou::ui::color::*;
+    widget::DropDownList::new(names, Some(selected))
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+}
+
 fn update_ui(model: &mut Model) {
     let ui = &mut model.ui.set_widgets();
     let mut should_refresh_model = false;
@@ -162,12 +299,12 @@ fn update_ui(model: &mut Model) {
         .font_size(20)
         .set(model.ids.param_title_text, ui);
 
-    for count in dialer(model.state.line_count as f32, 1.0, 512.0)
+    for count in dialer(model.state.params.line_count as f32, 1.0, 512.0)
         .down(SETTING_MARGIN)
         .label("Count")
         .set(model.ids.line_count, ui)
     {
-        model.state.line_count = count.floor() as usize;
+        model.state.params.line_count = count.floor() as usize;
         should_refresh_model = true;
     }
 
@@ -189,48 +326,78 @@ fn update_ui(model: &mut Model) {
         should_refresh_model = true;
     }
 
-    for vertical_jitter in slider(model.state.vertical_jitter, 0.001, 100.0)
+    for vertical_jitter in slider(model.state.params.vertical_jitter, 0.001, 100.0)
         .down(SETTING_MARGIN)
         .label("Jitter")
         .set(model.ids.vertical_jitter, ui)
     {
-        model.state.vertical_jitter = vertical_jitter;
+        model.state.params.vertical_jitter = vertical_jitter;
         should_refresh_model = true;
     }
 
-    for min_line_length in slider(model.state.min_line_length, 1.0, 100.0)
+    for min_line_length in slider(model.state.params.min_line_length, 1.0, 100.0)
         .down(SETTING_MARGIN)
         .label("Min Line Length")
         .set(model.ids.min_line_length, ui)
     {
-        model.state.min_line_length = min_line_length;
+        model.state.params.min_line_length = min_line_length;
         should_refresh_model = true;
     }
 
-    for noise_seed in slider(model.state.noise_seed as f32, 0.0, 100.0)
+    let noise_kinds = NoiseKind::names();
+    for kind_index in drop_down_list(&noise_kinds, model.state.params.noise.kind.index())
         .down(SETTING_MARGIN)
-        .label("Noise")
-        .set(model.ids.noise_seed, ui)
+        .label("Noise Kind")
+        .set(model.ids.noise_kind, ui)
     {
-        model.state.noise_seed = noise_seed as u64;
+        model.state.params.noise.kind = NoiseKind::ALL[kind_index];
         should_refresh_model = true;
     }
 
-    for noise_scale in slider(model.state.noise_scale as f32, 0.001, 0.01)
+    for seed in slider(model.state.params.seed as f32, 0.0, 100.0)
+        .down(SETTING_MARGIN)
+        .label("Seed")
+        .set(model.ids.seed, ui)
+    {
+        model.state.params.seed = seed as u64;
+        should_refresh_model = true;
+    }
+
+    for noise_scale in slider(model.state.params.noise.scale as f32, 0.001, 0.01)
         .down(SETTING_MARGIN)
         .label("Noise Scale")
         .set(model.ids.noise_scale, ui)
     {
-        model.state.noise_scale = noise_scale as f64;
+        model.state.params.noise.scale = noise_scale as f64;
         should_refresh_model = true;
     }
 
-    for stroke_weight in slider(model.state.stroke_weight, 1.0, 12.0)
+    for octaves in dialer(model.state.params.noise.octaves as f32, 1.0, 8.0)
         .down(SETTING_MARGIN)
-        .label("Stroke Weight (px)")
-        .set(model.ids.stroke_weight, ui)
+        .label("Noise Octaves")
+        .set(model.ids.noise_octaves, ui)
     {
-        model.state.stroke_weight = stroke_weight;
+        model.state.params.noise.octaves = octaves as u8;
+        should_refresh_model = true;
+    }
+
+    let smoothing_names = Smoothing::names();
+    for smoothing_index in drop_down_list(&smoothing_names, model.state.params.smoothing.index())
+        .down(SETTING_MARGIN)
+        .label("Smoothing")
+        .set(model.ids.smoothing, ui)
+    {
+        model.state.params.smoothing = Smoothing::ALL[smoothing_index];
+        should_refresh_model = true;
+    }
+
+    let pen_names = model.state.pens.names();
+    for pen_index in drop_down_list(&pen_names, model.state.line_pen)
+        .down(SETTING_MARGIN)
+        .label("Line Pen")
+        .set(model.ids.line_pen, ui)
+    {
+        model.state.line_pen = pen_index;
         should_refresh_model = true;
     }
 
@@ -241,10 +408,10 @@ fn update_ui(model: &mut Model) {
         .color(FILL)
         .label_color(LABEL_COLOR)
         .border(0.0)
-        .label("Toggle Viewbox")
-        .set(model.ids.toggle_viewbox, ui)
+        .label("Paper Preview")
+        .set(model.ids.toggle_paper_preview, ui)
     {
-        model.state.show_viewbox = !model.state.show_viewbox;
+        model.state.show_paper_preview = !model.state.show_paper_preview;
         should_refresh_model = true;
     }
 
@@ -263,6 +430,21 @@ fn update_ui(model: &mut Model) {
         }
     }
 
+    for _click in widget::Button::new()
+        .down(SETTING_MARGIN)
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+        .label("Export DXF")
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.state) {
+            error!("{:?}", err)
+        }
+    }
+
     if should_refresh_model {
         model.state.should_rebuild = true;
         trace!("refresh model called");
@@ -271,30 +453,38 @@ fn update_ui(model: &mut Model) {
 
 // Draw the state of your `Model` into the given `Frame` here.
 fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
+
+    let pen = model.state.pens.get(model.state.line_pen);
 
-    draw.background().color(WHITE);
+    if model.state.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
 
-    let bounding_rect = Rect::from_w_h(model.state.width, model.state.height)
-        .align_middle_x_of(app.window_rect())
-        .align_middle_y_of(app.window_rect());
+        let drawing = build_drawing_from_state(&model.state);
+        model.paper.draw(&draw, &drawing, |_| pen);
+    } else {
+        draw.background().color(WHITE);
 
-    if model.state.show_viewbox {
-        draw.rect()
-            .xy(bounding_rect.xy())
-            .wh(bounding_rect.wh())
-            .stroke(RED)
-            .stroke_weight(2.0)
-            .no_fill();
+        let bounding_rect = Rect::from_w_h(model.state.width, model.state.height)
+            .align_middle_x_of(app.window_rect())
+            .align_middle_y_of(app.window_rect());
+        let line_draw = draw.translate(bounding_rect.bottom_left().extend(0.0));
+
+        model
+            .state
+            .lines
+            .iter()
+            .for_each(|line| line.draw(&line_draw, pen));
     }
 
-    let line_draw = draw.translate(bounding_rect.bottom_left().extend(0.0));
+    if let Some(fraction_done) = model.state.generator.progress() {
+        draw_progress_bar(&window_draw, app.window_rect(), fraction_done);
+    }
 
-    model
-        .state
-        .lines
-        .iter()
-        .for_each(|line| line.draw(&line_draw, model.state.stroke_weight));
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
+    }
 
     // Write the result of our drawing to the window's frame.
     draw.to_frame(app, &frame).unwrap();
@@ -306,10 +496,8 @@ fn view(app: &App, model: &Model, frame: Frame) {
 fn build_svg_document_from_state(state: &State) -> svg::Document {
     let doc = svg::Document::new().set("viewBox", (0, 0, state.width, state.height));
 
-    let mut group = svg::node::element::Group::new()
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", state.stroke_weight);
+    let line_pen = state.pens.get(state.line_pen);
+    let mut group = line_pen.style_svg_group(svg::node::element::Group::new());
 
     for line in state.lines.iter() {
         let path = line.as_svg();
@@ -319,48 +507,66 @@ fn build_svg_document_from_state(state: &State) -> svg::Document {
 
     let bounding_rect = svg::node::element::Rectangle::new()
         .set("width", state.width)
-        .set("height", state.height)
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", 1);
+        .set("height", state.height);
+    let border = line_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
+
+    doc.add(group).add(border)
+}
+
+fn build_drawing_from_state(state: &State) -> Drawing {
+    let mut drawing = Drawing::new(state.width, state.height);
+    let border = drawing.border();
+
+    let lines = drawing.layer_mut("lines");
+    for line in state.lines.iter() {
+        lines.push(line.shape());
+    }
 
-    doc.add(group).add(bounding_rect)
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(state: &State) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let drawing = build_drawing_from_state(state);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(state.params.seed),
+        &state.params,
+        &drawing,
+    )?;
+
+    Ok(())
 }
 
 fn export_as_svg(state: &State) -> Result<(), anyhow::Error> {
     info!("exporting image as SVG...");
     let document = build_svg_document_from_state(state);
-    let base_path = std::env::var("SVG_EXPORT_DIRECTORY").context("setting 'SVG_EXPORT_DIRECTORY' is required, please set it to the directory you wish to export SVGs to")?;
-    let current_date = Local::today().format("%Y-%m-%d");
-    let svg_filename = format!("{}-plotling.svg", &current_date);
-    let mut svg_filepath: PathBuf = [base_path, svg_filename].iter().collect();
-
-    // I don't want to silently overwrite anything so I look for an unused filename,
-    // incrementing the counter until I find an unused number
-    // I could have also used a random string/number, I just like this better
-    if svg_filepath.exists() {
-        let mut counter = 1;
-
-        while svg_filepath.exists() {
-            if counter > 100 {
-                debug!(
-                    "export_as_svg counter has reached {}, you're not in an infinite loop are you?",
-                    counter
-                );
-            }
-
-            let _ = svg_filepath.pop();
-            let svg_filename = format!("{}-plotling-{}.svg", &current_date, &counter);
-            svg_filepath.push(svg_filename);
-            counter += 1;
-        }
-    }
+    let svg_filepath = unused_export_path("svg")?;
 
     svg::save(&svg_filepath, &document)?;
     info!(
         "SVG successfully exported to {}",
         &svg_filepath.to_string_lossy()
     );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(state.params.seed),
+        &state.params,
+        &build_drawing_from_state(state),
+    )?;
 
     Ok(())
 }
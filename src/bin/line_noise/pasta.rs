@@ -1,84 +1,115 @@
-use super::State;
+use super::Params;
+use lib_plotings::geometry::Shape;
+use lib_plotings::noise::{NoiseField, NoiseParams};
+use lib_plotings::pen::PenProfile;
+use lib_plotings::seed::Seeds;
+use lib_plotings::worker::Progress;
 use nannou::prelude::*;
-use noise::{NoiseFn, SuperSimplex};
-use rand::prelude::StdRng;
-use rand::{Rng, SeedableRng};
+use rand::Rng;
 use svg::node::element::Polyline;
 
+/// How far apart the points on smoothed lines are
+const SMOOTHED_POINT_SPACING: f32 = 2.0;
+
 pub struct Line {
-    start: Vec2,
-    end: Vec2,
+    points: Vec<Vec2>,
 }
 
 impl Line {
-    pub fn new(start: Vec2, end: Vec2) -> Self {
-        Self { start, end }
+    pub fn new(points: Vec<Vec2>) -> Self {
+        Self { points }
     }
 
-    pub fn draw(&self, draw: &Draw, stroke_weight: f32) {
+    pub fn draw(&self, draw: &Draw, pen: &PenProfile) {
         draw.polyline()
-            .weight(stroke_weight)
+            .weight(pen.stroke_weight())
+            .color(pen.nannou_colour())
             .caps_round()
-            .points(vec![self.start.clone(), self.end.clone()]);
+            .points(self.points.iter().cloned());
     }
 
     pub fn as_svg(&self) -> Polyline {
-        let points = format!(
-            "{:.2},{:.2} {:.2},{:.2}",
-            self.start.x, self.start.y, self.end.x, self.end.y
-        );
+        let points = self
+            .points
+            .iter()
+            .map(|xy| format!("{:.2},{:.2}", xy.x, xy.y))
+            .collect::<Vec<_>>()
+            .join(" ");
 
         Polyline::new().set("points", points)
     }
+
+    pub fn shape(&self) -> Shape {
+        Shape::Polyline(self.points.clone())
+    }
 }
 
 pub type Lines = Vec<Line>;
 
-pub fn new_lines_from_noise(params: &State) -> Lines {
-    let noise_fn = SuperSimplex::new();
-    let mut rng: StdRng = SeedableRng::seed_from_u64(params.noise_seed);
-    let horizontal_line_spacing = params.width as f64 / params.line_count as f64;
-    (0..=params.line_count)
-        .flat_map(|index| {
-            let mut subline = Vec::new();
-            let mut y = 0.0;
-            let x = index as f64 * horizontal_line_spacing;
-            let mut is_drawing = false;
-            let mut line_start = None;
-            let y_jitter = rng.gen_range(-params.vertical_jitter..params.vertical_jitter);
-
-            loop {
-                if y > params.height {
-                    break;
-                }
+/// Fill the page with lines, `None` if it's cancelled before they're all done
+pub fn new_lines_from_noise(
+    params: &Params,
+    width: f32,
+    height: f32,
+    progress: &Progress,
+) -> Option<Lines> {
+    let seeds = Seeds::new(params.seed);
+    let noise_field = NoiseField::new(NoiseParams {
+        seed: seeds.noise_seed("breaks"),
+        ..params.noise
+    });
+    let mut jitter_rng = seeds.rng("jitter");
+    let mut dice = seeds.rng("dice");
+    let horizontal_line_spacing = width as f64 / params.line_count as f64;
+    let mut lines = Vec::new();
 
-                let dice_roll: f64 = rng.gen();
-                let chance_of_state_change = {
-                    let (x, y) = (x * params.noise_scale, y as f64 * params.noise_scale);
-                    noise_fn.get([params.noise_seed as f64, x, y])
-                };
-
-                if is_drawing {
-                    // roll dice to see if we should stop drawing
-                    if dice_roll > chance_of_state_change {
-                        is_drawing = false;
-                        let start = line_start.take().expect("No line start was set?");
-                        let end = Vec2::new(x as f32, y + y_jitter);
-
-                        subline.push(Line::new(start, end))
-                    }
-                } else {
-                    // roll dice to see if we should start drawing
-                    if dice_roll < chance_of_state_change {
-                        is_drawing = true;
-                        line_start = Some(Vec2::new(x as f32, y + y_jitter));
-                    }
-                }
+    for index in 0..=params.line_count {
+        if progress.is_cancelled() {
+            return None;
+        }
+
+        let mut y = 0.0;
+        let x = index as f64 * horizontal_line_spacing;
+        let mut is_drawing = false;
+        let mut line_points = Vec::new();
+        let y_jitter = jitter_rng.gen_range(-params.vertical_jitter..params.vertical_jitter);
+
+        loop {
+            if y > height {
+                break;
+            }
 
-                y += params.min_line_length;
+            let dice_roll: f64 = dice.gen();
+            let chance_of_state_change = noise_field.get(vec2(x as f32, y)) as f64;
+            let xy = Vec2::new(x as f32, y + y_jitter);
+
+            if is_drawing {
+                line_points.push(xy);
+
+                // roll dice to see if we should stop drawing
+                if dice_roll > chance_of_state_change {
+                    is_drawing = false;
+                    let points =
+                        params
+                            .smoothing
+                            .smooth(&line_points, false, SMOOTHED_POINT_SPACING);
+
+                    lines.push(Line::new(points));
+                    line_points.clear();
+                }
+            } else {
+                // roll dice to see if we should start drawing
+                if dice_roll < chance_of_state_change {
+                    is_drawing = true;
+                    line_points.push(xy);
+                }
             }
 
-            subline.into_iter()
-        })
-        .collect()
+            y += params.min_line_length;
+        }
+
+        progress.set(index + 1, params.line_count + 1);
+    }
+
+    Some(lines)
 }
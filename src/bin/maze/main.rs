@@ -2,16 +2,23 @@ mod maze;
 mod params;
 mod wall;
 
-use anyhow::Context;
-use chrono::Local;
-use lib_plotings::{map_t_of_range_a_to_range_b, MouseButtonState};
-use log::{debug, error, info, trace, warn};
-use maze::Maze;
+use lib_plotings::camera::{is_mouse_over_ui, Camera};
+use lib_plotings::config::config;
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::Drawing;
+use lib_plotings::history::{history_path_for, History};
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::pen::{PenLibrary, PenProfile, PIXELS_PER_MM};
+use lib_plotings::preset::{draw_error_banner, PresetFile};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::seed::Seeds;
+use log::{error, info, trace, warn};
+use maze::{Maze, MazeEdit};
 use nannou::{prelude::*, ui::prelude::*};
 use params::MazeParams;
-use rand::{prelude::StdRng, SeedableRng};
-use std::path::PathBuf;
-use std::{cell::RefCell, mem};
+use std::mem;
 
 fn main() {
     let res = dotenv::dotenv();
@@ -26,34 +33,40 @@ fn main() {
 pub struct Model {
     ui: Ui,
     ids: Ids,
-    rng: RefCell<StdRng>,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<MazeParams>>,
     pub params: MazeParams,
     pub maze: Maze,
-    pub show_viewbox: bool,
-    pub mouse_xy: Point2,
-    pub mouse_button_l: MouseButtonState,
-    mouse_button_l_secret_state: bool,
+    /// Walls flipped by hand and restarts, for undo and redo
+    pub history: History<MazeEdit>,
+    pub pens: PenLibrary,
+    pub wall_pen: usize,
+    pub show_paper_preview: bool,
 }
 
 widget_ids! {
     struct Ids {
         columns,
+        export_dxf,
         export_svg,
         grid_cell_size,
         noise_seed,
         rows,
-        toggle_viewbox,
+        seed,
+        toggle_paper_preview,
+        wall_pen,
     }
 }
 
 fn model(app: &App) -> Model {
+    let (window_width, window_height) = config().window_size_or((1920, 1080));
     let window_id = app
         .new_window()
-        .size(1920, 1080)
+        .size(window_width, window_height)
         .view(view)
-        .mouse_moved(mouse_moved)
-        .mouse_pressed(mouse_pressed)
-        .mouse_released(mouse_released)
+        .event(event)
         .build()
         .expect("couldn't create a window");
 
@@ -62,81 +75,177 @@ fn model(app: &App) -> Model {
 
     // Generate some ids for our widgets.
     let ids = Ids::new(ui.widget_id_generator());
-    let params = MazeParams::default();
-    let mut rng: StdRng = SeedableRng::seed_from_u64(params.rng_seed);
-    let maze = Maze::new(&params, &mut rng);
-    let rng = RefCell::new(rng);
+    let mut preset = PresetFile::from_args();
+    let params = preset
+        .as_mut()
+        .and_then(|preset| preset.poll())
+        .unwrap_or_default();
+    let mut maze = Maze::new(&params);
+    let history = match &preset {
+        Some(preset) => load_history_beside(preset, &mut maze),
+        None => History::unlimited(),
+    };
+    let pens = PenLibrary::load();
+    let wall_pen = pens.index_of_or_first("0.3mm Marker");
 
     Model {
         ui,
         ids,
-        rng,
+        input: Input::new(KeyBindings::load()),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset,
+        params: MazeParams {
+            rng_seed: maze.seed(),
+            ..params
+        },
         maze,
-        params,
-        show_viewbox: false,
-        mouse_xy: Default::default(),
-        mouse_button_l: MouseButtonState::Released,
-        mouse_button_l_secret_state: false,
+        history,
+        pens,
+        wall_pen,
+        show_paper_preview: false,
     }
 }
 
-pub fn mouse_moved(_app: &App, model: &mut Model, position: Point2) {
-    model.mouse_xy = position;
-}
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    if model.input.button(MouseButton::Left).is_just_released() {
+        model.history.seal();
+    }
+    handle_actions(app, model);
+    update_ui(model);
+    reload_preset(model);
 
-pub fn mouse_pressed(_app: &App, model: &mut Model, button: MouseButton) {
-    match button {
-        MouseButton::Left => {
-            model.mouse_button_l_secret_state = true;
-        }
-        _ => trace!("unhandled mouse press event: {:?}", button),
+    // clicks on the UI aren't meant for the drawing underneath it
+    let mouse_xy = if is_mouse_over_ui(&model.ui) {
+        None
+    } else {
+        Some(model.camera.screen_to_world(model.input.mouse_xy()))
+    };
+    let edit = model.maze.update(
+        &model.params,
+        mouse_xy,
+        model.input.button(MouseButton::Left),
+    );
+    if let Some(edit) = edit {
+        edit_maze(model, edit);
     }
 }
 
-pub fn mouse_released(_app: &App, model: &mut Model, button: MouseButton) {
-    match button {
-        MouseButton::Left => {
-            model.mouse_button_l_secret_state = false;
+/// Make `edit` in a way that can be undone
+fn edit_maze(model: &mut Model, edit: MazeEdit) {
+    model.history.apply(edit, &mut model.maze);
+    history_changed(model);
+}
+
+/// Make `edit` so that it's undone on its own, not along with the seed dialer turned after it
+fn edit_maze_alone(model: &mut Model, edit: MazeEdit) {
+    model.history.apply_alone(edit, &mut model.maze);
+    history_changed(model);
+}
+
+/// Keep the seed in step with the maze and, when there's a preset, the history beside it
+fn history_changed(model: &mut Model) {
+    model.params.rng_seed = model.maze.seed();
+
+    if let Some(preset) = &model.preset {
+        if let Err(err) = model.history.save(history_path_for(preset.path())) {
+            warn!("{:?}", err);
         }
-        _ => trace!("unhandled mouse release event: {:?}", button),
     }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    update_ui(model);
+/// The history saved beside `preset`, replayed onto `maze`, so that walls flipped by hand
+/// survive closing the window. It keeps every edit, however many walls get flipped, so it always
+/// replays from the preset's maze.
+fn load_history_beside(preset: &PresetFile<MazeParams>, maze: &mut Maze) -> History<MazeEdit> {
+    let history_path = history_path_for(preset.path());
+    if !history_path.exists() {
+        return History::unlimited();
+    }
 
-    model
-        .mouse_button_l
-        .update(model.mouse_button_l_secret_state);
+    match History::load(&history_path) {
+        Ok(mut history) => {
+            // histories saved before they were unlimited may still have one
+            history.remove_limit();
+            history.replay(maze);
+            history
+        }
+        Err(err) => {
+            warn!("{:?}", err);
+            History::unlimited()
+        }
+    }
+}
 
-    let mouse_tile_position = screen_xy_to_tile_position(&model.mouse_xy, &model.params);
+/// Take on the preset's params whenever it's saved. A new seed restarts the maze in a way that
+/// can be undone, but a new grid size can't keep the walls and starts the history again.
+fn reload_preset(model: &mut Model) {
+    let params = match model.preset.as_mut().and_then(|preset| preset.poll()) {
+        Some(params) => params,
+        None => return,
+    };
+    let is_same_grid = params.columns == model.params.columns && params.rows == model.params.rows;
+    let seed = params.rng_seed;
+    model.params = params;
+
+    if !is_same_grid {
+        model.maze = Maze::new(&model.params);
+        model.history.clear();
+        history_changed(model);
+    } else if seed != model.maze.seed() {
+        let edit = MazeEdit::restart(&model.maze, seed);
+        edit_maze_alone(model, edit);
+    }
+}
 
-    model
-        .maze
-        .update(&model.params, mouse_tile_position, model.mouse_button_l)
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
 }
 
-pub fn screen_xy_to_tile_position(xy: &Point2, params: &MazeParams) -> Option<Point2> {
-    let rect = nannou::geom::Rect::from_x_y_w_h(0.0, 0.0, params.width(), params.height());
-
-    if rect.contains(*xy) {
-        let x = map_t_of_range_a_to_range_b(
-            xy.x - params.grid_cell_width as f32 / 2.0,
-            rect.left()..rect.right(),
-            0.0..(params.columns as f32),
-        )
-        .round();
-        let y = map_t_of_range_a_to_range_b(
-            xy.y - params.grid_cell_height as f32 / 2.0,
-            rect.bottom()..rect.top(),
-            0.0..(params.rows as f32),
-        )
-        .round();
-        let xy = pt2(x, y);
-
-        Some(xy)
-    } else {
-        None
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => {
+                let edit = MazeEdit::restart(&model.maze, model.params.rng_seed);
+                edit_maze_alone(model, edit);
+            }
+            Action::NewSeed => {
+                let edit = MazeEdit::restart(&model.maze, Seeds::random_master(0..=9999));
+                edit_maze_alone(model, edit);
+            }
+            Action::ExportSvg => {
+                let wall_pen = model.pens.get(model.wall_pen);
+                if let Err(err) =
+                    export_as_svg(&model.params, &model.maze, &model.history, wall_pen)
+                {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) = export_as_dxf(&model.params, &model.maze) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => model.show_paper_preview = !model.show_paper_preview,
+            Action::FitToWindow => model.camera.fit(
+                Rect::from_w_h(model.params.width(), model.params.height()),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Undo => {
+                if model.history.undo(&mut model.maze) {
+                    history_changed(model);
+                }
+            }
+            Action::Redo => {
+                if model.history.redo(&mut model.maze) {
+                    history_changed(model);
+                }
+            }
+            Action::Export | Action::Custom(_) => (),
+        }
     }
 }
 
@@ -144,7 +253,7 @@ fn update_ui(model: &mut Model) {
     // Calling `set_widgets` allows us to instantiate some widgets.
     let mut ui_cell = model.ui.set_widgets();
     let ui = &mut ui_cell;
-    let should_refresh_maze = false;
+    let mut should_refresh_maze = false;
 
     // fn dialer(val: f32, min: f32, max: f32) -> widget::NumberDialer<'static, f32> {
     //     widget::NumberDialer::new(val, min, max, 0)
@@ -268,10 +377,38 @@ fn update_ui(model: &mut Model) {
         .rgb(0.3, 0.3, 0.3)
         .label_rgb(1.0, 1.0, 1.0)
         .border(0.0)
-        .label("Toggle Viewbox")
-        .set(model.ids.toggle_viewbox, ui)
+        .label("Paper Preview")
+        .set(model.ids.toggle_paper_preview, ui)
+    {
+        model.show_paper_preview = !model.show_paper_preview;
+    }
+
+    if let Some(seed) = widget::NumberDialer::new(model.params.rng_seed as f32, 0.0, 9999.0, 0)
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Seed")
+        .set(model.ids.seed, ui)
     {
-        model.show_viewbox = !model.show_viewbox;
+        model.params.rng_seed = seed as u64;
+        should_refresh_maze = true;
+    }
+
+    let pen_names = model.pens.names();
+    for pen_index in widget::DropDownList::new(&pen_names, Some(model.wall_pen))
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Wall Pen")
+        .set(model.ids.wall_pen, ui)
+    {
+        model.wall_pen = pen_index;
     }
 
     for _click in widget::Button::new()
@@ -284,20 +421,32 @@ fn update_ui(model: &mut Model) {
         .label("Export SVG")
         .set(model.ids.export_svg, ui)
     {
-        if let Err(err) = export_as_svg(&model.params, &model.maze) {
+        let wall_pen = model.pens.get(model.wall_pen);
+        if let Err(err) = export_as_svg(&model.params, &model.maze, &model.history, wall_pen) {
             error!("{}", err)
         }
     }
 
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Export DXF")
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.params, &model.maze) {
+            error!("{:?}", err)
+        }
+    }
+
     mem::drop(ui_cell);
 
     if should_refresh_maze {
-        let rng = &mut *model.rng.borrow_mut();
-        let maze = Maze::new(&model.params, rng);
-        // TODO do I need to manually drop here or is the compiler smart enough?
-        mem::drop(rng);
-
-        model.maze = maze;
+        let edit = MazeEdit::restart(&model.maze, model.params.rng_seed);
+        edit_maze(model, edit);
         trace!(
             "should_refresh_maze=true, creating maze with {} lines",
             model.maze.walls().len()
@@ -308,21 +457,24 @@ fn update_ui(model: &mut Model) {
 // Draw the state of your `Model` into the given `Frame` here.
 fn view(app: &App, model: &Model, frame: Frame) {
     // Begin drawing
-    let draw = app.draw();
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
 
-    draw.background().color(WHITE);
+    let wall_pen = model.pens.get(model.wall_pen);
 
-    model.maze.draw(&draw, &model.params);
+    if model.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
 
-    if model.show_viewbox {
-        draw.rect()
-            // we want the box centered on the screen, nannou places rectangle from their center
-            .x_y(0.0, 0.0)
-            .w_h(model.params.width(), model.params.height())
-            .stroke(RED)
-            .stroke_weight(2.0)
-            .no_fill()
-            .finish();
+        let drawing = build_drawing_from_model(&model.params, &model.maze);
+        model.paper.draw(&draw, &drawing, |_| wall_pen);
+    } else {
+        draw.background().color(WHITE);
+
+        model.maze.draw(&draw, &model.params, wall_pen);
+    }
+
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
     }
 
     // Write the result of our drawing to the window's frame.
@@ -332,56 +484,85 @@ fn view(app: &App, model: &Model, frame: Frame) {
     model.ui.draw_to_frame(app, &frame).unwrap();
 }
 
-fn build_svg_document_from_model(params: &MazeParams, maze: &Maze) -> svg::Document {
+fn build_svg_document_from_model(
+    params: &MazeParams,
+    maze: &Maze,
+    wall_pen: &PenProfile,
+) -> svg::Document {
     let viewbox_width = params.grid_cell_width * params.columns;
     let viewbox_height = params.grid_cell_height * params.rows;
 
     let doc = svg::Document::new().set("viewBox", (0, 0, viewbox_width, viewbox_height));
-    let maze = maze.svg(params);
+    let maze = maze.svg(params, wall_pen);
     let bounding_rect = svg::node::element::Rectangle::new()
         .set("width", viewbox_width)
-        .set("height", viewbox_height)
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", "2mm");
+        .set("height", viewbox_height);
+    let border = wall_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
 
-    doc.add(maze).add(bounding_rect)
+    doc.add(maze).add(border)
 }
 
-fn export_as_svg(params: &MazeParams, maze: &Maze) -> Result<(), anyhow::Error> {
-    info!("exporting image as SVG...");
-    let document = build_svg_document_from_model(params, maze);
-    let base_path = std::env::var("SVG_EXPORT_DIRECTORY").context("setting 'SVG_EXPORT_DIRECTORY' is required, please set it to the directory you wish to export SVGs to")?;
-    let current_date = Local::today().format("%Y-%m-%d");
-    let svg_filename = format!("{}-plotling.svg", &current_date);
-    let mut svg_filepath: PathBuf = [base_path, svg_filename].iter().collect();
-
-    // I don't want to silently overwrite anything so I look for an unused filename,
-    // incrementing the counter until I find an unused number
-    // I could have also used a random string/number, I just like this better
-    if svg_filepath.exists() {
-        let mut counter = 1;
-
-        while svg_filepath.exists() {
-            if counter > 100 {
-                debug!(
-                    "export_as_svg counter has reached {}, you're not in an infinite loop are you?",
-                    counter
-                );
-            }
+fn build_drawing_from_model(params: &MazeParams, maze: &Maze) -> Drawing {
+    let mut drawing = Drawing::new(params.width(), params.height());
+    let border = drawing.border();
 
-            let _ = svg_filepath.pop();
-            let svg_filename = format!("{}-plotling-{}.svg", &current_date, &counter);
-            svg_filepath.push(svg_filename);
-            counter += 1;
-        }
+    let walls = drawing.layer_mut("walls");
+    for shape in maze.shapes(params) {
+        walls.push(shape);
     }
 
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(params: &MazeParams, maze: &Maze) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let drawing = build_drawing_from_model(params, maze);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(params.rng_seed),
+        params,
+        &drawing,
+    )?;
+
+    Ok(())
+}
+
+/// The maze's history is saved next to the SVG so a hand-curated maze can be made again
+fn export_as_svg(
+    params: &MazeParams,
+    maze: &Maze,
+    history: &History<MazeEdit>,
+    wall_pen: &PenProfile,
+) -> Result<(), anyhow::Error> {
+    info!("exporting image as SVG...");
+    let document = build_svg_document_from_model(params, maze, wall_pen);
+    let svg_filepath = unused_export_path("svg")?;
+
     svg::save(&svg_filepath, &document)?;
+    history.save(history_path_for(&svg_filepath))?;
     info!(
         "SVG successfully exported to {}",
         &svg_filepath.to_string_lossy()
     );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(params.rng_seed),
+        params,
+        &build_drawing_from_model(params, maze),
+    )?;
 
     Ok(())
 }
@@ -1,76 +1,100 @@
 use crate::params::MazeParams;
 use crate::wall::Wall;
-use lib_plotings::MouseButtonState;
+use lib_plotings::geometry::Shape;
+use lib_plotings::grid::Grid;
+use lib_plotings::history::Edit;
+use lib_plotings::input::ButtonState;
+use lib_plotings::pen::PenProfile;
+use lib_plotings::seed::Seeds;
 use log::trace;
 use nannou::prelude::*;
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 
+#[derive(Default)]
 pub struct Maze {
-    walls: Vec<Wall>,
+    walls: Grid<Wall>,
+    /// The seed the walls were made from, before any were flipped
+    seed: u64,
+    /// The cell under the mouse
+    hovered: Option<(usize, usize)>,
 }
 
 impl Maze {
-    pub fn new(params: &MazeParams, rng: &mut impl Rng) -> Self {
-        let walls = (0..params.rows)
-            .flat_map(|y| (0..params.columns).map(move |x| pt2(x as f32, y as f32)))
-            .map(|xy| Wall::new(xy, rng))
-            .collect();
-
-        Self { walls }
+    /// The same params, seed included, always give the same maze
+    pub fn new(params: &MazeParams) -> Self {
+        Self {
+            walls: new_walls(params.columns, params.rows, params.rng_seed),
+            seed: params.rng_seed,
+            hovered: None,
+        }
     }
 
+    /// `mouse_xy` is in window coordinates. Returns the edit a click asks for, it's up to the
+    /// caller to make it so that it can be undone.
     pub fn update(
         &mut self,
-        _params: &MazeParams,
-        mouse_tile_position: Option<Point2>,
-        mouse_button_state: MouseButtonState,
-    ) {
-        if let Some(xy) = mouse_tile_position {
-            self.walls
-                .iter_mut()
-                .for_each(|wall| wall.set_is_hovered(wall.xy() == &xy));
-
-            if mouse_button_state.is_just_pressed() {
-                trace!("Left mouse button click at {:?}", mouse_tile_position);
-                self.flip_wall_at(&xy);
+        params: &MazeParams,
+        mouse_xy: Option<Point2>,
+        mouse_button_state: ButtonState,
+    ) -> Option<MazeEdit> {
+        self.hovered = mouse_xy.and_then(|xy| self.walls.cell_at(&params.window_layout(), xy));
+
+        match self.hovered {
+            Some((x, y)) if mouse_button_state.is_just_pressed() => {
+                trace!("Left mouse button click on cell {:?}", (x, y));
+                Some(MazeEdit::FlipWall { x, y })
             }
+            _ => None,
         }
     }
 
-    pub fn new_walls_from_rng(&mut self, params: &MazeParams, rng: &mut impl Rng) {
-        self.walls = (0..params.rows)
-            .flat_map(|y| (0..params.columns).map(move |x| pt2(x as f32, y as f32)))
-            .map(|xy| Wall::new(xy, rng))
-            .collect();
+    /// Throw away any flipped walls and start again from `seed`
+    pub fn reset_walls(&mut self, seed: u64) {
+        self.walls = new_walls(self.walls.columns(), self.walls.rows(), seed);
+        self.seed = seed;
     }
 
-    pub fn flip_wall_at(&mut self, xy: &Point2) {
-        if let Some(wall) = self.walls.iter_mut().filter(|wall| wall.xy() == xy).next() {
+    pub fn flip_wall_at(&mut self, x: usize, y: usize) {
+        if let Some(wall) = self.walls.get_mut(x as isize, y as isize) {
             wall.flip();
         }
     }
 
-    pub fn draw(&self, draw: &Draw, params: &MazeParams) {
-        let maze_width = params.width();
-        let maze_height = params.height();
-        let centered_origin = vec3(
-            maze_width * -0.5,
-            maze_height * -0.5 + params.grid_cell_height as f32,
-            0.0,
-        );
-        let draw = draw.translate(centered_origin);
-
-        self.walls.iter().for_each(|wall| wall.draw(&draw, &params));
+    /// The cells whose walls have been flipped since they were made from the seed
+    pub fn flipped_walls(&self) -> Vec<(usize, usize)> {
+        let unflipped = new_walls(self.walls.columns(), self.walls.rows(), self.seed);
+
+        self.walls
+            .iter()
+            .filter(|&((x, y), wall)| {
+                unflipped
+                    .get(x as isize, y as isize)
+                    .map_or(false, |original| original.kind() != wall.kind())
+            })
+            .map(|(xy, _)| xy)
+            .collect()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn draw(&self, draw: &Draw, params: &MazeParams, pen: &PenProfile) {
+        let layout = params.window_layout();
+
+        for ((x, y), wall) in self.walls.iter() {
+            let is_hovered = self.hovered == Some((x, y));
+
+            wall.draw(draw, layout.cell_rect(x, y), is_hovered, pen);
+        }
     }
 
-    pub fn svg(&self, params: &MazeParams) -> svg::node::element::Group {
-        let mut group = svg::node::element::Group::new()
-            .set("fill", "none")
-            .set("stroke", "black")
-            .set("stroke-width", "0.3mm");
+    pub fn svg(&self, params: &MazeParams, pen: &PenProfile) -> svg::node::element::Group {
+        let mut group = pen.style_svg_group(svg::node::element::Group::new());
+        let layout = params.drawing_layout();
 
-        for wall in self.walls().iter() {
-            let line = wall.svg(params);
+        for ((x, y), wall) in self.walls.iter() {
+            let line = wall.svg(layout.cell_rect(x, y));
 
             group = group.add(line);
         }
@@ -78,13 +102,92 @@ impl Maze {
         group
     }
 
-    pub fn walls(&self) -> &[Wall] {
+    pub fn shapes(&self, params: &MazeParams) -> Vec<Shape> {
+        let layout = params.drawing_layout();
+
+        self.walls
+            .iter()
+            .map(|((x, y), wall)| wall.shape(layout.cell_rect(x, y)))
+            .collect()
+    }
+
+    pub fn walls(&self) -> &Grid<Wall> {
         &self.walls
     }
 }
 
-impl Default for Maze {
-    fn default() -> Self {
-        Self { walls: Vec::new() }
+fn new_walls(columns: usize, rows: usize, seed: u64) -> Grid<Wall> {
+    let mut rng = Seeds::new(seed).rng("walls");
+
+    Grid::from_fn(columns, rows, |_, _| Wall::new(&mut rng))
+}
+
+/// The changes to a maze that can be undone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MazeEdit {
+    FlipWall {
+        x: usize,
+        y: usize,
+    },
+    /// Make the walls again from a seed. The walls flipped by hand before are kept so that
+    /// undoing brings them back.
+    Restart {
+        seed_before: u64,
+        seed_after: u64,
+        flipped_before: Vec<(usize, usize)>,
+    },
+}
+
+impl MazeEdit {
+    /// The edit for throwing away `maze`'s flipped walls and starting again from `seed`
+    pub fn restart(maze: &Maze, seed: u64) -> Self {
+        MazeEdit::Restart {
+            seed_before: maze.seed(),
+            seed_after: seed,
+            flipped_before: maze.flipped_walls(),
+        }
+    }
+}
+
+impl Edit<Maze> for MazeEdit {
+    fn apply(&self, maze: &mut Maze) {
+        match self {
+            MazeEdit::FlipWall { x, y } => maze.flip_wall_at(*x, *y),
+            MazeEdit::Restart { seed_after, .. } => maze.reset_walls(*seed_after),
+        }
+    }
+
+    fn revert(&self, maze: &mut Maze) {
+        match self {
+            MazeEdit::FlipWall { x, y } => maze.flip_wall_at(*x, *y),
+            MazeEdit::Restart {
+                seed_before,
+                flipped_before,
+                ..
+            } => {
+                maze.reset_walls(*seed_before);
+                for (x, y) in flipped_before.iter() {
+                    maze.flip_wall_at(*x, *y);
+                }
+            }
+        }
+    }
+
+    /// Turning the seed dialer makes a restart per step, they're undone together
+    fn merge(&mut self, next: &Self) -> bool {
+        match (self, next) {
+            (
+                MazeEdit::Restart { seed_after, .. },
+                MazeEdit::Restart {
+                    seed_before,
+                    seed_after: next_seed_after,
+                    flipped_before,
+                },
+            ) if seed_before == seed_after && flipped_before.is_empty() => {
+                *seed_after = *next_seed_after;
+                true
+            }
+            _ => false,
+        }
     }
 }
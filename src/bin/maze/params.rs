@@ -1,3 +1,10 @@
+use lib_plotings::grid::CellLayout;
+use lib_plotings::preset::{check_range, Validate};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MazeParams {
     pub grid_cell_width: usize,
     pub grid_cell_height: usize,
@@ -14,6 +21,20 @@ impl MazeParams {
     pub fn height(&self) -> f32 {
         (self.grid_cell_height * self.rows) as f32
     }
+
+    fn cell_wh(&self) -> Vec2 {
+        vec2(self.grid_cell_width as f32, self.grid_cell_height as f32)
+    }
+
+    /// Where the cells are on screen, centered in the window with the first row at the bottom
+    pub fn window_layout(&self) -> CellLayout {
+        CellLayout::new(pt2(self.width(), self.height()) * -0.5, self.cell_wh())
+    }
+
+    /// Where the cells are in exported drawings, with the first row at the top
+    pub fn drawing_layout(&self) -> CellLayout {
+        CellLayout::new(pt2(0.0, 0.0), self.cell_wh())
+    }
 }
 
 impl Default for MazeParams {
@@ -27,3 +48,12 @@ impl Default for MazeParams {
         }
     }
 }
+
+impl Validate for MazeParams {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        check_range("grid_cell_width", self.grid_cell_width, 1..=1000)?;
+        check_range("grid_cell_height", self.grid_cell_height, 1..=1000)?;
+        check_range("columns", self.columns, 1..=1000)?;
+        check_range("rows", self.rows, 1..=1000)
+    }
+}
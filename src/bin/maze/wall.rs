@@ -1,3 +1,5 @@
+use lib_plotings::geometry::Shape;
+use lib_plotings::pen::PenProfile;
 use nannou::prelude::*;
 use rand::{
     distributions::{Distribution, Standard},
@@ -5,27 +7,15 @@ use rand::{
 };
 use svg::node::element::Line;
 
-use crate::params::MazeParams;
-
 pub struct Wall {
-    xy: Point2,
     kind: WallKind,
-    is_hovered: bool,
 }
 
 impl Wall {
-    pub fn new(xy: Point2, rng: &mut impl Rng) -> Self {
+    pub fn new(rng: &mut impl Rng) -> Self {
         let kind = rng.gen();
 
-        Self {
-            xy,
-            kind,
-            is_hovered: false,
-        }
-    }
-
-    pub fn xy(&self) -> &Point2 {
-        &self.xy
+        Self { kind }
     }
 
     pub fn kind(&self) -> WallKind {
@@ -39,72 +29,52 @@ impl Wall {
         };
     }
 
-    pub fn set_is_hovered(&mut self, is_hovered: bool) {
-        self.is_hovered = is_hovered;
-    }
-
-    /// Get a reference to the wall's is hovered.
-    pub fn is_hovered(&self) -> bool {
-        self.is_hovered
-    }
-
-    pub fn draw(&self, draw: &Draw, params: &MazeParams) {
-        let gcw = params.grid_cell_width as f32;
-        let gch = params.grid_cell_height as f32;
-
-        let x = self.xy().x * gcw;
-        let y = self.xy().y * gch;
-        let start = match self.kind() {
-            WallKind::A => pt2(0.0, 0.0),
-            WallKind::B => pt2(0.0, -gch),
-        };
-        let end = match self.kind() {
-            WallKind::A => pt2(gcw, -gch),
-            WallKind::B => pt2(gcw, 0.0),
-        };
+    pub fn draw(&self, draw: &Draw, cell: Rect, is_hovered: bool, pen: &PenProfile) {
+        let (start, end) = self.endpoints(cell);
 
         draw.line()
-            .x_y(x, y)
             .points(start, end)
-            .weight(3.0)
+            .weight(pen.stroke_weight())
+            .color(pen.nannou_colour())
             .end_cap_round();
 
-        if self.is_hovered {
-            let (x, y) = (x + gcw / 2.0, y - gch / 2.0);
-
+        if is_hovered {
             draw.rect()
-                .w_h(gcw, gch)
-                .x_y(x, y)
+                .xy(cell.xy())
+                .wh(cell.wh())
                 .no_fill()
                 .stroke(RED)
                 .stroke_weight(1.0);
         }
     }
 
-    pub fn svg(&self, params: &MazeParams) -> svg::node::element::Line {
-        let gcw = params.grid_cell_width as f32;
-        let gch = params.grid_cell_height as f32;
+    /// Where the wall starts and ends when it's in `cell`. A walls go from the corner with the
+    /// lowest x and highest y to the opposite corner, B walls cross them.
+    pub fn endpoints(&self, cell: Rect) -> (Point2, Point2) {
+        match self.kind() {
+            WallKind::A => (cell.top_left(), cell.bottom_right()),
+            WallKind::B => (cell.bottom_left(), cell.top_right()),
+        }
+    }
 
-        let x = self.xy().x * gcw;
-        let y = self.xy().y * gch;
-        let start = match self.kind() {
-            WallKind::A => pt2(0.0, 0.0),
-            WallKind::B => pt2(0.0, -gch),
-        };
-        let end = match self.kind() {
-            WallKind::A => pt2(gcw, -gch),
-            WallKind::B => pt2(gcw, 0.0),
-        };
+    pub fn svg(&self, cell: Rect) -> svg::node::element::Line {
+        let (start, end) = self.endpoints(cell);
 
         Line::new()
-            .set("x1", x + start.x)
-            .set("y1", y + start.y + gch)
-            .set("x2", x + end.x)
-            .set("y2", y + end.y + gch)
+            .set("x1", start.x)
+            .set("y1", start.y)
+            .set("x2", end.x)
+            .set("y2", end.y)
+    }
+
+    pub fn shape(&self, cell: Rect) -> Shape {
+        let (start, end) = self.endpoints(cell);
+
+        Shape::Polyline(vec![start, end])
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum WallKind {
     A,
     B,
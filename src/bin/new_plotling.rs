@@ -0,0 +1,35 @@
+/// Starts a new generator from the template, with everything but the drawing wired up, e.g.
+/// `cargo run --release --bin new_plotling -- spiral_lines`
+/// It's written to `src/bin/<name>/` and added to Cargo.toml.
+use lib_plotings::config::positional_args;
+use lib_plotings::scaffold::scaffold;
+use log::{error, info, warn};
+use std::path::Path;
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    let name = match positional_args().into_iter().next() {
+        Some(name) => name,
+        None => {
+            error!("give the new generator a name, e.g. `cargo run --bin new_plotling -- spiral_lines`");
+            std::process::exit(1);
+        }
+    };
+
+    match scaffold(Path::new(env!("CARGO_MANIFEST_DIR")), &name) {
+        Ok(plotling_dir) => info!(
+            "created {}, run it with `cargo run --release --bin {}`",
+            plotling_dir.to_string_lossy(),
+            name
+        ),
+        Err(err) => {
+            error!("{:?}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use lib_plotings::pen::PenProfile;
 use lib_plotings::svg::svg_circle;
 use nannou::prelude::{pt2, Point2};
 use nannou::Draw;
@@ -13,12 +14,13 @@ pub struct FixedBody {
 }
 
 impl FixedBody {
-    pub fn draw(&self, draw: &Draw, params: &Params) {
+    pub fn draw(&self, draw: &Draw, params: &Params, pen: &PenProfile) {
         draw.ellipse()
             .xy(self.xy)
             .radius(self.radius)
             .no_fill()
-            .stroke_weight(8.0);
+            .stroke(pen.nannou_colour())
+            .stroke_weight(pen.stroke_weight());
     }
 
     pub fn svg(&self, params: &Params) -> Ellipse {
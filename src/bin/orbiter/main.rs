@@ -4,17 +4,29 @@ mod orbiter;
 mod params;
 mod system;
 
-use anyhow::Context;
-use chrono::Local;
-use lib_plotings::MouseButtonState;
-use log::{debug, error, info, trace, warn};
+use lib_plotings::camera::{is_mouse_over_ui, Camera};
+use lib_plotings::clock::SimulationClock;
+use lib_plotings::config::config;
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::Drawing;
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::pen::{PenLibrary, PenProfile, PIXELS_PER_MM};
+use lib_plotings::preset::{draw_error_banner, PresetFile};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::seed::Seeds;
+use lib_plotings::smoothing::Smoothing;
+use lib_plotings::svg::svg_window_transform;
+use log::{error, info, trace, warn};
 use nannou::{prelude::*, ui::prelude::*};
 use params::Params;
-use rand::{prelude::StdRng, SeedableRng};
-use std::path::PathBuf;
-use std::{cell::RefCell, mem};
+use std::mem;
 use system::System;
 
+/// How often the simulation steps at normal speed
+const STEPS_PER_SECOND: u32 = 60;
+
 fn main() {
     let res = dotenv::dotenv();
     env_logger::init();
@@ -28,33 +40,44 @@ fn main() {
 pub struct Model {
     ui: Ui,
     ids: Ids,
-    rng: RefCell<StdRng>,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<Params>>,
+    clock: SimulationClock,
     pub params: Params,
     pub system: System,
-    pub show_viewbox: bool,
-    pub mouse_xy: Point2,
-    pub mouse_button_l: MouseButtonState,
-    mouse_button_l_secret_state: bool,
+    pub pens: PenLibrary,
+    pub trail_pen: usize,
+    pub body_pen: usize,
+    pub show_paper_preview: bool,
 }
 
 widget_ids! {
     struct Ids {
+        body_pen,
+        export_dxf,
         export_svg,
         noise_seed,
-        toggle_viewbox,
+        pause,
+        toggle_paper_preview,
+        trail_pen,
+        trail_smoothing,
         restart,
+        seed,
+        single_step,
+        speed,
         starting_velocity,
     }
 }
 
 fn model(app: &App) -> Model {
+    let (window_width, window_height) = config().window_size_or((1920, 1080));
     let window_id = app
         .new_window()
-        .size(1920, 1080)
+        .size(window_width, window_height)
         .view(view)
-        .mouse_moved(mouse_moved)
-        .mouse_pressed(mouse_pressed)
-        .mouse_released(mouse_released)
+        .event(event)
         .build()
         .expect("couldn't create a window");
 
@@ -64,55 +87,92 @@ fn model(app: &App) -> Model {
     // Generate some ids for our widgets.
     let ids = Ids::new(ui.widget_id_generator());
     let params = Params::default();
-    let mut rng: StdRng = SeedableRng::seed_from_u64(params.rng_seed);
-    let system = System::new_from_params(&params, &mut rng);
-    let rng = RefCell::new(rng);
+    let system = System::new_from_params(&params);
+    let pens = PenLibrary::load();
+    let trail_pen = pens.index_of_or_first("0.3mm Marker");
+    let body_pen = pens.index_of_or_first("1mm Marker");
 
     Model {
         ui,
         ids,
-        rng,
+        input: Input::new(KeyBindings::load()),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset: PresetFile::from_args(),
+        clock: SimulationClock::from_steps_per_second(STEPS_PER_SECOND),
         system,
         params,
-        show_viewbox: false,
-        mouse_xy: Default::default(),
-        mouse_button_l: MouseButtonState::Released,
-        mouse_button_l_secret_state: false,
+        pens,
+        trail_pen,
+        body_pen,
+        show_paper_preview: false,
     }
 }
 
-pub fn mouse_moved(_app: &App, model: &mut Model, position: Point2) {
-    model.mouse_xy = position;
-}
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    handle_actions(app, model);
+    update_ui(model);
 
-pub fn mouse_pressed(_app: &App, model: &mut Model, button: MouseButton) {
-    match button {
-        MouseButton::Left => {
-            model.mouse_button_l_secret_state = true;
-        }
-        _ => trace!("unhandled mouse press event: {:?}", button),
+    if let Some(params) = model.preset.as_mut().and_then(|preset| preset.poll()) {
+        model.params = params;
+        model.system = System::new_from_params(&model.params);
+        model.clock.reset();
     }
-}
 
-pub fn mouse_released(_app: &App, model: &mut Model, button: MouseButton) {
-    match button {
-        MouseButton::Left => {
-            model.mouse_button_l_secret_state = false;
-        }
-        _ => trace!("unhandled mouse release event: {:?}", button),
-    }
+    let steps = model.clock.advance(update.since_last);
+    // clicks on the UI aren't meant for the drawing underneath it
+    let mouse_xy = if is_mouse_over_ui(&model.ui) {
+        None
+    } else {
+        Some(model.camera.screen_to_world(model.input.mouse_xy()))
+    };
+    model.system.update(
+        &model.params,
+        steps,
+        mouse_xy,
+        model.input.button(MouseButton::Left),
+    )
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    update_ui(model);
-
-    model
-        .mouse_button_l
-        .update(model.mouse_button_l_secret_state);
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
+}
 
-    model
-        .system
-        .update(&model.params, Some(model.mouse_xy), model.mouse_button_l)
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => {
+                model.system = System::new_from_params(&model.params);
+                model.clock.reset();
+            }
+            Action::NewSeed => {
+                model.params.rng_seed = Seeds::random_master(0..=9999);
+                model.system = System::new_from_params(&model.params);
+                model.clock.reset();
+            }
+            Action::ExportSvg => {
+                let trail_pen = model.pens.get(model.trail_pen);
+                let body_pen = model.pens.get(model.body_pen);
+                if let Err(err) = export_as_svg(&model.params, &model.system, trail_pen, body_pen) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) = export_as_dxf(&model.params, &model.system) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => model.show_paper_preview = !model.show_paper_preview,
+            Action::FitToWindow => model.camera.fit(
+                Rect::from_w_h(model.params.width, model.params.height),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Export | Action::Undo | Action::Redo | Action::Custom(_) => (),
+        }
+    }
 }
 
 fn update_ui(model: &mut Model) {
@@ -249,6 +309,64 @@ fn update_ui(model: &mut Model) {
         should_refresh_system = true;
     }
 
+    if let Some(seed) = widget::NumberDialer::new(model.params.rng_seed as f32, 0.0, 9999.0, 0)
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Seed")
+        .set(model.ids.seed, ui)
+    {
+        model.params.rng_seed = seed as u64;
+        should_refresh_system = true;
+    }
+
+    let pause_label = if model.clock.is_paused() {
+        "Resume"
+    } else {
+        "Pause"
+    };
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label(pause_label)
+        .set(model.ids.pause, ui)
+    {
+        model.clock.toggle_pause();
+    }
+
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Step")
+        .set(model.ids.single_step, ui)
+    {
+        model.clock.single_step();
+    }
+
+    for speed in widget::Slider::new(model.clock.speed() as f32, 0.1, 8.0)
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Speed")
+        .set(model.ids.speed, ui)
+    {
+        model.clock.set_speed(speed as f64);
+    }
+
     for _click in widget::Button::new()
         // .down(10.0)
         .top_left_with_margin(20.0)
@@ -257,10 +375,52 @@ fn update_ui(model: &mut Model) {
         .rgb(0.3, 0.3, 0.3)
         .label_rgb(1.0, 1.0, 1.0)
         .border(0.0)
-        .label("Toggle Viewbox")
-        .set(model.ids.toggle_viewbox, ui)
+        .label("Paper Preview")
+        .set(model.ids.toggle_paper_preview, ui)
+    {
+        model.show_paper_preview = !model.show_paper_preview;
+    }
+
+    let pen_names = model.pens.names();
+    for pen_index in widget::DropDownList::new(&pen_names, Some(model.trail_pen))
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Trail Pen")
+        .set(model.ids.trail_pen, ui)
+    {
+        model.trail_pen = pen_index;
+    }
+
+    for pen_index in widget::DropDownList::new(&pen_names, Some(model.body_pen))
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Body Pen")
+        .set(model.ids.body_pen, ui)
+    {
+        model.body_pen = pen_index;
+    }
+
+    let smoothing_names = Smoothing::names();
+    for smoothing_index in
+        widget::DropDownList::new(&smoothing_names, Some(model.params.trail_smoothing.index()))
+            .down(10.0)
+            .w_h(300.0, 20.0)
+            .label_font_size(12)
+            .rgb(0.3, 0.3, 0.3)
+            .label_rgb(1.0, 1.0, 1.0)
+            .border(0.0)
+            .label("Trail Smoothing")
+            .set(model.ids.trail_smoothing, ui)
     {
-        model.show_viewbox = !model.show_viewbox;
+        model.params.trail_smoothing = Smoothing::ALL[smoothing_index];
     }
 
     for _click in widget::Button::new()
@@ -273,20 +433,33 @@ fn update_ui(model: &mut Model) {
         .label("Export SVG")
         .set(model.ids.export_svg, ui)
     {
-        if let Err(err) = export_as_svg(&model.params, &model.system) {
+        let trail_pen = model.pens.get(model.trail_pen);
+        let body_pen = model.pens.get(model.body_pen);
+        if let Err(err) = export_as_svg(&model.params, &model.system, trail_pen, body_pen) {
             error!("{}", err)
         }
     }
 
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Export DXF")
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.params, &model.system) {
+            error!("{:?}", err)
+        }
+    }
+
     mem::drop(ui_cell);
 
     if should_refresh_system {
-        let rng = &mut *model.rng.borrow_mut();
-        let system = System::new_from_params(&model.params, rng);
-        // TODO do I need to manually drop here or is the compiler smart enough?
-        mem::drop(rng);
-
-        model.system = system;
+        model.system = System::new_from_params(&model.params);
+        model.clock.reset();
         trace!(
             "should_refresh_system=true, creating {} orbiter(s)",
             model.system.orbiters().len()
@@ -297,21 +470,31 @@ fn update_ui(model: &mut Model) {
 // Draw the state of your `Model` into the given `Frame` here.
 fn view(app: &App, model: &Model, frame: Frame) {
     // Begin drawing
-    let draw = app.draw();
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
+
+    let trail_pen = model.pens.get(model.trail_pen);
+    let body_pen = model.pens.get(model.body_pen);
 
-    draw.background().color(WHITE);
+    if model.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
 
-    model.system.draw(&draw, &model.params);
+        let drawing = build_drawing_from_model(&model.params, &model.system);
+        model.paper.draw(&draw, &drawing, |layer| {
+            if layer.name == "bodies" {
+                body_pen
+            } else {
+                trail_pen
+            }
+        });
+    } else {
+        draw.background().color(WHITE);
+
+        model.system.draw(&draw, &model.params, trail_pen, body_pen);
+    }
 
-    if model.show_viewbox {
-        draw.rect()
-            // we want the box centered on the screen, nannou places rectangle from their center
-            .x_y(0.0, 0.0)
-            .w_h(model.params.width, model.params.height)
-            .stroke(RED)
-            .stroke_weight(2.0)
-            .no_fill()
-            .finish();
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
     }
 
     // Write the result of our drawing to the window's frame.
@@ -321,53 +504,82 @@ fn view(app: &App, model: &Model, frame: Frame) {
     model.ui.draw_to_frame(app, &frame).unwrap();
 }
 
-fn build_svg_document_from_model(params: &Params, system: &System) -> svg::Document {
+fn build_svg_document_from_model(
+    params: &Params,
+    system: &System,
+    trail_pen: &PenProfile,
+    body_pen: &PenProfile,
+) -> svg::Document {
     let doc = svg::Document::new().set("viewBox", (0, 0, params.width, params.height));
-    let system = system.svg(params);
+    // the bodies orbit the middle of the window
+    let system = system.svg(params, trail_pen, body_pen).set(
+        "transform",
+        svg_window_transform(params.width, params.height),
+    );
     let bounding_rect = svg::node::element::Rectangle::new()
         .set("width", params.width)
-        .set("height", params.height)
-        .set("fill", "none")
-        .set("stroke", "black")
-        .set("stroke-width", "2mm");
+        .set("height", params.height);
+    let border = trail_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
 
-    doc.add(system).add(bounding_rect)
+    doc.add(system).add(border)
 }
 
-fn export_as_svg(params: &Params, system: &System) -> Result<(), anyhow::Error> {
-    info!("exporting image as SVG...");
-    let document = build_svg_document_from_model(params, system);
-    let base_path = std::env::var("SVG_EXPORT_DIRECTORY").context("setting 'SVG_EXPORT_DIRECTORY' is required, please set it to the directory you wish to export SVGs to")?;
-    let current_date = Local::today().format("%Y-%m-%d");
-    let svg_filename = format!("{}-plotling.svg", &current_date);
-    let mut svg_filepath: PathBuf = [base_path, svg_filename].iter().collect();
-
-    // I don't want to silently overwrite anything so I look for an unused filename,
-    // incrementing the counter until I find an unused number
-    // I could have also used a random string/number, I just like this better
-    if svg_filepath.exists() {
-        let mut counter = 1;
-
-        while svg_filepath.exists() {
-            if counter > 100 {
-                debug!(
-                    "export_as_svg counter has reached {}, you're not in an infinite loop are you?",
-                    counter
-                );
-            }
+fn build_drawing_from_model(params: &Params, system: &System) -> Drawing {
+    let mut drawing = Drawing::new(params.width, params.height);
+    let border = drawing.border();
 
-            let _ = svg_filepath.pop();
-            let svg_filename = format!("{}-plotling-{}.svg", &current_date, &counter);
-            svg_filepath.push(svg_filename);
-            counter += 1;
-        }
-    }
+    system.add_to_drawing(&mut drawing, params);
+    drawing.move_from_window();
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(params: &Params, system: &System) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let drawing = build_drawing_from_model(params, system);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(params.rng_seed),
+        params,
+        &drawing,
+    )?;
+
+    Ok(())
+}
+
+fn export_as_svg(
+    params: &Params,
+    system: &System,
+    trail_pen: &PenProfile,
+    body_pen: &PenProfile,
+) -> Result<(), anyhow::Error> {
+    info!("exporting image as SVG...");
+    let document = build_svg_document_from_model(params, system, trail_pen, body_pen);
+    let svg_filepath = unused_export_path("svg")?;
 
     svg::save(&svg_filepath, &document)?;
     info!(
         "SVG successfully exported to {}",
         &svg_filepath.to_string_lossy()
     );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(params.rng_seed),
+        params,
+        &build_drawing_from_model(params, system),
+    )?;
 
     Ok(())
 }
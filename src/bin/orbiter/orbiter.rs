@@ -1,15 +1,19 @@
 use crate::massive::Massive;
 use crate::params::Params;
+use lib_plotings::geometry::{Drawing, Shape};
+use lib_plotings::pen::PenProfile;
+use lib_plotings::smoothing::Smoothing;
 use lib_plotings::svg::svg_circle;
 use log::debug;
 use nannou::prelude::*;
 use once_cell::sync::Lazy;
-use rand::Rng;
 use std::collections::VecDeque;
-use svg::node::element::Group;
+use svg::node::element::{Group, Polyline};
 
 pub const GRAVITATIONAL_CONSTANT: Lazy<f32> = Lazy::new(|| 6.67428 * (10.0.powi(-11)));
 pub const GRAVITY_MULTIPLIER: f32 = 1000.0;
+/// How far apart the points on smoothed trails are
+const SMOOTHED_TRAIL_SPACING: f32 = 2.0;
 
 #[derive(Debug, Clone)]
 pub struct Orbiter {
@@ -35,10 +39,8 @@ impl Orbiter {
         }
     }
 
-    pub fn new_from_params(params: &Params, rng: &mut impl Rng) -> Self {
+    pub fn new_from_params(params: &Params, mass: f32, radius: f32) -> Self {
         let xy = pt2(params.width / 2.0, params.height / 2.0);
-        let mass = rng.gen_range(params.mass_range.clone());
-        let radius = rng.gen_range(params.radius_range.clone());
         let velocity = vec2(-0.2, -1.0);
 
         Self::new(mass, radius, velocity, xy, params.max_trail_dots)
@@ -60,14 +62,14 @@ impl Orbiter {
         self.forces_to_be_applied.push(force_vector);
     }
 
-    pub fn update(&mut self, params: &Params, tick_has_occurred: bool) {
+    pub fn update(&mut self, params: &Params, should_drop_trail_dot: bool) {
         for force in self.forces_to_be_applied.drain(..) {
             self.velocity += force;
         }
 
         self.current_xy += self.velocity;
 
-        if tick_has_occurred {
+        if should_drop_trail_dot {
             self.trail.push_back(self.current_xy);
 
             if self.trail.len() > params.max_trail_dots {
@@ -76,32 +78,70 @@ impl Orbiter {
         }
     }
 
-    pub fn draw(&self, draw: &Draw, _params: &Params) {
-        for trailer_xy in self.trail.iter() {
-            draw.ellipse()
-                .xy(trailer_xy.clone())
-                .radius(2.0)
-                .no_fill()
-                .stroke_weight(1.0);
+    /// The trail as a smoothed line, or `None` if the trail is drawn as dots
+    fn smoothed_trail(&self, params: &Params) -> Option<Vec<Point2>> {
+        if params.trail_smoothing == Smoothing::None {
+            return None;
+        }
+
+        let trail: Vec<Point2> = self.trail.iter().cloned().collect();
+
+        Some(
+            params
+                .trail_smoothing
+                .smooth(&trail, false, SMOOTHED_TRAIL_SPACING),
+        )
+    }
+
+    pub fn draw(
+        &self,
+        draw: &Draw,
+        params: &Params,
+        trail_pen: &PenProfile,
+        body_pen: &PenProfile,
+    ) {
+        if let Some(trail) = self.smoothed_trail(params) {
+            draw.polyline()
+                .weight(trail_pen.stroke_weight())
+                .color(trail_pen.nannou_colour())
+                .caps_round()
+                .points(trail);
+        } else {
+            for trailer_xy in self.trail.iter() {
+                draw.ellipse()
+                    .xy(trailer_xy.clone())
+                    .radius(2.0)
+                    .no_fill()
+                    .stroke(trail_pen.nannou_colour())
+                    .stroke_weight(trail_pen.stroke_weight());
+            }
         }
 
         draw.ellipse()
             .xy(self.current_xy)
             .radius(self.radius)
             .no_fill()
-            .stroke_weight(3.0);
+            .stroke(body_pen.nannou_colour())
+            .stroke_weight(body_pen.stroke_weight());
     }
 
-    pub fn svg(&self, _params: &Params) -> Group {
-        let mut trailers = Group::new()
-            .set("fill", "none")
-            .set("stroke", "black")
-            .set("stroke-width", "1");
+    pub fn svg(&self, params: &Params, trail_pen: &PenProfile, body_pen: &PenProfile) -> Group {
+        let mut trailers = trail_pen.style_svg_group(Group::new());
+
+        if let Some(trail) = self.smoothed_trail(params) {
+            let points = trail
+                .iter()
+                .map(|xy| format!("{:.2},{:.2}", xy.x, xy.y))
+                .collect::<Vec<_>>()
+                .join(" ");
 
-        for trail_xy in self.trail.iter() {
-            let dot = svg_circle(*trail_xy, 4.0);
+            trailers = trailers.add(Polyline::new().set("points", points));
+        } else {
+            for trail_xy in self.trail.iter() {
+                let dot = svg_circle(*trail_xy, 4.0);
 
-            trailers = trailers.add(dot);
+                trailers = trailers.add(dot);
+            }
         }
 
         Group::new()
@@ -109,18 +149,42 @@ impl Orbiter {
             .add(
                 svg_circle(self.start_xy, self.radius)
                     .set("fill", "none")
-                    .set("stroke", "black")
-                    .set("stroke-width", "5"),
+                    .set("stroke", body_pen.colour.as_str())
+                    .set("stroke-width", body_pen.svg_stroke_width()),
             )
             // ending position
             .add(
                 svg_circle(self.current_xy, self.radius)
-                    .set("fill", "black")
+                    .set("fill", body_pen.colour.as_str())
                     .set("stroke", "none"),
             )
             // trailers
             .add(trailers)
     }
+
+    /// Add this orbiter's starting and ending positions to the "bodies" layer and its trail to
+    /// the "trails" layer. Unlike the SVG export, the ending position can't be filled in.
+    pub fn add_to_drawing(&self, drawing: &mut Drawing, params: &Params) {
+        let bodies = drawing.layer_mut("bodies");
+        for center in [self.start_xy, self.current_xy].iter() {
+            bodies.push(Shape::Circle {
+                center: *center,
+                radius: self.radius,
+            });
+        }
+
+        let trails = drawing.layer_mut("trails");
+        if let Some(trail) = self.smoothed_trail(params) {
+            trails.push(Shape::Polyline(trail));
+        } else {
+            for trail_xy in self.trail.iter() {
+                trails.push(Shape::Circle {
+                    center: *trail_xy,
+                    radius: 4.0,
+                });
+            }
+        }
+    }
 }
 
 impl Massive for &Orbiter {
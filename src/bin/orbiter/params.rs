@@ -1,5 +1,10 @@
+use lib_plotings::preset::{check_not_empty, check_range, Validate};
+use lib_plotings::smoothing::Smoothing;
+use serde::{Deserialize, Serialize};
 use std::ops::Range;
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Params {
     pub width: f32,
     pub height: f32,
@@ -9,6 +14,10 @@ pub struct Params {
     pub rng_seed: u64,
     pub orbiter_count: u8,
     pub max_trail_dots: usize,
+    /// How many simulation steps pass between dots being dropped on the trail
+    pub steps_per_trail_dot: u64,
+    /// Without smoothing trails are dotted, with it they're drawn as a line through the dots
+    pub trail_smoothing: Smoothing,
 }
 
 impl Default for Params {
@@ -22,6 +31,18 @@ impl Default for Params {
             radius_range: 5.0..20.0,
             orbiter_count: 1,
             max_trail_dots: 100,
+            steps_per_trail_dot: 60,
+            trail_smoothing: Smoothing::None,
         }
     }
 }
+
+impl Validate for Params {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        self.trail_smoothing.validate()?;
+        check_range("width", self.width, 10.0..=10_000.0)?;
+        check_range("height", self.height, 10.0..=10_000.0)?;
+        check_not_empty("radius_range", &self.radius_range)?;
+        check_not_empty("mass_range", &self.mass_range)
+    }
+}
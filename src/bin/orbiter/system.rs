@@ -1,7 +1,9 @@
 use crate::fixed_body::FixedBody;
 use crate::{orbiter::Orbiter, params::Params};
-use lib_plotings::interval::Interval;
-use lib_plotings::MouseButtonState;
+use lib_plotings::geometry::Drawing;
+use lib_plotings::input::ButtonState;
+use lib_plotings::pen::PenProfile;
+use lib_plotings::seed::Seeds;
 use log::trace;
 use nannou::prelude::*;
 use rand::Rng;
@@ -10,34 +12,44 @@ use svg::node::element::Group;
 pub struct System {
     orbiters: Vec<Orbiter>,
     fixed_bodies: Vec<FixedBody>,
-    orbital_ticker: Interval,
+    /// How many steps have been simulated
+    steps: u64,
 }
 
 impl System {
-    pub fn new_from_params(params: &Params, rng: &mut impl Rng) -> Self {
+    /// The same params, seed included, always give the same system
+    pub fn new_from_params(params: &Params) -> Self {
+        let seeds = Seeds::new(params.rng_seed);
+        let mut masses = seeds.rng("masses");
+        let mut radii = seeds.rng("radii");
         let orbiters = (0..params.orbiter_count)
             .into_iter()
-            .map(|_index| Orbiter::new_from_params(params, rng))
+            .map(|_index| {
+                let mass = masses.gen_range(params.mass_range.clone());
+                let radius = radii.gen_range(params.radius_range.clone());
+
+                Orbiter::new_from_params(params, mass, radius)
+            })
             .collect();
 
         let fixed_bodies = vec![FixedBody::default()];
-        let orbital_ticker = Interval::new_from_seconds(1);
 
         Self {
             orbiters,
             fixed_bodies,
-            orbital_ticker,
+            steps: 0,
         }
     }
 
+    /// Handle the mouse and then simulate `steps` steps, as many as a
+    /// [`SimulationClock`](lib_plotings::clock::SimulationClock) says are due
     pub fn update(
         &mut self,
         params: &Params,
+        steps: u32,
         mouse_position: Option<Point2>,
-        mouse_button_state: MouseButtonState,
+        mouse_button_state: ButtonState,
     ) {
-        let orbital_tick_occurred = self.orbital_ticker.tick();
-
         if let Some(_xy) = mouse_position {
             // mouse position stuff
 
@@ -45,6 +57,18 @@ impl System {
                 trace!("Left mouse button click at {:?}", mouse_position);
             }
         }
+
+        for _ in 0..steps {
+            self.step(params);
+        }
+    }
+
+    /// Move the simulation on by one step. Nothing depends on how long a step takes in real
+    /// time, so a system can be run without a window by calling this in a loop.
+    pub fn step(&mut self, params: &Params) {
+        let should_drop_trail_dot = self.steps % params.steps_per_trail_dot.max(1) == 0;
+        self.steps += 1;
+
         let cloned_orbiters = self.orbiters.clone();
 
         // Queue up forces of gravity
@@ -66,25 +90,31 @@ impl System {
 
         // Apply queued forces to each orbiter
         for orbiter in self.orbiters.iter_mut() {
-            orbiter.update(params, orbital_tick_occurred);
+            orbiter.update(params, should_drop_trail_dot);
         }
     }
 
-    pub fn draw(&self, draw: &Draw, params: &Params) {
+    pub fn draw(
+        &self,
+        draw: &Draw,
+        params: &Params,
+        trail_pen: &PenProfile,
+        body_pen: &PenProfile,
+    ) {
         self.fixed_bodies
             .iter()
-            .for_each(|fixed_body| fixed_body.draw(draw, params));
+            .for_each(|fixed_body| fixed_body.draw(draw, params, body_pen));
 
         self.orbiters
             .iter()
-            .for_each(|orbiter| orbiter.draw(draw, params));
+            .for_each(|orbiter| orbiter.draw(draw, params, trail_pen, body_pen));
     }
 
-    pub fn svg(&self, params: &Params) -> Group {
+    pub fn svg(&self, params: &Params, trail_pen: &PenProfile, body_pen: &PenProfile) -> Group {
         let mut group = Group::new();
 
         for orbiter in self.orbiters.iter() {
-            let orbiter_group = orbiter.svg(params);
+            let orbiter_group = orbiter.svg(params, trail_pen, body_pen);
 
             group = group.add(orbiter_group);
         }
@@ -92,6 +122,12 @@ impl System {
         group
     }
 
+    pub fn add_to_drawing(&self, drawing: &mut Drawing, params: &Params) {
+        for orbiter in self.orbiters.iter() {
+            orbiter.add_to_drawing(drawing, params);
+        }
+    }
+
     /// Get a reference to the system's orbiters.
     pub fn orbiters(&self) -> &[Orbiter] {
         self.orbiters.as_slice()
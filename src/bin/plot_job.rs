@@ -0,0 +1,184 @@
+/// Plots a drawing on a G-code plotter as a job that can be picked back up, stopping between
+/// layers for a pen change, e.g. `cargo run --release --bin plot_job -- maze.svg /dev/ttyUSB0`.
+/// Progress is saved beside the drawing in `maze.job.json`, pass that instead of the drawing to
+/// carry on after a crash, a dry pen or quitting at a pen change.
+use anyhow::{anyhow, bail, Context};
+use lib_plotings::job::{JobStatus, PauseHandle, PlotJob};
+use lib_plotings::optimize;
+use lib_plotings::pen::PIXELS_PER_MM;
+use lib_plotings::pen_change::{format_duration, CliPenChangePrompt, PlotterSpeeds};
+use lib_plotings::plotter::gcode::GCodeStream;
+use lib_plotings::plotter::FLATTENING_TOLERANCE_MM;
+use lib_plotings::svg_import;
+use log::{error, warn};
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const MM_PER_UNIT: f32 = 1.0 / PIXELS_PER_MM;
+
+const USAGE: &str = "usage: plot_job <drawing.svg|drawing.job.json> <device> [options]
+
+options:
+    --pen <name>        draw no faster than the pen with this name in the pen profiles can go
+    --reorder           plot each layer's paths in an order that keeps pen-up travel short
+    --restart <path>    plot again from path <path> onwards, counting from 0
+    --replot <count>    plot the last <count> paths again, e.g. after a pen ran dry";
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(&options) {
+        error!("{:?}", err);
+        std::process::exit(1);
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    input: PathBuf,
+    device: PathBuf,
+    pen: Option<String>,
+    reorder: bool,
+    restart: Option<usize>,
+    replot: Option<usize>,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, anyhow::Error> {
+        let mut paths = Vec::new();
+        let mut options = Options {
+            input: PathBuf::new(),
+            device: PathBuf::new(),
+            pen: None,
+            reorder: false,
+            restart: None,
+            replot: None,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--pen" => options.pen = Some(value_of(&arg, &mut args)?),
+                "--reorder" => options.reorder = true,
+                "--restart" => options.restart = Some(count_of(&arg, &mut args)?),
+                "--replot" => options.replot = Some(count_of(&arg, &mut args)?),
+                _ if arg.starts_with("--") => bail!("'{}' isn't an option", arg),
+                _ => paths.push(PathBuf::from(&arg)),
+            }
+        }
+
+        match &paths[..] {
+            [input, device] => {
+                options.input = input.clone();
+                options.device = device.clone();
+            }
+            _ => bail!("a drawing or job and a device to plot on are required"),
+        }
+
+        Ok(options)
+    }
+}
+
+fn value_of(
+    option: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<String, anyhow::Error> {
+    args.next()
+        .ok_or_else(|| anyhow!("'{}' needs a value", option))
+}
+
+fn count_of(option: &str, args: &mut impl Iterator<Item = String>) -> Result<usize, anyhow::Error> {
+    let value = value_of(option, args)?;
+
+    value
+        .parse()
+        .with_context(|| format!("'{}' isn't a number of paths", value))
+}
+
+fn run(options: &Options) -> Result<(), anyhow::Error> {
+    let is_job = options
+        .input
+        .extension()
+        .map_or(false, |extension| extension == "json");
+    let mut job = if is_job {
+        PlotJob::load(&options.input)?
+    } else {
+        new_job(options)?
+    };
+
+    if let Some(index) = options.restart {
+        job.restart_from(index)?;
+    }
+    if let Some(count) = options.replot {
+        job.replot_last(count)?;
+    }
+
+    // a device that isn't there is a mistake, so it isn't created as a file
+    let device = OpenOptions::new()
+        .write(true)
+        .open(&options.device)
+        .with_context(|| format!("couldn't open '{}'", options.device.to_string_lossy()))?;
+    let speeds = PlotterSpeeds::for_pen(options.pen.as_deref())?;
+    let mut plotter = GCodeStream::new(device, speeds)?;
+
+    println!(
+        "{} of {} paths to go, about {}",
+        job.len() - job.completed(),
+        job.len(),
+        format_duration(
+            job.layer_estimates(&speeds)
+                .into_iter()
+                .map(|(_, estimate)| estimate)
+                .fold(Duration::ZERO, Duration::saturating_add)
+        )
+    );
+
+    match job.run_layers(
+        &mut plotter,
+        &mut CliPenChangePrompt,
+        &speeds,
+        &PauseHandle::default(),
+    )? {
+        JobStatus::Finished => println!("finished plotting all {} paths", job.len()),
+        JobStatus::Paused => println!(
+            "stopped after {} of {} paths, pass '{}' to carry on",
+            job.completed(),
+            job.len(),
+            job.file_path().to_string_lossy()
+        ),
+    }
+
+    Ok(())
+}
+
+/// A job for the drawing at `options.input`, saved beside it as `<drawing>.job.json`. An
+/// existing job is never replaced, it has to be resumed or deleted first.
+fn new_job(options: &Options) -> Result<PlotJob, anyhow::Error> {
+    let job_path = options.input.with_extension("job.json");
+    if job_path.exists() {
+        bail!(
+            "there's already a job for this drawing, pass '{}' to carry on with it or delete it to start over",
+            job_path.to_string_lossy()
+        );
+    }
+
+    let tolerance = FLATTENING_TOLERANCE_MM / MM_PER_UNIT;
+    let mut drawing = svg_import::open(&options.input, tolerance)?;
+    if options.reorder {
+        drawing = optimize::reorder(&drawing);
+    }
+
+    Ok(PlotJob::new(job_path, &drawing, MM_PER_UNIT))
+}
@@ -0,0 +1,276 @@
+/// Replays G-code, HPGL or EBB commands on a virtual plotter so exports can be checked without
+/// tying up the AxiDraw. Run it with the file to replay and, optionally, where to save the
+/// reconstructed SVG: `cargo run --release --bin plot_simulator -- plot.gcode reconstructed.svg`
+use anyhow::Context;
+use lib_plotings::config::{config, positional_args};
+use lib_plotings::geometry::Drawing;
+use lib_plotings::plotter::{CommandFormat, Move, Plotter, Simulator};
+use log::{error, info, warn};
+use nannou::{prelude::*, ui::prelude::*};
+use std::path::PathBuf;
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    nannou::app(model).update(update).run();
+}
+
+struct Model {
+    ui: Ui,
+    ids: Ids,
+    simulator: Simulator,
+    drawing: Drawing,
+    svg_output_path: Option<PathBuf>,
+    /// Millimeters of carriage travel per second of playback
+    playback_speed: f32,
+    /// How far along all of the simulator's moves the playback has gotten, in millimeters
+    playback_distance: f32,
+    show_travel: bool,
+}
+
+widget_ids! {
+    struct Ids {
+        export_svg,
+        param_title_text,
+        playback_speed,
+        restart,
+        stats_text,
+        toggle_travel,
+    }
+}
+
+fn model(app: &App) -> Model {
+    let (window_width, window_height) = config().window_size_or((1920, 1080));
+    let window_id = app
+        .new_window()
+        .size(window_width, window_height)
+        .view(view)
+        .build()
+        .expect("couldn't create a window");
+
+    // Create the UI.
+    let mut ui = app.new_ui().window(window_id).build().unwrap();
+
+    // Generate some ids for our widgets.
+    let ids = Ids::new(ui.widget_id_generator());
+
+    let mut args = positional_args().into_iter();
+    let simulator = match args.next() {
+        Some(path) => simulate_file(&PathBuf::from(path)).unwrap_or_else(|err| {
+            error!("{:?}", err);
+            Simulator::new()
+        }),
+        None => {
+            error!("pass the G-code, HPGL or EBB file to replay as the first argument");
+            Simulator::new()
+        }
+    };
+    let svg_output_path = args.next().map(PathBuf::from);
+
+    info!(
+        "simulated {} moves, {:.1}mm pen down, {:.1}mm pen up, {} pen lifts",
+        simulator.moves().len(),
+        simulator.pen_down_distance(),
+        simulator.pen_up_distance(),
+        simulator.pen_lifts()
+    );
+
+    let drawing = simulator.drawing();
+
+    Model {
+        ui,
+        ids,
+        simulator,
+        drawing,
+        svg_output_path,
+        playback_speed: 100.0,
+        playback_distance: 0.0,
+        show_travel: true,
+    }
+}
+
+fn simulate_file(path: &PathBuf) -> Result<Simulator, anyhow::Error> {
+    let format = CommandFormat::from_path(path)?;
+    let input = std::fs::read_to_string(path)
+        .with_context(|| format!("couldn't read '{}'", path.to_string_lossy()))?;
+    let commands = format.parse(&input)?;
+    let mut simulator = Simulator::new();
+    simulator.execute_all(&commands)?;
+
+    Ok(simulator)
+}
+
+fn update(_app: &App, model: &mut Model, update: Update) {
+    update_ui(model);
+
+    model.playback_distance += model.playback_speed * update.since_last.as_secs_f32();
+}
+
+fn update_ui(model: &mut Model) {
+    let ui = &mut model.ui.set_widgets();
+
+    widget::Text::new("Plot Simulator")
+        .top_left_with_margin(20.0)
+        .rgb(0.3, 0.3, 0.3)
+        .font_size(20)
+        .set(model.ids.param_title_text, ui);
+
+    let stats = format!(
+        "{} moves\n{:.1}mm pen down\n{:.1}mm pen up\n{} pen lifts",
+        model.simulator.moves().len(),
+        model.simulator.pen_down_distance(),
+        model.simulator.pen_up_distance(),
+        model.simulator.pen_lifts()
+    );
+
+    widget::Text::new(&stats)
+        .down(10.0)
+        .rgb(0.3, 0.3, 0.3)
+        .font_size(12)
+        .set(model.ids.stats_text, ui);
+
+    for playback_speed in widget::Slider::new(model.playback_speed, 10.0, 2000.0)
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Playback Speed (mm/s)")
+        .set(model.ids.playback_speed, ui)
+    {
+        model.playback_speed = playback_speed;
+    }
+
+    for is_toggled in widget::Toggle::new(model.show_travel)
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Toggle Pen-Up Travel")
+        .set(model.ids.toggle_travel, ui)
+    {
+        model.show_travel = is_toggled;
+    }
+
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Restart")
+        .set(model.ids.restart, ui)
+    {
+        model.playback_distance = 0.0;
+    }
+
+    for _click in widget::Button::new()
+        .down(10.0)
+        .w_h(300.0, 20.0)
+        .label_font_size(12)
+        .rgb(0.3, 0.3, 0.3)
+        .label_rgb(1.0, 1.0, 1.0)
+        .border(0.0)
+        .label("Export Reconstructed SVG")
+        .set(model.ids.export_svg, ui)
+    {
+        if let Err(err) = export_as_svg(&model.drawing, model.svg_output_path.as_ref()) {
+            error!("{}", err)
+        }
+    }
+}
+
+// Draw the state of your `Model` into the given `Frame` here.
+fn view(app: &App, model: &Model, frame: Frame) {
+    let draw = app.draw();
+
+    draw.background().color(WHITE);
+
+    let drawing = &model.drawing;
+    let window_rect = app.window_rect();
+    // Fit the plot into the window, leaving a margin around it
+    let scale = (window_rect.w() / drawing.width.max(1.0))
+        .min(window_rect.h() / drawing.height.max(1.0))
+        * 0.9;
+    // Plotter coordinates have y pointing away from home, nannou's y points up the screen
+    let to_screen = |xy: Point2| {
+        pt2(
+            (xy.x - drawing.width / 2.0) * scale,
+            (drawing.height / 2.0 - xy.y) * scale,
+        )
+    };
+
+    let mut distance_left = model.playback_distance;
+    let mut pen_xy = None;
+
+    for m in model.simulator.moves().iter() {
+        if distance_left <= 0.0 {
+            break;
+        }
+
+        let Move {
+            from,
+            to,
+            pen_is_down,
+            ..
+        } = *m;
+        let length = m.length();
+        let to = if distance_left < length {
+            from.lerp(to, distance_left / length)
+        } else {
+            to
+        };
+        distance_left -= length;
+        pen_xy = Some(to);
+
+        if pen_is_down {
+            draw.line()
+                .start(to_screen(from))
+                .end(to_screen(to))
+                .weight(2.0)
+                .color(BLACK)
+                .end_cap_round();
+        } else if model.show_travel {
+            draw.line()
+                .start(to_screen(from))
+                .end(to_screen(to))
+                .weight(1.0)
+                .color(rgba(1.0, 0.0, 0.0, 0.4));
+        }
+    }
+
+    if let Some(xy) = pen_xy {
+        draw.ellipse()
+            .xy(to_screen(xy))
+            .radius(6.0)
+            .no_fill()
+            .stroke(BLUE)
+            .stroke_weight(2.0);
+    }
+
+    // Write the result of our drawing to the window's frame.
+    draw.to_frame(app, &frame).unwrap();
+
+    // Draw the state of the `Ui` to the frame.
+    model.ui.draw_to_frame(app, &frame).unwrap();
+}
+
+fn export_as_svg(drawing: &Drawing, path: Option<&PathBuf>) -> Result<(), anyhow::Error> {
+    info!("exporting reconstructed plot as SVG...");
+    let path = path.context(
+        "pass the path to save the reconstructed SVG to as the second argument to export it",
+    )?;
+
+    svg::save(path, &drawing.svg())?;
+    info!("SVG successfully exported to {}", &path.to_string_lossy());
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+/// Runs an SVG through the same post-processing the plotlings use before they're plotted, and
+/// writes the result as SVG, DXF, G-code or HPGL depending on the output's extension, e.g.
+/// `cargo run --release --bin plotopt -- in.svg out.gcode --paper a4 --clip --merge 0.1 --reorder`
+use anyhow::{anyhow, bail, Context};
+use lib_plotings::dxf;
+use lib_plotings::geometry::Drawing;
+use lib_plotings::optimize::{self, DrawingStats};
+use lib_plotings::paper::Paper;
+use lib_plotings::pen::PIXELS_PER_MM;
+use lib_plotings::pen_change::PlotterSpeeds;
+use lib_plotings::plotter::{plot_drawing, CommandFormat, FLATTENING_TOLERANCE_MM};
+use lib_plotings::svg_import;
+use log::{error, info, warn};
+use nannou::prelude::*;
+use std::path::{Path, PathBuf};
+
+const MM_PER_UNIT: f32 = 1.0 / PIXELS_PER_MM;
+
+const USAGE: &str = "usage: plotopt <input.svg> <output.svg|dxf|gcode|hpgl> [options]
+
+options:
+    --paper <size>      scale the drawing to fit a4, a3, a5, letter, tabloid or <width>x<height> (mm)
+    --landscape         turn the paper on its side
+    --margin <mm>       space to leave around the edge of the paper, 10mm by default
+    --clip              cut away anything that's in the margin
+    --merge <mm>        join paths whose ends are closer than this
+    --simplify <mm>     drop points that change a path's course by less than this
+    --reorder           plot paths in an order that keeps pen-up travel short
+    --split-layers      write each layer to its own file, named after the layer
+    --pen <name>        write G-code that draws no faster than this pen from the pen profiles can go";
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    let options = match Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(&options) {
+        error!("{:?}", err);
+        std::process::exit(1);
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    input: PathBuf,
+    output: PathBuf,
+    paper: Option<Paper>,
+    margin_mm: f32,
+    clip: bool,
+    merge_mm: Option<f32>,
+    simplify_mm: Option<f32>,
+    reorder: bool,
+    split_layers: bool,
+    pen: Option<String>,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, anyhow::Error> {
+        let mut paths = Vec::new();
+        let mut paper = None;
+        let mut is_landscape = false;
+        let mut options = Options {
+            input: PathBuf::new(),
+            output: PathBuf::new(),
+            paper: None,
+            margin_mm: 10.0,
+            clip: false,
+            merge_mm: None,
+            simplify_mm: None,
+            reorder: false,
+            split_layers: false,
+            pen: None,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--paper" => paper = Some(Paper::from_name(&value_of(&arg, &mut args)?)?),
+                "--landscape" => is_landscape = true,
+                "--margin" => options.margin_mm = mm_value_of(&arg, &mut args)?,
+                "--clip" => options.clip = true,
+                "--merge" => options.merge_mm = Some(mm_value_of(&arg, &mut args)?),
+                "--simplify" => options.simplify_mm = Some(mm_value_of(&arg, &mut args)?),
+                "--reorder" => options.reorder = true,
+                "--split-layers" => options.split_layers = true,
+                "--pen" => options.pen = Some(value_of(&arg, &mut args)?),
+                _ if arg.starts_with("--") => bail!("'{}' isn't an option", arg),
+                _ => paths.push(PathBuf::from(&arg)),
+            }
+        }
+
+        match &paths[..] {
+            [input, output] => {
+                options.input = input.clone();
+                options.output = output.clone();
+            }
+            _ => bail!("an input and an output file are required"),
+        }
+
+        options.paper = if is_landscape {
+            Some(paper.unwrap_or_default().landscape())
+        } else {
+            paper
+        };
+
+        Ok(options)
+    }
+}
+
+fn value_of(
+    option: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<String, anyhow::Error> {
+    args.next()
+        .ok_or_else(|| anyhow!("'{}' needs a value", option))
+}
+
+fn mm_value_of(
+    option: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<f32, anyhow::Error> {
+    let value = value_of(option, args)?;
+
+    value
+        .parse()
+        .with_context(|| format!("'{}' isn't a number of millimeters", value))
+}
+
+fn run(options: &Options) -> Result<(), anyhow::Error> {
+    let tolerance = FLATTENING_TOLERANCE_MM / MM_PER_UNIT;
+    let mut drawing = svg_import::open(&options.input, tolerance)?;
+    let before = DrawingStats::of(&drawing, MM_PER_UNIT);
+    let margin = options.margin_mm * PIXELS_PER_MM;
+
+    if let Some(paper) = options.paper {
+        drawing = optimize::fit_to_paper(&drawing, paper, margin);
+    }
+
+    if options.clip {
+        let printable = Rect::from_corners(
+            pt2(margin, margin),
+            pt2(drawing.width - margin, drawing.height - margin),
+        );
+        drawing = optimize::clip(&drawing, printable, tolerance);
+    }
+
+    if let Some(merge_mm) = options.merge_mm {
+        drawing = optimize::merge(&drawing, merge_mm * PIXELS_PER_MM);
+    }
+
+    if let Some(simplify_mm) = options.simplify_mm {
+        drawing = optimize::simplify(&drawing, simplify_mm * PIXELS_PER_MM);
+    }
+
+    if options.reorder {
+        drawing = optimize::reorder(&drawing);
+    }
+
+    let speeds = PlotterSpeeds::for_pen(options.pen.as_deref())?;
+    let after = DrawingStats::of(&drawing, MM_PER_UNIT);
+    println!("before: {}", before);
+    println!("after:  {}", after);
+
+    if options.split_layers {
+        for layer_drawing in optimize::split_layers(&drawing) {
+            let path = layer_output_path(&options.output, &layer_drawing.layers[0].name);
+            write_drawing(&path, &layer_drawing, &speeds)?;
+        }
+    } else {
+        write_drawing(&options.output, &drawing, &speeds)?;
+    }
+
+    Ok(())
+}
+
+/// `out.svg` with a layer named `pen 1` becomes `out-pen_1.svg`
+fn layer_output_path(output: &Path, layer_name: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let layer_name: String = layer_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut filename = format!("{}-{}", stem, layer_name);
+
+    if let Some(extension) = output.extension() {
+        filename = format!("{}.{}", filename, extension.to_string_lossy());
+    }
+
+    output.with_file_name(filename)
+}
+
+fn write_drawing(
+    path: &Path,
+    drawing: &Drawing,
+    speeds: &PlotterSpeeds,
+) -> Result<(), anyhow::Error> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+
+    match extension.as_deref() {
+        Some("svg") => svg::save(path, &drawing.svg())
+            .with_context(|| format!("couldn't write SVG to '{}'", path.to_string_lossy()))?,
+        Some("dxf") => dxf::save(path, drawing, MM_PER_UNIT)?,
+        _ => {
+            let format = CommandFormat::from_path(path)?;
+            let mut commands = Vec::new();
+            plot_drawing(&mut commands, drawing, MM_PER_UNIT)?;
+            let output = format.write(&commands, speeds, drawing.height * MM_PER_UNIT)?;
+
+            std::fs::write(path, output)
+                .with_context(|| format!("couldn't write '{}'", path.to_string_lossy()))?;
+        }
+    }
+
+    info!("wrote {}", path.to_string_lossy());
+
+    Ok(())
+}
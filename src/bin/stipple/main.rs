@@ -0,0 +1,652 @@
+/// Stippling, shading with dots. The dots are Poisson disk samples thinned out where the picture
+/// should be lighter, either following a noise field or a grayscale image passed as the first
+/// argument, e.g. `cargo run --release --bin stipple -- portrait.png`
+use anyhow::Context;
+use lib_plotings::camera::Camera;
+use lib_plotings::config::{config, positional_args};
+use lib_plotings::dxf;
+use lib_plotings::export::unused_export_path;
+use lib_plotings::geometry::{Drawing, Shape};
+use lib_plotings::input::{Action, Input, KeyBindings};
+use lib_plotings::journal::record_export;
+use lib_plotings::noise::{to_unit, NoiseField, NoiseParams};
+use lib_plotings::pen::{PenLibrary, PIXELS_PER_MM};
+use lib_plotings::preset::{
+    check_range, draw_error_banner, set_loop_mode_for, PresetFile, Validate,
+};
+use lib_plotings::preview::PaperPreview;
+use lib_plotings::sampling::{variable_poisson_disk, Region};
+use lib_plotings::seed::Seeds;
+use lib_plotings::svg::svg_circle;
+use log::{error, info, trace, warn};
+use nannou::image::{self, GrayImage};
+use nannou::{geom::Rect, prelude::*, ui::prelude::*};
+use serde::{Deserialize, Serialize};
+use svg::node::element::{Group, Path};
+
+fn main() {
+    let res = dotenv::dotenv();
+    env_logger::init();
+    if let Err(err) = res {
+        warn!("{}", err)
+    };
+
+    nannou::app(model).update(update).run();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DensitySource {
+    Noise,
+    Image,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DotStyle {
+    /// The pen is put down and lifted straight back up, leaving a dot the size of its tip
+    Tap,
+    /// A tiny circle, for dots bigger than the pen
+    Circle,
+}
+
+/// The params that place the dots, everything a preset can set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Params {
+    pub density_source: DensitySource,
+    pub dot_style: DotStyle,
+    pub dot_radius: f32,
+    /// Darker images and higher noise get more dots, inverting gets fewer
+    pub invert: bool,
+    pub min_distance: f32,
+    pub noise: NoiseParams,
+    pub seed: u64,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            density_source: DensitySource::Noise,
+            dot_style: DotStyle::Tap,
+            dot_radius: 2.0,
+            invert: false,
+            min_distance: 6.0,
+            noise: NoiseParams {
+                scale: 0.005,
+                ..Default::default()
+            },
+            seed: 0,
+        }
+    }
+}
+
+impl Validate for Params {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        self.noise.check(0.001..=0.02)?;
+        check_range("dot_radius", self.dot_radius, 0.5..=10.0)?;
+        check_range("min_distance", self.min_distance, 2.0..=50.0)
+    }
+}
+
+pub struct State {
+    pub width: f32,
+    pub height: f32,
+    pub dots: Vec<Point2>,
+    pub params: Params,
+    pub image: Option<GrayImage>,
+    pub should_rebuild: bool,
+    pub show_paper_preview: bool,
+    pub pens: PenLibrary,
+    pub dot_pen: usize,
+}
+
+impl State {
+    fn new(width: f32, height: f32, image: Option<GrayImage>) -> Self {
+        let pens = PenLibrary::load();
+
+        Self {
+            width,
+            height,
+            dots: Vec::new(),
+            params: Params {
+                density_source: if image.is_some() {
+                    DensitySource::Image
+                } else {
+                    DensitySource::Noise
+                },
+                ..Default::default()
+            },
+            image,
+            should_rebuild: true,
+            show_paper_preview: false,
+            dot_pen: pens.index_of_or_first("0.3mm Marker"),
+            pens,
+        }
+    }
+
+    fn update(&mut self) {
+        if self.should_rebuild {
+            let region = Region::Rect(Rect::from_corners(
+                pt2(0.0, 0.0),
+                pt2(self.width, self.height),
+            ));
+            let seeds = Seeds::new(self.params.seed);
+            let noise_field = NoiseField::new(NoiseParams {
+                seed: seeds.noise_seed("density"),
+                ..self.params.noise
+            });
+            self.dots = variable_poisson_disk(
+                &region,
+                self.params.min_distance,
+                seeds.seed("dots"),
+                |xy| self.density_at(&noise_field, xy),
+            );
+            self.should_rebuild = false;
+            trace!("placed {} dots", self.dots.len());
+        }
+    }
+
+    /// How likely a dot at `xy` is to be kept, from 0 to 1
+    fn density_at(&self, noise_field: &NoiseField, xy: Point2) -> f32 {
+        let density = match (self.params.density_source, &self.image) {
+            (DensitySource::Image, Some(image)) => {
+                // the image is stretched over the whole drawing
+                let x = (xy.x / self.width * image.width() as f32) as u32;
+                let y = (xy.y / self.height * image.height() as f32) as u32;
+                let luma = image
+                    .get_pixel(x.min(image.width() - 1), y.min(image.height() - 1))
+                    .0[0];
+
+                1.0 - luma as f32 / 255.0
+            }
+            _ => to_unit(noise_field.get(xy)),
+        };
+
+        if self.params.invert {
+            1.0 - density
+        } else {
+            density
+        }
+    }
+}
+
+struct Model {
+    ui: Ui,
+    ids: Ids,
+    input: Input,
+    camera: Camera,
+    paper: PaperPreview,
+    preset: Option<PresetFile<Params>>,
+    pub state: State,
+}
+
+widget_ids! {
+    struct Ids {
+        density_source,
+        dot_pen,
+        dot_radius,
+        dot_style,
+        export_dxf,
+        export_svg,
+        height,
+        invert,
+        min_distance,
+        noise_scale,
+        param_title_text,
+        seed,
+        toggle_paper_preview,
+        width,
+    }
+}
+
+fn model(app: &App) -> Model {
+    let preset = PresetFile::from_args();
+    set_loop_mode_for(app, &preset);
+    let (width, height) = (800, 800);
+
+    let (window_width, window_height) = config().window_size_or((1280, 900));
+
+    let window_id = app
+        .new_window()
+        .size(window_width, window_height)
+        .view(view)
+        .event(event)
+        .build()
+        .expect("couldn't create a window");
+
+    // Create the UI.
+    let mut ui = app.new_ui().window(window_id).build().unwrap();
+
+    // Generate some ids for our widgets.
+    let ids = Ids::new(ui.widget_id_generator());
+    let image = match image_path_from_args() {
+        Some(path) => match open_image(&path) {
+            Ok(image) => Some(image),
+            Err(err) => {
+                error!("{:?}", err);
+                None
+            }
+        },
+        None => None,
+    };
+    let state = State::new(width as f32, height as f32, image);
+
+    Model {
+        ui,
+        ids,
+        input: Input::new(KeyBindings::load()),
+        camera: Camera::new(),
+        paper: PaperPreview::from_env(),
+        preset,
+        state,
+    }
+}
+
+/// The first argument that isn't an option like `--preset <file>`
+fn image_path_from_args() -> Option<String> {
+    positional_args().into_iter().next()
+}
+
+fn open_image(path: &str) -> Result<GrayImage, anyhow::Error> {
+    let image = image::open(path).with_context(|| format!("couldn't open image '{}'", path))?;
+
+    Ok(image.to_luma8())
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.input.update();
+    model.camera.update(&model.input, &model.ui);
+    handle_actions(app, model);
+    update_ui(model);
+
+    if let Some(params) = model.preset.as_mut().and_then(|preset| preset.poll()) {
+        model.state.params = params;
+        model.state.should_rebuild = true;
+    }
+
+    model.state.update();
+}
+
+fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+    model.input.handle_event(&event);
+}
+
+fn handle_actions(app: &App, model: &mut Model) {
+    for action in model.input.actions().to_vec() {
+        match action {
+            Action::Regenerate => model.state.should_rebuild = true,
+            Action::NewSeed => {
+                model.state.params.seed = Seeds::random_master(0..=100);
+                model.state.should_rebuild = true;
+            }
+            Action::ExportSvg => {
+                if let Err(err) = export_as_svg(&model.state) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::ExportDxf => {
+                if let Err(err) = export_as_dxf(&model.state) {
+                    error!("{:?}", err)
+                }
+            }
+            Action::TogglePaperPreview => {
+                model.state.show_paper_preview = !model.state.show_paper_preview
+            }
+            Action::FitToWindow => model.camera.fit(
+                Rect::from_w_h(model.state.width, model.state.height),
+                app.window_rect(),
+            ),
+            Action::ActualSize => model.camera.reset(),
+            Action::Export | Action::Undo | Action::Redo | Action::Custom(_) => (),
+        }
+    }
+}
+
+const SETTING_WIDTH: f64 = 300.0;
+const SETTING_HEIGHT: f64 = 30.0;
+const SETTING_MARGIN: f64 = 10.0;
+const LABEL_COLOR: nannou::ui::color::Color = nannou::ui::color::WHITE;
+const FILL: nannou::ui::color::Color = nannou::ui::color::DARK_BLUE;
+const FONT_SIZE: u32 = 12;
+
+fn dialer(val: f32, min: f32, max: f32) -> widget::NumberDialer<'static, f32> {
+    use nannou::ui::color::*;
+    widget::NumberDialer::new(val, min, max, 0)
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+}
+
+fn slider(val: f32, min: f32, max: f32) -> widget::Slider<'static, f32> {
+    use nannou::ui::color::*;
+    widget::Slider::new(val, min, max)
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+}
+
+fn drop_down_list(names: &[String], selected: usize) -> widget::DropDownList<'_, String> {
+    use nannou::ui::color::*;
+    widget::DropDownList::new(names, Some(selected))
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+}
+
+fn button(label: &str) -> widget::Button<'_, widget::button::Flat> {
+    use nannou::ui::color::*;
+    widget::Button::new()
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+        .label(label)
+}
+
+fn update_ui(model: &mut Model) {
+    let ui = &mut model.ui.set_widgets();
+    let mut should_refresh_model = false;
+
+    widget::Text::new("Parameters")
+        .top_left_with_margin(20.0)
+        .color(LABEL_COLOR)
+        .font_size(20)
+        .set(model.ids.param_title_text, ui);
+
+    for height in dialer(model.state.height, 10.0, 2160.0)
+        .down(SETTING_MARGIN)
+        .label("Height")
+        .set(model.ids.height, ui)
+    {
+        model.state.height = height.floor();
+        should_refresh_model = true;
+    }
+
+    for width in dialer(model.state.width, 10.0, 3840.0)
+        .down(SETTING_MARGIN)
+        .label("Width")
+        .set(model.ids.width, ui)
+    {
+        model.state.width = width.floor();
+        should_refresh_model = true;
+    }
+
+    for min_distance in slider(model.state.params.min_distance, 2.0, 50.0)
+        .down(SETTING_MARGIN)
+        .label("Min Distance")
+        .set(model.ids.min_distance, ui)
+    {
+        model.state.params.min_distance = min_distance;
+        should_refresh_model = true;
+    }
+
+    let density_sources = if model.state.image.is_some() {
+        vec!["Noise".to_owned(), "Image".to_owned()]
+    } else {
+        vec!["Noise".to_owned()]
+    };
+    let selected_source = match model.state.params.density_source {
+        DensitySource::Noise => 0,
+        DensitySource::Image => 1,
+    };
+    for source_index in drop_down_list(&density_sources, selected_source)
+        .down(SETTING_MARGIN)
+        .label("Density")
+        .set(model.ids.density_source, ui)
+    {
+        model.state.params.density_source = if source_index == 1 {
+            DensitySource::Image
+        } else {
+            DensitySource::Noise
+        };
+        should_refresh_model = true;
+    }
+
+    if model.state.params.density_source == DensitySource::Noise {
+        for seed in slider(model.state.params.seed as f32, 0.0, 100.0)
+            .down(SETTING_MARGIN)
+            .label("Seed")
+            .set(model.ids.seed, ui)
+        {
+            model.state.params.seed = seed as u64;
+            should_refresh_model = true;
+        }
+
+        for noise_scale in slider(model.state.params.noise.scale as f32, 0.001, 0.02)
+            .down(SETTING_MARGIN)
+            .label("Noise Scale")
+            .set(model.ids.noise_scale, ui)
+        {
+            model.state.params.noise.scale = noise_scale as f64;
+            should_refresh_model = true;
+        }
+    }
+
+    for invert in widget::Toggle::new(model.state.params.invert)
+        .down(SETTING_MARGIN)
+        .w_h(SETTING_WIDTH, SETTING_HEIGHT)
+        .label_font_size(FONT_SIZE)
+        .color(FILL)
+        .label_color(LABEL_COLOR)
+        .border(0.0)
+        .label("Invert")
+        .set(model.ids.invert, ui)
+    {
+        model.state.params.invert = invert;
+        should_refresh_model = true;
+    }
+
+    let dot_styles = vec!["Pen Taps".to_owned(), "Circles".to_owned()];
+    let selected_style = match model.state.params.dot_style {
+        DotStyle::Tap => 0,
+        DotStyle::Circle => 1,
+    };
+    for style_index in drop_down_list(&dot_styles, selected_style)
+        .down(SETTING_MARGIN)
+        .label("Dot Style")
+        .set(model.ids.dot_style, ui)
+    {
+        model.state.params.dot_style = if style_index == 1 {
+            DotStyle::Circle
+        } else {
+            DotStyle::Tap
+        };
+    }
+
+    if model.state.params.dot_style == DotStyle::Circle {
+        for dot_radius in slider(model.state.params.dot_radius, 0.5, 10.0)
+            .down(SETTING_MARGIN)
+            .label("Dot Radius")
+            .set(model.ids.dot_radius, ui)
+        {
+            model.state.params.dot_radius = dot_radius;
+        }
+    }
+
+    let pen_names = model.state.pens.names();
+    for pen_index in drop_down_list(&pen_names, model.state.dot_pen)
+        .down(SETTING_MARGIN)
+        .label("Dot Pen")
+        .set(model.ids.dot_pen, ui)
+    {
+        model.state.dot_pen = pen_index;
+    }
+
+    for _click in button("Paper Preview")
+        .down(SETTING_MARGIN)
+        .set(model.ids.toggle_paper_preview, ui)
+    {
+        model.state.show_paper_preview = !model.state.show_paper_preview;
+    }
+
+    for _click in button("Export SVG")
+        .down(SETTING_MARGIN)
+        .set(model.ids.export_svg, ui)
+    {
+        if let Err(err) = export_as_svg(&model.state) {
+            error!("{:?}", err)
+        }
+    }
+
+    for _click in button("Export DXF")
+        .down(SETTING_MARGIN)
+        .set(model.ids.export_dxf, ui)
+    {
+        if let Err(err) = export_as_dxf(&model.state) {
+            error!("{:?}", err)
+        }
+    }
+
+    if should_refresh_model {
+        model.state.should_rebuild = true;
+        trace!("refresh model called");
+    }
+}
+
+// Draw the state of your `Model` into the given `Frame` here.
+fn view(app: &App, model: &Model, frame: Frame) {
+    let window_draw = app.draw();
+    let draw = model.camera.draw(&window_draw);
+    let state = &model.state;
+
+    let pen = state.pens.get(state.dot_pen);
+
+    if state.show_paper_preview {
+        draw.background().color(LIGHTGRAY);
+
+        let drawing = build_drawing_from_state(state);
+        model.paper.draw(&draw, &drawing, |_| pen);
+    } else {
+        draw.background().color(WHITE);
+
+        let bounding_rect = Rect::from_w_h(state.width, state.height)
+            .align_middle_x_of(app.window_rect())
+            .align_middle_y_of(app.window_rect());
+        let radius = match state.params.dot_style {
+            DotStyle::Tap => pen.stroke_weight() / 2.0,
+            DotStyle::Circle => state.params.dot_radius,
+        };
+
+        for xy in state.dots.iter() {
+            // drawing coordinates have y pointing down the page, the window has it pointing up
+            let window_xy = pt2(bounding_rect.left() + xy.x, bounding_rect.top() - xy.y);
+            let dot = draw.ellipse().xy(window_xy).radius(radius);
+
+            match state.params.dot_style {
+                DotStyle::Tap => dot.color(pen.nannou_colour()),
+                DotStyle::Circle => dot
+                    .no_fill()
+                    .stroke(pen.nannou_colour())
+                    .stroke_weight(pen.stroke_weight()),
+            };
+        }
+    }
+
+    if let Some(error) = model.preset.as_ref().and_then(|preset| preset.error()) {
+        draw_error_banner(&window_draw, app.window_rect(), error);
+    }
+
+    // Write the result of our drawing to the window's frame.
+    draw.to_frame(app, &frame).unwrap();
+
+    // Draw the state of the `Ui` to the frame.
+    model.ui.draw_to_frame(app, &frame).unwrap();
+}
+
+fn build_svg_document_from_state(state: &State) -> svg::Document {
+    let doc = svg::Document::new().set("viewBox", (0, 0, state.width, state.height));
+
+    let dot_pen = state.pens.get(state.dot_pen);
+    // round caps turn a zero length line into a dot the width of the pen
+    let mut group = dot_pen
+        .style_svg_group(Group::new())
+        .set("stroke-linecap", "round");
+
+    for xy in state.dots.iter() {
+        group = match state.params.dot_style {
+            DotStyle::Tap => {
+                group.add(Path::new().set("d", format!("M{:.2},{:.2} l0,0", xy.x, xy.y)))
+            }
+            DotStyle::Circle => group.add(svg_circle(*xy, state.params.dot_radius)),
+        };
+    }
+
+    let bounding_rect = svg::node::element::Rectangle::new()
+        .set("width", state.width)
+        .set("height", state.height);
+    let border = dot_pen
+        .style_svg_group(svg::node::element::Group::new())
+        .add(bounding_rect);
+
+    doc.add(group).add(border)
+}
+
+fn build_drawing_from_state(state: &State) -> Drawing {
+    let mut drawing = Drawing::new(state.width, state.height);
+    let border = drawing.border();
+
+    let dots = drawing.layer_mut("dots");
+    for xy in state.dots.iter() {
+        dots.push(match state.params.dot_style {
+            // a line that goes nowhere, the pen comes down and goes straight back up
+            DotStyle::Tap => Shape::Polyline(vec![*xy, *xy]),
+            DotStyle::Circle => Shape::Circle {
+                center: *xy,
+                radius: state.params.dot_radius,
+            },
+        });
+    }
+
+    drawing.layer_mut("border").push(border);
+
+    drawing
+}
+
+fn export_as_dxf(state: &State) -> Result<(), anyhow::Error> {
+    info!("exporting image as DXF...");
+    let drawing = build_drawing_from_state(state);
+    let dxf_filepath = unused_export_path("dxf")?;
+
+    dxf::save(&dxf_filepath, &drawing, 1.0 / PIXELS_PER_MM)?;
+    info!(
+        "DXF successfully exported to {}",
+        &dxf_filepath.to_string_lossy()
+    );
+    record_export(
+        &dxf_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(state.params.seed),
+        &state.params,
+        &drawing,
+    )?;
+
+    Ok(())
+}
+
+fn export_as_svg(state: &State) -> Result<(), anyhow::Error> {
+    info!("exporting image as SVG...");
+    let document = build_svg_document_from_state(state);
+    let svg_filepath = unused_export_path("svg")?;
+
+    svg::save(&svg_filepath, &document)
+        .with_context(|| format!("couldn't write SVG to '{}'", svg_filepath.to_string_lossy()))?;
+    info!(
+        "SVG successfully exported to {}",
+        &svg_filepath.to_string_lossy()
+    );
+    record_export(
+        &svg_filepath,
+        env!("CARGO_BIN_NAME"),
+        Some(state.params.seed),
+        &state.params,
+        &build_drawing_from_state(state),
+    )?;
+
+    Ok(())
+}
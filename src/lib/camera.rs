@@ -0,0 +1,140 @@
+//! A camera for looking around a drawing: scroll to zoom in on the mouse, drag with the right or
+//! middle mouse button to pan and fit the whole drawing in the window. World coordinates are the
+//! window coordinates the generators draw in at 1:1. Drawing goes through [`Camera::draw`] and
+//! anything the mouse points at goes through [`Camera::screen_to_world`], so what's clicked is
+//! always what's under the mouse however far the view has moved. The mouse is left alone while
+//! it's over the UI, see [`is_mouse_over_ui`].
+
+use crate::input::Input;
+use nannou::prelude::*;
+use nannou::ui::Ui;
+
+/// How much a point of scrolling zooms by, scrolling `n` points zooms by `e^(n * ZOOM_PER_POINT)`
+const ZOOM_PER_POINT: f32 = 0.002;
+const MIN_ZOOM: f32 = 0.05;
+const MAX_ZOOM: f32 = 50.0;
+/// How much of the window a fitted drawing fills, leaving a little space around it
+const FIT_MARGIN: f32 = 0.9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// The point in the world shown at the middle of the window
+    center: Point2,
+    /// Window points per world point
+    zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            center: pt2(0.0, 0.0),
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn center(&self) -> Point2 {
+        self.center
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Where a point in the window is in the world, for hit-testing the mouse
+    pub fn screen_to_world(&self, xy: Point2) -> Point2 {
+        self.center + xy / self.zoom
+    }
+
+    pub fn world_to_screen(&self, xy: Point2) -> Point2 {
+        (xy - self.center) * self.zoom
+    }
+
+    /// A `Draw` that takes world coordinates and puts them where the camera shows them. Stroke
+    /// weights are scaled along with everything else.
+    pub fn draw(&self, draw: &Draw) -> Draw {
+        draw.scale(self.zoom).translate(-self.center.extend(0.0))
+    }
+
+    /// Zoom by `factor`, keeping the world point under `screen_xy` where it is
+    pub fn zoom_about(&mut self, screen_xy: Point2, factor: f32) {
+        let world_xy = self.screen_to_world(screen_xy);
+
+        self.zoom = (self.zoom * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+        self.center = world_xy - screen_xy / self.zoom;
+    }
+
+    /// Move the view along with the mouse, `screen_delta` is how far it moved in the window
+    pub fn pan(&mut self, screen_delta: Vec2) {
+        self.center -= screen_delta / self.zoom;
+    }
+
+    /// Show the whole of `world_rect` as large as it fits in `window_rect`
+    pub fn fit(&mut self, world_rect: Rect, window_rect: Rect) {
+        let zoom = (window_rect.w() / world_rect.w().max(1.0))
+            .min(window_rect.h() / world_rect.h().max(1.0))
+            * FIT_MARGIN;
+
+        self.zoom = zoom.max(MIN_ZOOM).min(MAX_ZOOM);
+        self.center = world_rect.xy() - window_rect.xy() / self.zoom;
+    }
+
+    /// Back to 1:1 with the world centred
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Zoom with the scroll wheel and pan with right or middle drags, once per update after
+    /// [`Input::update`]. Scrolling and dragging a widget in `ui` doesn't move the camera.
+    pub fn update(&mut self, input: &Input, ui: &Ui) {
+        if is_mouse_over_ui(ui) {
+            return;
+        }
+
+        let scroll = input.scroll().y;
+        if scroll != 0.0 {
+            self.zoom_about(input.mouse_xy(), (scroll * ZOOM_PER_POINT).exp());
+        }
+
+        if let Some(drag) = input.drag() {
+            if drag.button == MouseButton::Right || drag.button == MouseButton::Middle {
+                self.pan(input.mouse_delta());
+            }
+        }
+    }
+}
+
+/// Whether the mouse is over one of the widgets in `ui`, or dragging one, so clicks and scrolls
+/// are meant for the widget rather than the drawing
+pub fn is_mouse_over_ui(ui: &Ui) -> bool {
+    let mouse = &ui.global_input().current;
+
+    [mouse.widget_capturing_mouse, mouse.widget_under_mouse]
+        .iter()
+        .flatten()
+        // the window itself is a widget, and the mouse is always over that
+        .any(|id| *id != ui.window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zooming_keeps_the_point_under_the_mouse() {
+        let mut camera = Camera::new();
+        camera.pan(vec2(30.0, -10.0));
+        let mouse_xy = pt2(120.0, 45.0);
+        let world_xy = camera.screen_to_world(mouse_xy);
+
+        camera.zoom_about(mouse_xy, 2.5);
+
+        assert!(camera.world_to_screen(world_xy).distance(mouse_xy) < 1e-3);
+        assert!(camera.screen_to_world(mouse_xy).distance(world_xy) < 1e-3);
+    }
+}
@@ -0,0 +1,158 @@
+//! A clock for simulations that advances in fixed steps rather than following the wall clock.
+//! Real time is fed in each frame with [`SimulationClock::advance`], which says how many steps to
+//! run, and everything the simulation does happens per step. The same seed then gives the same
+//! result whatever the frame rate, and a simulation can be run without a window at all by
+//! stepping it directly.
+
+use std::time::Duration;
+
+/// The most steps a single frame can ask for. After a long stall (a breakpoint, a dragged
+/// window) the simulation slows down instead of freezing the app while it catches up.
+const MAX_STEPS_PER_ADVANCE: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationClock {
+    /// Simulated seconds per step
+    step_seconds: f64,
+    /// Simulated seconds that have built up but not been spent on a step yet
+    accumulated_seconds: f64,
+    /// How many simulated seconds pass per real second
+    speed: f64,
+    is_paused: bool,
+    /// Steps asked for with [`SimulationClock::single_step`] that haven't been handed out yet
+    queued_steps: u32,
+    steps: u64,
+}
+
+impl SimulationClock {
+    pub fn new(step_seconds: f64) -> Self {
+        assert!(
+            step_seconds > 0.0,
+            "a simulation step has to take some time"
+        );
+
+        Self {
+            step_seconds,
+            accumulated_seconds: 0.0,
+            speed: 1.0,
+            is_paused: false,
+            queued_steps: 0,
+            steps: 0,
+        }
+    }
+
+    pub fn from_steps_per_second(steps_per_second: u32) -> Self {
+        Self::new(1.0 / steps_per_second.max(1) as f64)
+    }
+
+    /// Let `elapsed` real time pass, returning how many steps should be simulated now. Steps
+    /// queued with [`SimulationClock::single_step`] are handed out even while paused.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        let mut steps = std::mem::take(&mut self.queued_steps);
+
+        if !self.is_paused {
+            self.accumulated_seconds += elapsed.as_secs_f64() * self.speed;
+            let due = (self.accumulated_seconds / self.step_seconds).floor();
+            self.accumulated_seconds -= due * self.step_seconds;
+            steps += due as u32;
+        }
+
+        if steps > MAX_STEPS_PER_ADVANCE {
+            // the extra time is dropped rather than owed, or it would only pile up
+            steps = MAX_STEPS_PER_ADVANCE;
+            self.accumulated_seconds = 0.0;
+        }
+
+        self.steps += steps as u64;
+
+        steps
+    }
+
+    pub fn pause(&mut self) {
+        self.is_paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.is_paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.is_paused = !self.is_paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    /// Ask for one more step on the next [`SimulationClock::advance`], for stepping through a
+    /// paused simulation
+    pub fn single_step(&mut self) {
+        self.queued_steps += 1;
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// 2.0 runs the simulation twice as fast as real time, 0.5 half as fast
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn step_seconds(&self) -> f64 {
+        self.step_seconds
+    }
+
+    /// How many steps have been handed out since the clock was made or reset
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Simulated seconds since the clock was made or reset
+    pub fn simulated_seconds(&self) -> f64 {
+        self.steps as f64 * self.step_seconds
+    }
+
+    /// Start counting from zero again, keeping the speed and whether the clock's paused
+    pub fn reset(&mut self) {
+        self.accumulated_seconds = 0.0;
+        self.queued_steps = 0;
+        self.steps = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_dont_depend_on_frame_rate() {
+        let mut slow_frames = SimulationClock::from_steps_per_second(60);
+        let mut fast_frames = SimulationClock::from_steps_per_second(60);
+
+        for _ in 0..30 {
+            slow_frames.advance(Duration::from_millis(100));
+        }
+        for _ in 0..360 {
+            fast_frames.advance(Duration::from_micros(8_333));
+        }
+
+        assert!((slow_frames.steps() as i64 - 180).abs() <= 1);
+        assert!((fast_frames.steps() as i64 - 180).abs() <= 1);
+    }
+
+    #[test]
+    fn test_pause_and_single_step() {
+        let mut clock = SimulationClock::from_steps_per_second(10);
+        clock.pause();
+
+        assert_eq!(clock.advance(Duration::from_secs(1)), 0);
+        clock.single_step();
+        assert_eq!(clock.advance(Duration::from_secs(1)), 1);
+
+        clock.resume();
+        clock.set_speed(0.5);
+        assert_eq!(clock.advance(Duration::from_secs(1)), 5);
+        assert_eq!(clock.steps(), 6);
+    }
+}
@@ -0,0 +1,396 @@
+//! Settings shared by every generator, each read from the first of these that has it:
+//!
+//! 1. a command line flag, e.g. `--export-dir ~/plots`
+//! 2. an environment variable, e.g. `SVG_EXPORT_DIRECTORY`
+//! 3. the project's `.env` file, with the same names as the environment variables
+//! 4. the user's config file, `~/.config/plotlings/config.toml` or the file named by
+//!    `PLOTLINGS_CONFIG`, e.g. `export_directory = "~/plots"`
+//! 5. the built-in defaults
+//!
+//! A value that can't be understood is skipped with a warning, so the next place down gets a
+//! say. `cargo run --bin config` prints every setting and where it came from.
+
+use crate::export::ExportFormat;
+use crate::paper::Paper;
+use crate::pen::DEFAULT_PEN_PROFILES_PATH;
+use anyhow::{anyhow, Context};
+use log::warn;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE_ENV: &str = "PLOTLINGS_CONFIG";
+
+static CONFIG: Lazy<Config> = Lazy::new(Config::load);
+
+/// The config for this run of the program, read the first time it's asked for
+pub fn config() -> &'static Config {
+    &CONFIG
+}
+
+/// The command line arguments that aren't options or their values. Every option the
+/// generators take, like `--preset` or the config flags, is followed by a value.
+pub fn positional_args() -> Vec<String> {
+    let mut args = std::env::args().skip(1);
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--") {
+            let _ = args.next();
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    positional
+}
+
+/// Where a setting's value came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    ConfigFile(PathBuf),
+    DotEnv(&'static str),
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "built-in default"),
+            Source::ConfigFile(path) => write!(f, "config file {}", path.to_string_lossy()),
+            Source::DotEnv(name) => write!(f, "{} in .env", name),
+            Source::Env(name) => write!(f, "environment variable {}", name),
+            Source::Flag(flag) => write!(f, "command line flag {}", flag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+/// The names a setting goes by in each of the places it can be set
+struct Key {
+    file: &'static str,
+    env: &'static str,
+    flag: &'static str,
+}
+
+const EXPORT_DIRECTORY: Key = Key {
+    file: "export_directory",
+    env: "SVG_EXPORT_DIRECTORY",
+    flag: "--export-dir",
+};
+const PAPER: Key = Key {
+    file: "paper",
+    env: "PAPER",
+    flag: "--paper",
+};
+const PEN_PROFILES: Key = Key {
+    file: "pen_profiles",
+    env: "PEN_PROFILES",
+    flag: "--pens",
+};
+const WINDOW_SIZE: Key = Key {
+    file: "window_size",
+    env: "WINDOW_SIZE",
+    flag: "--window-size",
+};
+const EXPORT_FORMATS: Key = Key {
+    file: "export_formats",
+    env: "EXPORT_FORMATS",
+    flag: "--formats",
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Where exports, their presets and the export journal are written
+    pub export_directory: Setting<PathBuf>,
+    /// The paper the preview shows drawings on
+    pub paper: Setting<Paper>,
+    /// The file pens are loaded from
+    pub pen_profiles: Setting<PathBuf>,
+    /// The size of generator windows, each generator picks its own if it isn't set
+    pub window_size: Setting<Option<(u32, u32)>>,
+    /// What the export key writes
+    pub export_formats: Setting<Vec<ExportFormat>>,
+}
+
+impl Config {
+    /// Read every layer of config, see the module docs for the order
+    pub fn load() -> Self {
+        Self::from_layers(&Layers::gather())
+    }
+
+    fn from_layers(layers: &Layers) -> Self {
+        Self {
+            export_directory: layers.setting(
+                &EXPORT_DIRECTORY,
+                PathBuf::from("exports"),
+                |value| Ok(expand_home(value)),
+            ),
+            paper: layers.setting(&PAPER, Paper::default(), Paper::from_name),
+            pen_profiles: layers.setting(
+                &PEN_PROFILES,
+                PathBuf::from(DEFAULT_PEN_PROFILES_PATH),
+                |value| Ok(expand_home(value)),
+            ),
+            window_size: layers.setting(&WINDOW_SIZE, None, |value| {
+                parse_window_size(value).map(Some)
+            }),
+            export_formats: layers.setting(&EXPORT_FORMATS, vec![ExportFormat::Svg], |value| {
+                value
+                    .split(',')
+                    .map(ExportFormat::from_name)
+                    .collect::<Result<Vec<_>, _>>()
+            }),
+        }
+    }
+
+    /// The window size from the config, or `default` if it doesn't set one
+    pub fn window_size_or(&self, default: (u32, u32)) -> (u32, u32) {
+        self.window_size.value.unwrap_or(default)
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let paper = &self.paper.value;
+        let window_size = match self.window_size.value {
+            Some((width, height)) => format!("{}x{}", width, height),
+            None => "each generator's own".to_owned(),
+        };
+        let export_formats = self
+            .export_formats
+            .value
+            .iter()
+            .map(|format| format.extension())
+            .collect::<Vec<_>>()
+            .join(",");
+        let rows = [
+            (
+                EXPORT_DIRECTORY.file,
+                self.export_directory.value.to_string_lossy().into_owned(),
+                &self.export_directory.source,
+            ),
+            (
+                PAPER.file,
+                format!("{}x{}mm", paper.width_mm, paper.height_mm),
+                &self.paper.source,
+            ),
+            (
+                PEN_PROFILES.file,
+                self.pen_profiles.value.to_string_lossy().into_owned(),
+                &self.pen_profiles.source,
+            ),
+            (WINDOW_SIZE.file, window_size, &self.window_size.source),
+            (
+                EXPORT_FORMATS.file,
+                export_formats,
+                &self.export_formats.source,
+            ),
+        ];
+
+        for (name, value, source) in rows.iter() {
+            writeln!(f, "{:<18} {:<32} from {}", name, value, source)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Everything that was set, in every place it could have been
+#[derive(Debug, Default)]
+struct Layers {
+    flags: Vec<String>,
+    env: HashMap<String, String>,
+    dot_env: HashMap<String, String>,
+    file: Option<(PathBuf, toml::value::Table)>,
+}
+
+impl Layers {
+    fn gather() -> Self {
+        let dot_env = dotenv::dotenv_iter()
+            .map(|iter| iter.filter_map(Result::ok).collect())
+            .unwrap_or_default();
+        let env: HashMap<String, String> = std::env::vars().collect();
+        let file = config_file_path(&env).and_then(|path| match read_config_file(&path) {
+            Ok(table) => Some((path, table)),
+            Err(err) => {
+                warn!("{:?}", err);
+                None
+            }
+        });
+
+        Self {
+            flags: std::env::args().skip(1).collect(),
+            env,
+            dot_env,
+            file,
+        }
+    }
+
+    /// The values set for `key`, most important first
+    fn values_for(&self, key: &Key) -> Vec<(String, Source)> {
+        let mut values = Vec::new();
+
+        if let Some(value) = self.flags.iter().skip_while(|arg| *arg != key.flag).nth(1) {
+            values.push((value.clone(), Source::Flag(key.flag)));
+        }
+
+        let dot_env_value = self.dot_env.get(key.env);
+        if let Some(value) = self.env.get(key.env) {
+            // the generators load .env into the environment when they start, but never over the
+            // top of anything that's already set
+            let source = if Some(value) == dot_env_value {
+                Source::DotEnv(key.env)
+            } else {
+                Source::Env(key.env)
+            };
+            values.push((value.clone(), source));
+        }
+        if let Some(value) = dot_env_value {
+            values.push((value.clone(), Source::DotEnv(key.env)));
+        }
+
+        if let Some((path, table)) = &self.file {
+            if let Some(value) = table.get(key.file).and_then(toml_to_string) {
+                values.push((value, Source::ConfigFile(path.clone())));
+            }
+        }
+
+        values
+    }
+
+    fn setting<T>(
+        &self,
+        key: &Key,
+        default: T,
+        parse: impl Fn(&str) -> Result<T, anyhow::Error>,
+    ) -> Setting<T> {
+        for (value, source) in self.values_for(key) {
+            match parse(value.trim()) {
+                Ok(value) => return Setting { value, source },
+                Err(err) => warn!("ignoring {} from {}: {:?}", key.file, source, err),
+            }
+        }
+
+        Setting {
+            value: default,
+            source: Source::Default,
+        }
+    }
+}
+
+/// TOML values as they'd be written in an environment variable, lists are joined with commas
+fn toml_to_string(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value.clone()),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        toml::Value::Array(values) => values
+            .iter()
+            .map(toml_to_string)
+            .collect::<Option<Vec<_>>>()
+            .map(|values| values.join(",")),
+        toml::Value::Datetime(_) | toml::Value::Table(_) => None,
+    }
+}
+
+fn config_file_path(env: &HashMap<String, String>) -> Option<PathBuf> {
+    if let Some(path) = env.get(CONFIG_FILE_ENV) {
+        return Some(expand_home(path));
+    }
+
+    let config_dir = env
+        .get("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env.get("HOME").map(|home| Path::new(home).join(".config")))?;
+    let path = config_dir.join("plotlings").join("config.toml");
+
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<toml::value::Table, anyhow::Error> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("couldn't read config '{}'", path.to_string_lossy()))?;
+
+    toml::from_str(&contents)
+        .with_context(|| format!("'{}' isn't a valid config file", path.to_string_lossy()))
+}
+
+/// Paths starting with `~/` are in the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn parse_window_size(size: &str) -> Result<(u32, u32), anyhow::Error> {
+    let (width, height) = size.split_once('x').ok_or_else(|| {
+        anyhow!(
+            "'{}' isn't a window size, it should look like 1920x1080",
+            size
+        )
+    })?;
+    let size = |size: &str| {
+        size.trim()
+            .parse::<u32>()
+            .with_context(|| format!("'{}' isn't a window size", size))
+    };
+
+    Ok((size(width)?, size(height)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_setting_comes_from_the_most_important_layer_that_has_it() {
+        let strings = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        };
+        let file_path = PathBuf::from("config.toml");
+        let file = toml::from_str(
+            "export_directory = \"from-file\"\npaper = \"a3\"\nexport_formats = [\"svg\", \"dxf\"]",
+        )
+        .unwrap();
+        let layers = Layers {
+            flags: vec!["--paper".to_owned(), "a5".to_owned()],
+            env: strings(&[("SVG_EXPORT_DIRECTORY", "from-dot-env"), ("PAPER", "a4")]),
+            dot_env: strings(&[("SVG_EXPORT_DIRECTORY", "from-dot-env")]),
+            file: Some((file_path.clone(), file)),
+        };
+
+        let config = Config::from_layers(&layers);
+
+        assert_eq!(config.paper.value, Paper::A5);
+        assert_eq!(config.paper.source, Source::Flag("--paper"));
+        assert_eq!(config.export_directory.value, PathBuf::from("from-dot-env"));
+        assert_eq!(
+            config.export_directory.source,
+            Source::DotEnv("SVG_EXPORT_DIRECTORY")
+        );
+        assert_eq!(
+            config.export_formats.value,
+            vec![ExportFormat::Svg, ExportFormat::Dxf]
+        );
+        assert_eq!(config.export_formats.source, Source::ConfigFile(file_path));
+        assert_eq!(config.window_size.source, Source::Default);
+    }
+}
@@ -0,0 +1,368 @@
+//! Curved path segments and how to turn them into the straight lines a plotter moves along. Curves
+//! are only flattened when something needs points, SVG exports keep them as curves.
+
+use nannou::prelude::*;
+use std::f32::consts::{PI, TAU};
+use svg::node::element::path::Data;
+
+/// How many times a bezier curve may be split in half while flattening it, 2^16 segments is far
+/// more than any curve on paper needs
+const MAX_SUBDIVISION_DEPTH: u32 = 16;
+
+/// One piece of a path, starting from wherever the piece before it ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    LineTo(Point2),
+    QuadraticTo {
+        control: Point2,
+        to: Point2,
+    },
+    CubicTo {
+        control_1: Point2,
+        control_2: Point2,
+        to: Point2,
+    },
+    /// A circular arc around `center`. A positive `sweep` (in radians) goes clockwise on the page,
+    /// y points down.
+    ArcTo {
+        center: Point2,
+        sweep: f32,
+    },
+}
+
+impl PathSegment {
+    /// Where this segment ends when it starts from `from`
+    pub fn end(&self, from: Point2) -> Point2 {
+        match *self {
+            PathSegment::LineTo(to)
+            | PathSegment::QuadraticTo { to, .. }
+            | PathSegment::CubicTo { to, .. } => to,
+            PathSegment::ArcTo { center, sweep } => rotate_around(from, center, sweep),
+        }
+    }
+
+    /// Add the points along this segment, except for `from` itself, to `points`
+    pub fn flatten_into(&self, from: Point2, tolerance: f32, points: &mut Vec<Point2>) {
+        match *self {
+            PathSegment::LineTo(to) => points.push(to),
+            PathSegment::QuadraticTo { control, to } => {
+                flatten_quadratic(from, control, to, tolerance, points)
+            }
+            PathSegment::CubicTo {
+                control_1,
+                control_2,
+                to,
+            } => flatten_cubic(from, control_1, control_2, to, tolerance, points),
+            PathSegment::ArcTo { center, sweep } => {
+                flatten_arc(from, center, sweep, tolerance, points)
+            }
+        }
+    }
+
+    /// Points that the segment never strays outside of (it's inside their bounding rect)
+    pub fn hull(&self, from: Point2) -> Vec<Point2> {
+        match *self {
+            PathSegment::LineTo(to) => vec![to],
+            PathSegment::QuadraticTo { control, to } => vec![control, to],
+            PathSegment::CubicTo {
+                control_1,
+                control_2,
+                to,
+            } => vec![control_1, control_2, to],
+            PathSegment::ArcTo { center, sweep } => {
+                let radius = from.distance(center);
+                let mut points = Vec::new();
+                flatten_arc(from, center, sweep, radius * 0.01, &mut points);
+                points
+            }
+        }
+    }
+
+    /// The same segment scaled up by `scale` around the origin and then moved by `offset`
+    pub fn transformed(&self, scale: f32, offset: Vec2) -> Self {
+        let transform = |xy: Point2| xy * scale + offset;
+
+        match *self {
+            PathSegment::LineTo(to) => PathSegment::LineTo(transform(to)),
+            PathSegment::QuadraticTo { control, to } => PathSegment::QuadraticTo {
+                control: transform(control),
+                to: transform(to),
+            },
+            PathSegment::CubicTo {
+                control_1,
+                control_2,
+                to,
+            } => PathSegment::CubicTo {
+                control_1: transform(control_1),
+                control_2: transform(control_2),
+                to: transform(to),
+            },
+            // scaling by a negative amount turns the arc half way around, it still goes the same way
+            PathSegment::ArcTo { center, sweep } => PathSegment::ArcTo {
+                center: transform(center),
+                sweep,
+            },
+        }
+    }
+
+    /// The same segment reflected in the x axis, which turns its arcs the other way
+    pub fn mirrored_y(&self) -> Self {
+        let mirror = |xy: Point2| pt2(xy.x, -xy.y);
+
+        match *self {
+            PathSegment::LineTo(to) => PathSegment::LineTo(mirror(to)),
+            PathSegment::QuadraticTo { control, to } => PathSegment::QuadraticTo {
+                control: mirror(control),
+                to: mirror(to),
+            },
+            PathSegment::CubicTo {
+                control_1,
+                control_2,
+                to,
+            } => PathSegment::CubicTo {
+                control_1: mirror(control_1),
+                control_2: mirror(control_2),
+                to: mirror(to),
+            },
+            PathSegment::ArcTo { center, sweep } => PathSegment::ArcTo {
+                center: mirror(center),
+                sweep: -sweep,
+            },
+        }
+    }
+
+    /// Add this segment to SVG path data as the matching `L`, `Q`, `C` or `A` command
+    pub fn add_to_svg_data(&self, from: Point2, data: Data) -> Data {
+        match *self {
+            PathSegment::LineTo(to) => data.line_to(svg_parameters(&[to])),
+            PathSegment::QuadraticTo { control, to } => {
+                data.quadratic_curve_to(svg_parameters(&[control, to]))
+            }
+            PathSegment::CubicTo {
+                control_1,
+                control_2,
+                to,
+            } => data.cubic_curve_to(svg_parameters(&[control_1, control_2, to])),
+            PathSegment::ArcTo { center, sweep } => {
+                // an arc command can't draw a whole circle since its start and end would be the
+                // same point, so long arcs are drawn in two halves
+                if sweep.abs() >= TAU - f32::EPSILON {
+                    let half = PathSegment::ArcTo {
+                        center,
+                        sweep: sweep / 2.0,
+                    };
+                    let data = half.add_to_svg_data(from, data);
+                    return half.add_to_svg_data(half.end(from), data);
+                }
+
+                let radius = round_for_svg(from.distance(center));
+                let to = self.end(from);
+                let is_large_arc = sweep.abs() > PI;
+                let is_clockwise = sweep > 0.0;
+
+                data.elliptical_arc_to(vec![
+                    radius,
+                    radius,
+                    0.0,
+                    if is_large_arc { 1.0 } else { 0.0 },
+                    if is_clockwise { 1.0 } else { 0.0 },
+                    round_for_svg(to.x),
+                    round_for_svg(to.y),
+                ])
+            }
+        }
+    }
+}
+
+/// Flatten a quadratic bezier curve by raising it to the cubic curve that traces the same path
+pub fn flatten_quadratic(
+    from: Point2,
+    control: Point2,
+    to: Point2,
+    tolerance: f32,
+    points: &mut Vec<Point2>,
+) {
+    let control_1 = from + (control - from) * (2.0 / 3.0);
+    let control_2 = to + (control - to) * (2.0 / 3.0);
+
+    flatten_cubic(from, control_1, control_2, to, tolerance, points);
+}
+
+/// Flatten a cubic bezier curve by splitting it in half until each piece is within `tolerance`
+/// of a straight line, so gentle stretches get few points and tight bends get many
+pub fn flatten_cubic(
+    from: Point2,
+    control_1: Point2,
+    control_2: Point2,
+    to: Point2,
+    tolerance: f32,
+    points: &mut Vec<Point2>,
+) {
+    flatten_cubic_recursive(from, control_1, control_2, to, tolerance, 0, points);
+}
+
+fn flatten_cubic_recursive(
+    from: Point2,
+    control_1: Point2,
+    control_2: Point2,
+    to: Point2,
+    tolerance: f32,
+    depth: u32,
+    points: &mut Vec<Point2>,
+) {
+    let is_flat = distance_to_segment(control_1, from, to) <= tolerance
+        && distance_to_segment(control_2, from, to) <= tolerance;
+
+    if is_flat || depth >= MAX_SUBDIVISION_DEPTH {
+        points.push(to);
+        return;
+    }
+
+    // de Casteljau's algorithm at t = 0.5
+    let ab = (from + control_1) / 2.0;
+    let bc = (control_1 + control_2) / 2.0;
+    let cd = (control_2 + to) / 2.0;
+    let abc = (ab + bc) / 2.0;
+    let bcd = (bc + cd) / 2.0;
+    let middle = (abc + bcd) / 2.0;
+
+    flatten_cubic_recursive(from, ab, abc, middle, tolerance, depth + 1, points);
+    flatten_cubic_recursive(middle, bcd, cd, to, tolerance, depth + 1, points);
+}
+
+/// Flatten a circular arc into chords that stray no further than `tolerance` from it
+pub fn flatten_arc(
+    from: Point2,
+    center: Point2,
+    sweep: f32,
+    tolerance: f32,
+    points: &mut Vec<Point2>,
+) {
+    let radius = from.distance(center);
+    // The sagitta of a chord spanning `angle` is r * (1 - cos(angle / 2))
+    let max_angle = if tolerance > 0.0 && tolerance < radius {
+        2.0 * (1.0 - tolerance / radius).acos()
+    } else {
+        PI / 4.0
+    };
+    let segments = ((sweep.abs() / max_angle).ceil() as usize).max(1);
+
+    for index in 1..=segments {
+        let angle = sweep * index as f32 / segments as f32;
+        points.push(rotate_around(from, center, angle));
+    }
+}
+
+/// Clockwise on the page, since y points down
+fn rotate_around(xy: Point2, center: Point2, angle: f32) -> Point2 {
+    let (sin, cos) = angle.sin_cos();
+    let offset = xy - center;
+
+    center
+        + vec2(
+            offset.x * cos - offset.y * sin,
+            offset.x * sin + offset.y * cos,
+        )
+}
+
+/// How far `xy` is from the closest point on the line from `a` to `b`
+pub(crate) fn distance_to_segment(xy: Point2, a: Point2, b: Point2) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+
+    if length_squared == 0.0 {
+        return xy.distance(a);
+    }
+
+    let t = ((xy - a).dot(ab) / length_squared).max(0.0).min(1.0);
+
+    xy.distance(a + ab * t)
+}
+
+/// Hundredths of a unit is plenty, anything more just makes files bigger
+pub(crate) fn round_for_svg(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+pub(crate) fn svg_parameters(points: &[Point2]) -> Vec<f32> {
+    points
+        .iter()
+        .flat_map(|xy| vec![round_for_svg(xy.x), round_for_svg(xy.y)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flattening_stays_within_tolerance() {
+        let (from, control_1, control_2, to) = (
+            pt2(0.0, 0.0),
+            pt2(0.0, 100.0),
+            pt2(100.0, 100.0),
+            pt2(100.0, 0.0),
+        );
+        let mut points = vec![from];
+        flatten_cubic(from, control_1, control_2, to, 0.1, &mut points);
+
+        assert_eq!(*points.last().unwrap(), to);
+        for t in (0..=100).map(|t| t as f32 / 100.0) {
+            let mt = 1.0 - t;
+            let on_curve = from * mt * mt * mt
+                + control_1 * 3.0 * mt * mt * t
+                + control_2 * 3.0 * mt * t * t
+                + to * t * t * t;
+            let distance = points
+                .windows(2)
+                .map(|segment| distance_to_segment(on_curve, segment[0], segment[1]))
+                .fold(f32::MAX, f32::min);
+
+            assert!(
+                distance <= 0.1,
+                "{} is {} from the flattened curve",
+                t,
+                distance
+            );
+        }
+    }
+
+    #[test]
+    fn test_arc_ends_where_it_should() {
+        let quarter = PathSegment::ArcTo {
+            center: pt2(0.0, 0.0),
+            sweep: PI / 2.0,
+        };
+
+        // clockwise on the page goes from +x to +y
+        assert!(quarter.end(pt2(10.0, 0.0)).distance(pt2(0.0, 10.0)) < 1e-4);
+
+        let mut points = Vec::new();
+        quarter.flatten_into(pt2(10.0, 0.0), 0.01, &mut points);
+        assert!(points.last().unwrap().distance(pt2(0.0, 10.0)) < 1e-4);
+    }
+
+    #[test]
+    fn test_transforming_an_arc_moves_its_points() {
+        let arc = PathSegment::ArcTo {
+            center: pt2(5.0, 0.0),
+            sweep: PI / 3.0,
+        };
+        let from = pt2(15.0, 0.0);
+        let transform = |xy: Point2| xy * -2.0 + vec2(100.0, 50.0);
+
+        let mut points = Vec::new();
+        arc.flatten_into(from, 0.01, &mut points);
+        let mut transformed_points = Vec::new();
+        arc.transformed(-2.0, vec2(100.0, 50.0)).flatten_into(
+            transform(from),
+            0.02,
+            &mut transformed_points,
+        );
+
+        assert_eq!(points.len(), transformed_points.len());
+        for (xy, transformed) in points.into_iter().zip(transformed_points) {
+            assert!(transform(xy).distance(transformed) < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Writes drawings as AutoCAD R12 ASCII DXF, the flavour every laser cutter and CAM package we've
+//! tried can open. R12 predates `LWPOLYLINE`, so polylines are written as `POLYLINE` entities
+//! followed by their `VERTEX`es, which is what R12 readers expect. Curved paths are flattened.
+
+use crate::geometry::{Drawing, Shape};
+use crate::plotter::FLATTENING_TOLERANCE_MM;
+use anyhow::Context;
+use nannou::prelude::*;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Converts a drawing into the text of a DXF file. `mm_per_unit` converts drawing units into
+/// millimeters, and y is flipped so the drawing isn't mirrored, DXF's y axis points up the page.
+pub fn document(drawing: &Drawing, mm_per_unit: f32) -> String {
+    let mut dxf = DxfWriter::default();
+    let to_mm = |xy: Point2| pt2(xy.x, drawing.height - xy.y) * mm_per_unit;
+
+    dxf.section("HEADER");
+    dxf.pair(9, "$ACADVER");
+    dxf.pair(1, "AC1009");
+    // 1 is metric
+    dxf.pair(9, "$MEASUREMENT");
+    dxf.pair(70, 1);
+    dxf.pair(9, "$EXTMIN");
+    dxf.point(pt2(0.0, 0.0));
+    dxf.pair(9, "$EXTMAX");
+    dxf.point(vec2(drawing.width, drawing.height) * mm_per_unit);
+    dxf.end_section();
+
+    dxf.section("TABLES");
+    dxf.pair(0, "TABLE");
+    dxf.pair(2, "LAYER");
+    dxf.pair(70, drawing.layers.len());
+    for (index, layer) in drawing.layers.iter().enumerate() {
+        dxf.pair(0, "LAYER");
+        dxf.pair(2, layer_name(&layer.name));
+        dxf.pair(70, 0);
+        // AutoCAD colour numbers start at 1 (red), 7 is black/white
+        dxf.pair(62, index % 255 + 1);
+        dxf.pair(6, "CONTINUOUS");
+    }
+    dxf.pair(0, "ENDTAB");
+    dxf.end_section();
+
+    dxf.section("ENTITIES");
+    for layer in drawing.layers.iter() {
+        let layer_name = layer_name(&layer.name);
+
+        for shape in layer.shapes.iter() {
+            match shape {
+                Shape::Polyline(points) if points.len() == 2 => {
+                    dxf.pair(0, "LINE");
+                    dxf.pair(8, &layer_name);
+                    dxf.point(to_mm(points[0]));
+                    dxf.pair(11, to_mm(points[1]).x);
+                    dxf.pair(21, to_mm(points[1]).y);
+                    dxf.pair(31, 0.0);
+                }
+                Shape::Polyline(points) => dxf.polyline(&layer_name, points, false, to_mm),
+                Shape::Polygon(points) => dxf.polyline(&layer_name, points, true, to_mm),
+                Shape::Circle { center, radius } => {
+                    dxf.pair(0, "CIRCLE");
+                    dxf.pair(8, &layer_name);
+                    dxf.point(to_mm(*center));
+                    dxf.pair(40, radius * mm_per_unit);
+                }
+                Shape::Path { is_closed, .. } => {
+                    let mut points = shape.to_polyline(FLATTENING_TOLERANCE_MM / mm_per_unit);
+                    if *is_closed {
+                        points.pop();
+                    }
+
+                    dxf.polyline(&layer_name, &points, *is_closed, to_mm)
+                }
+            }
+        }
+    }
+    dxf.end_section();
+
+    dxf.pair(0, "EOF");
+    dxf.output
+}
+
+pub fn save(path: &Path, drawing: &Drawing, mm_per_unit: f32) -> Result<(), anyhow::Error> {
+    std::fs::write(path, document(drawing, mm_per_unit))
+        .with_context(|| format!("couldn't write DXF to '{}'", path.to_string_lossy()))
+}
+
+/// DXF layer names can't contain most punctuation, anything that isn't allowed becomes `_`
+fn layer_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '$' {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if name.is_empty() {
+        "0".to_owned()
+    } else {
+        name
+    }
+}
+
+#[derive(Default)]
+struct DxfWriter {
+    output: String,
+}
+
+impl DxfWriter {
+    /// Every value in a DXF file is preceded by a group code saying what it is
+    fn pair(&mut self, code: u16, value: impl std::fmt::Display) {
+        // writing to a String can't fail
+        let _ = write!(self.output, "{:>3}\n{}\n", code, value);
+    }
+
+    fn point(&mut self, xy: Point2) {
+        self.pair(10, xy.x);
+        self.pair(20, xy.y);
+        self.pair(30, 0.0);
+    }
+
+    fn section(&mut self, name: &str) {
+        self.pair(0, "SECTION");
+        self.pair(2, name);
+    }
+
+    fn end_section(&mut self) {
+        self.pair(0, "ENDSEC");
+    }
+
+    fn polyline(
+        &mut self,
+        layer_name: &str,
+        points: &[Point2],
+        is_closed: bool,
+        to_mm: impl Fn(Point2) -> Point2,
+    ) {
+        self.pair(0, "POLYLINE");
+        self.pair(8, layer_name);
+        // "vertices follow", required by R12 even though it's always 1
+        self.pair(66, 1);
+        self.point(pt2(0.0, 0.0));
+        self.pair(70, if is_closed { 1 } else { 0 });
+
+        for xy in points.iter() {
+            self.pair(0, "VERTEX");
+            self.pair(8, layer_name);
+            self.point(to_mm(*xy));
+        }
+
+        self.pair(0, "SEQEND");
+        self.pair(8, layer_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entities_are_written_in_mm_on_their_layers() {
+        let mut drawing = Drawing::new(100.0, 100.0);
+        drawing
+            .layer_mut("walls")
+            .push(Shape::Polyline(vec![pt2(0.0, 0.0), pt2(10.0, 0.0)]));
+        drawing.layer_mut("circles").push(Shape::Circle {
+            center: pt2(50.0, 50.0),
+            radius: 10.0,
+        });
+        drawing.layer_mut("0.3mm pen").push(Shape::Polygon(vec![
+            pt2(0.0, 0.0),
+            pt2(10.0, 0.0),
+            pt2(10.0, 10.0),
+        ]));
+
+        let dxf = document(&drawing, 0.5);
+
+        assert!(dxf.contains("  0\nLINE\n  8\nWALLS\n 10\n0\n 20\n50\n"));
+        assert!(dxf.contains("  0\nCIRCLE\n  8\nCIRCLES\n 10\n25\n 20\n25\n 30\n0\n 40\n5\n"));
+        assert_eq!(dxf.matches("\nVERTEX\n  8\n0_3MM_PEN\n").count(), 3);
+        assert!(dxf.ends_with("  0\nEOF\n"));
+    }
+}
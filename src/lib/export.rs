@@ -0,0 +1,79 @@
+use crate::config::config;
+use anyhow::{bail, Context};
+use chrono::Local;
+use log::debug;
+use std::path::{Path, PathBuf};
+
+/// The kinds of file a generator can export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Svg,
+    Dxf,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Result<Self, anyhow::Error> {
+        match name.trim().to_lowercase().as_str() {
+            "svg" => Ok(ExportFormat::Svg),
+            "dxf" => Ok(ExportFormat::Dxf),
+            _ => bail!(
+                "'{}' isn't a format that can be exported, try svg or dxf",
+                name
+            ),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Svg => "svg",
+            ExportFormat::Dxf => "dxf",
+        }
+    }
+}
+
+/// What gets saved beside an export under the same name, like its preset
+/// (`2021-07-04-plotling.toml`) or a maze's history (`2021-07-04-plotling.history.json`)
+const EXPORT_EXTENSIONS: &[&str] = &["svg", "dxf", "toml", "history.json"];
+
+/// Find a path in the export directory to export today's plotling to, e.g.
+/// `2021-07-04-plotling.dxf`. Existing exports are never overwritten, a counter is added to the
+/// filename until it's one that no export, preset or history is using yet, so the files that go
+/// with one export never get mixed up with another's. The directory is made if it doesn't exist
+/// yet.
+pub fn unused_export_path(extension: &str) -> Result<PathBuf, anyhow::Error> {
+    let base_path = &config().export_directory.value;
+    std::fs::create_dir_all(base_path).with_context(|| {
+        format!(
+            "couldn't make the export directory '{}'",
+            base_path.to_string_lossy()
+        )
+    })?;
+    let current_date = Local::today().format("%Y-%m-%d").to_string();
+
+    Ok(unused_path_in(base_path, &current_date, extension))
+}
+
+pub(crate) fn unused_path_in(directory: &Path, date: &str, extension: &str) -> PathBuf {
+    let is_taken = |stem: &str| {
+        EXPORT_EXTENSIONS
+            .iter()
+            .chain(std::iter::once(&extension))
+            .any(|sibling| directory.join(format!("{}.{}", stem, sibling)).exists())
+    };
+    let mut stem = format!("{}-plotling", date);
+    let mut counter = 1;
+
+    while is_taken(&stem) {
+        if counter > 100 {
+            debug!(
+                "unused_export_path counter has reached {}, you're not in an infinite loop are you?",
+                counter
+            );
+        }
+
+        stem = format!("{}-plotling-{}", date, counter);
+        counter += 1;
+    }
+
+    directory.join(format!("{}.{}", stem, extension))
+}
@@ -0,0 +1,127 @@
+//! A static HTML page of everything in the export journal, newest first, for browsing what's
+//! been made without opening every file. Each piece shows its SVG as a thumbnail with its params
+//! and the command that opens it again.
+
+use crate::journal::JournalEntry;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; background: #f4f4f4; }
+.pieces { display: grid; grid-template-columns: repeat(auto-fill, minmax(320px, 1fr)); gap: 1.5em; }
+.piece { background: white; padding: 1em; box-shadow: 0 1px 3px rgba(0, 0, 0, 0.2); }
+.thumbnail { display: flex; align-items: center; justify-content: center; height: 280px; }
+.thumbnail img { max-width: 100%; max-height: 100%; }
+.missing { color: #888; }
+code, pre { font-size: 0.8em; overflow-x: auto; }";
+
+/// Build the page for `entries`, with files linked relative to `gallery_dir` where they can be
+pub fn render(entries: &[JournalEntry], gallery_dir: &Path) -> String {
+    let mut html = String::new();
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Plotlings</title>\n\
+         <style>\n{}\n</style>\n</head>\n<body>\n<h1>Plotlings</h1>\n<p>{} exports</p>\n\
+         <div class=\"pieces\">\n",
+        STYLE,
+        entries.len()
+    );
+
+    for entry in entries.iter().rev() {
+        render_entry(&mut html, entry, gallery_dir);
+    }
+
+    html.push_str("</div>\n</body>\n</html>\n");
+
+    html
+}
+
+fn render_entry(html: &mut String, entry: &JournalEntry, gallery_dir: &Path) {
+    let link = escape(&relative_to(&entry.path, gallery_dir).to_string_lossy());
+    let thumbnail = match thumbnail_for(&entry.path) {
+        Some(svg_path) => format!(
+            "<img src=\"{}\" alt=\"{}\" loading=\"lazy\">",
+            escape(&relative_to(&svg_path, gallery_dir).to_string_lossy()),
+            escape(&entry.generator)
+        ),
+        None => "<span class=\"missing\">no preview</span>".to_owned(),
+    };
+    let params = serde_json::to_string_pretty(&entry.params).unwrap_or_default();
+    let seed = entry
+        .seed
+        .map_or_else(|| "none".to_owned(), |seed| seed.to_string());
+
+    let _ = write!(
+        html,
+        "<div class=\"piece\">\n<a class=\"thumbnail\" href=\"{link}\">{thumbnail}</a>\n\
+         <h2>{generator}</h2>\n<p>{exported_at}<br>seed {seed}, {width:.0}&times;{height:.0}mm<br>\
+         {stats}</p>\n<p><a href=\"{link}\">{link}</a></p>\n<code>{reopen}</code>\n\
+         <details><summary>params</summary><pre>{params}</pre></details>\n</div>\n",
+        link = link,
+        thumbnail = thumbnail,
+        generator = escape(&entry.generator),
+        exported_at = escape(&entry.exported_at),
+        seed = seed,
+        width = entry.width_mm,
+        height = entry.height_mm,
+        stats = escape(&entry.stats.to_string()),
+        reopen = escape(&entry.reopen_command()),
+        params = escape(&params),
+    );
+}
+
+/// The export itself if it's an SVG, otherwise an SVG exported under the same name
+fn thumbnail_for(path: &Path) -> Option<PathBuf> {
+    let svg_path = path.with_extension("svg");
+
+    if path == svg_path || svg_path.exists() {
+        Some(svg_path)
+    } else {
+        None
+    }
+}
+
+fn relative_to(path: &Path, dir: &Path) -> PathBuf {
+    path.strip_prefix(dir).unwrap_or(path).to_owned()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::DrawingStats;
+
+    #[test]
+    fn test_entries_link_to_their_export_and_escape_params() {
+        let entry = JournalEntry {
+            exported_at: "2021-07-04T12:00:00+00:00".to_owned(),
+            generator: "maze".to_owned(),
+            seed: Some(7),
+            params: serde_json::json!({ "label": "<b>walls</b>" }),
+            path: PathBuf::from("/exports/2021-07-04-plotling.svg"),
+            preset: Some(PathBuf::from("/exports/2021-07-04-plotling.toml")),
+            width_mm: 100.0,
+            height_mm: 50.0,
+            stats: DrawingStats {
+                layers: 2,
+                paths: 10,
+                pen_down_mm: 500.0,
+                pen_up_mm: 100.0,
+                pen_lifts: 9,
+            },
+        };
+
+        let html = render(&[entry], Path::new("/exports"));
+
+        assert!(html.contains("<img src=\"2021-07-04-plotling.svg\""));
+        assert!(html.contains("--preset /exports/2021-07-04-plotling.toml"));
+        assert!(html.contains("&lt;b&gt;walls&lt;/b&gt;"));
+        assert!(!html.contains("<b>walls"));
+    }
+}
@@ -0,0 +1,320 @@
+use nannou::prelude::*;
+use std::f32::consts::TAU;
+use svg::node::element::path::Data;
+use svg::node::element::{Group, Path, Polygon, Polyline};
+
+use crate::curve::{svg_parameters, PathSegment};
+use crate::svg::svg_circle;
+
+/// A single thing the pen draws. Coordinates are in drawing units with the origin in the top left
+/// corner and y pointing down the page, the same as the `viewBox` of an exported SVG.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Polyline(Vec<Point2>),
+    /// A polyline that returns to its first point
+    Polygon(Vec<Point2>),
+    Circle {
+        center: Point2,
+        radius: f32,
+    },
+    /// Lines and curves joined end to end, see [`PathSegment`]
+    Path {
+        start: Point2,
+        segments: Vec<PathSegment>,
+        is_closed: bool,
+    },
+}
+
+impl Shape {
+    /// Convert this shape into the list of points the pen should visit. Curved shapes are split
+    /// into straight segments that stray no further than `tolerance` from the true curve.
+    pub fn to_polyline(&self, tolerance: f32) -> Vec<Point2> {
+        match self {
+            Shape::Polyline(points) => points.clone(),
+            Shape::Polygon(points) => points.iter().chain(points.first()).cloned().collect(),
+            Shape::Circle { center, radius } => {
+                let segments = circle_segment_count(*radius, tolerance);
+
+                (0..=segments)
+                    .map(|index| {
+                        let angle = index as f32 / segments as f32 * TAU;
+                        *center + vec2(angle.cos(), angle.sin()) * *radius
+                    })
+                    .collect()
+            }
+            Shape::Path {
+                start,
+                segments,
+                is_closed,
+            } => {
+                let mut points = vec![*start];
+
+                for segment in segments.iter() {
+                    let from = points[points.len() - 1];
+                    segment.flatten_into(from, tolerance, &mut points);
+                }
+
+                if *is_closed && points.last() != Some(start) {
+                    points.push(*start);
+                }
+
+                points
+            }
+        }
+    }
+
+    /// The same shape scaled up by `scale` around the origin and then moved by `offset`
+    pub fn transformed(&self, scale: f32, offset: Vec2) -> Shape {
+        let transform = |xy: &Point2| *xy * scale + offset;
+
+        match self {
+            Shape::Polyline(points) => Shape::Polyline(points.iter().map(transform).collect()),
+            Shape::Polygon(points) => Shape::Polygon(points.iter().map(transform).collect()),
+            Shape::Circle { center, radius } => Shape::Circle {
+                center: transform(center),
+                radius: radius * scale.abs(),
+            },
+            Shape::Path {
+                start,
+                segments,
+                is_closed,
+            } => Shape::Path {
+                start: transform(start),
+                segments: segments
+                    .iter()
+                    .map(|segment| segment.transformed(scale, offset))
+                    .collect(),
+                is_closed: *is_closed,
+            },
+        }
+    }
+
+    /// The same shape reflected in the x axis
+    pub fn mirrored_y(&self) -> Shape {
+        let mirror = |xy: &Point2| pt2(xy.x, -xy.y);
+
+        match self {
+            Shape::Polyline(points) => Shape::Polyline(points.iter().map(mirror).collect()),
+            Shape::Polygon(points) => Shape::Polygon(points.iter().map(mirror).collect()),
+            Shape::Circle { center, radius } => Shape::Circle {
+                center: mirror(center),
+                radius: *radius,
+            },
+            Shape::Path {
+                start,
+                segments,
+                is_closed,
+            } => Shape::Path {
+                start: mirror(start),
+                segments: segments.iter().map(PathSegment::mirrored_y).collect(),
+                is_closed: *is_closed,
+            },
+        }
+    }
+
+    pub fn bounding_rect(&self) -> Option<Rect> {
+        match self {
+            Shape::Polyline(points) | Shape::Polygon(points) => bounding_rect_of_points(points),
+            Shape::Circle { center, radius } => Some(Rect::from_xy_wh(
+                *center,
+                vec2(*radius * 2.0, *radius * 2.0),
+            )),
+            Shape::Path {
+                start, segments, ..
+            } => {
+                let mut hull = vec![*start];
+                let mut from = *start;
+
+                for segment in segments.iter() {
+                    hull.extend(segment.hull(from));
+                    from = segment.end(from);
+                }
+
+                bounding_rect_of_points(&hull)
+            }
+        }
+    }
+
+    pub fn add_to_svg_group(&self, group: Group) -> Group {
+        match self {
+            Shape::Polyline(points) => {
+                group.add(Polyline::new().set("points", svg_points_attribute(points)))
+            }
+            Shape::Polygon(points) => {
+                group.add(Polygon::new().set("points", svg_points_attribute(points)))
+            }
+            Shape::Circle { center, radius } => group.add(svg_circle(*center, *radius)),
+            Shape::Path {
+                start,
+                segments,
+                is_closed,
+            } => {
+                let mut data = Data::new().move_to(svg_parameters(&[*start]));
+                let mut from = *start;
+
+                for segment in segments.iter() {
+                    data = segment.add_to_svg_data(from, data);
+                    from = segment.end(from);
+                }
+
+                if *is_closed {
+                    data = data.close();
+                }
+
+                group.add(Path::new().set("d", data))
+            }
+        }
+    }
+}
+
+/// A group of shapes drawn with the same pen
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layer {
+    pub name: String,
+    pub shapes: Vec<Shape>,
+}
+
+impl Layer {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            shapes: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, shape: Shape) {
+        self.shapes.push(shape);
+    }
+
+    pub fn svg(&self) -> Group {
+        let mut group = Group::new()
+            .set("id", self.name.as_str())
+            .set("fill", "none")
+            .set("stroke", "black")
+            .set("stroke-width", "0.3mm");
+
+        for shape in self.shapes.iter() {
+            group = shape.add_to_svg_group(group);
+        }
+
+        group
+    }
+}
+
+/// Everything a plotling wants put on paper, independent of the format it ends up exported as
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Drawing {
+    pub width: f32,
+    pub height: f32,
+    pub layers: Vec<Layer>,
+}
+
+impl Drawing {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Get the layer with the given name, creating it if it doesn't exist yet
+    pub fn layer_mut(&mut self, name: &str) -> &mut Layer {
+        match self.layers.iter().position(|layer| layer.name == name) {
+            Some(index) => &mut self.layers[index],
+            None => {
+                self.layers.push(Layer::new(name));
+                self.layers.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// A rectangle around the edge of the page, what the SVG exports draw as their bounding rect
+    pub fn border(&self) -> Shape {
+        Shape::Polygon(vec![
+            pt2(0.0, 0.0),
+            pt2(self.width, 0.0),
+            pt2(self.width, self.height),
+            pt2(0.0, self.height),
+        ])
+    }
+
+    /// Move every shape from window coordinates, with the origin in the middle of the drawing
+    /// and y pointing up, to drawing coordinates. For generators that build their shapes the way
+    /// they're drawn on screen.
+    pub fn move_from_window(&mut self) {
+        let offset = vec2(self.width * 0.5, self.height * 0.5);
+
+        for layer in self.layers.iter_mut() {
+            for shape in layer.shapes.iter_mut() {
+                *shape = shape.mirrored_y().transformed(1.0, offset);
+            }
+        }
+    }
+
+    pub fn shape_count(&self) -> usize {
+        self.layers.iter().map(|layer| layer.shapes.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shape_count() == 0
+    }
+
+    pub fn svg(&self) -> svg::Document {
+        let mut doc = svg::Document::new().set("viewBox", (0, 0, self.width, self.height));
+
+        for layer in self.layers.iter() {
+            doc = doc.add(layer.svg());
+        }
+
+        doc
+    }
+}
+
+pub fn bounding_rect_of_points(points: &[Point2]) -> Option<Rect> {
+    let first = points.first()?;
+    let (l, r, b, t) = points
+        .iter()
+        .fold((first.x, first.x, first.y, first.y), |(l, r, b, t), xy| {
+            (l.min(xy.x), r.max(xy.x), b.min(xy.y), t.max(xy.y))
+        });
+
+    Some(Rect::from_corners(vec2(l, b), vec2(r, t)))
+}
+
+/// Whether `xy` is inside `polygon`, by the even-odd rule so holes made by a polygon crossing
+/// itself count as outside
+pub fn polygon_contains(polygon: &[Point2], xy: Point2) -> bool {
+    let mut is_inside = false;
+
+    for (index, a) in polygon.iter().enumerate() {
+        let b = polygon[(index + 1) % polygon.len()];
+
+        // does a ray going right from xy cross the edge from a to b?
+        if (a.y > xy.y) != (b.y > xy.y) && xy.x < a.x + (xy.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            is_inside = !is_inside;
+        }
+    }
+
+    is_inside
+}
+
+fn circle_segment_count(radius: f32, tolerance: f32) -> usize {
+    if tolerance <= 0.0 || tolerance >= radius {
+        return 8;
+    }
+
+    // The sagitta of a chord spanning `angle` is r * (1 - cos(angle / 2))
+    let max_angle = 2.0 * (1.0 - tolerance / radius).acos();
+
+    ((TAU / max_angle).ceil() as usize).max(8)
+}
+
+fn svg_points_attribute(points: &[Point2]) -> String {
+    let data: Vec<_> = points
+        .iter()
+        .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+        .collect();
+
+    data.join(" ")
+}
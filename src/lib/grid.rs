@@ -0,0 +1,280 @@
+//! A fixed size 2D grid of cells, stored row by row. Cells are addressed by column (`x`) and row
+//! (`y`), both counting from zero. Lookups that might fall off the edge take signed coordinates
+//! and come in three flavours: checked ([`Grid::get`]), wrapping around to the other side
+//! ([`Grid::get_wrapping`]) and clamped to the nearest edge cell ([`Grid::get_clamped`]).
+
+use nannou::prelude::*;
+
+/// Offsets to the cells sharing an edge with a cell
+const EDGE_NEIGHBOURS: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+/// Offsets to the cells sharing an edge or a corner with a cell
+const ALL_NEIGHBOURS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<T> {
+    columns: usize,
+    rows: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Fill a grid by calling `cell` with each cell's column and row, a row at a time
+    pub fn from_fn(columns: usize, rows: usize, mut cell: impl FnMut(usize, usize) -> T) -> Self {
+        let cells = (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .map(|(x, y)| cell(x, y))
+            .collect();
+
+        Self {
+            columns,
+            rows,
+            cells,
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.columns && (y as usize) < self.rows
+    }
+
+    /// Where a cell is in the row by row list of cells, if it's in the grid
+    pub fn index_of(&self, x: isize, y: isize) -> Option<usize> {
+        if self.contains(x, y) {
+            Some(y as usize * self.columns + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Like [`Grid::index_of`], but coordinates off one edge come back in on the opposite edge
+    pub fn wrapping_index_of(&self, x: isize, y: isize) -> usize {
+        let (columns, rows) = (self.columns as isize, self.rows as isize);
+
+        (y.rem_euclid(rows) * columns + x.rem_euclid(columns)) as usize
+    }
+
+    pub fn get(&self, x: isize, y: isize) -> Option<&T> {
+        self.index_of(x, y).map(|index| &self.cells[index])
+    }
+
+    pub fn get_mut(&mut self, x: isize, y: isize) -> Option<&mut T> {
+        self.index_of(x, y).map(move |index| &mut self.cells[index])
+    }
+
+    pub fn get_wrapping(&self, x: isize, y: isize) -> &T {
+        &self.cells[self.wrapping_index_of(x, y)]
+    }
+
+    pub fn get_wrapping_mut(&mut self, x: isize, y: isize) -> &mut T {
+        let index = self.wrapping_index_of(x, y);
+
+        &mut self.cells[index]
+    }
+
+    pub fn get_clamped(&self, x: isize, y: isize) -> &T {
+        let x = x.max(0).min(self.columns as isize - 1);
+        let y = y.max(0).min(self.rows as isize - 1);
+
+        &self.cells[y as usize * self.columns + x as usize]
+    }
+
+    /// The cells sharing an edge with a cell, leaving out any that are off the grid
+    pub fn neighbours_4(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbours(x, y, &EDGE_NEIGHBOURS)
+    }
+
+    /// The cells sharing an edge or a corner with a cell, leaving out any that are off the grid
+    pub fn neighbours_8(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbours(x, y, &ALL_NEIGHBOURS)
+    }
+
+    fn neighbours(
+        &self,
+        x: usize,
+        y: usize,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        offsets
+            .iter()
+            .map(move |(dx, dy)| (x as isize + dx, y as isize + dy))
+            .filter(move |(x, y)| self.contains(*x, *y))
+            .map(|(x, y)| (x as usize, y as usize))
+    }
+
+    /// Every cell along with its column and row, a row at a time
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        let columns = self.columns;
+
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(index, cell)| ((index % columns, index / columns), cell))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = ((usize, usize), &mut T)> {
+        let columns = self.columns;
+
+        self.cells
+            .iter_mut()
+            .enumerate()
+            .map(move |(index, cell)| ((index % columns, index / columns), cell))
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.columns..(y + 1) * self.columns]
+    }
+
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[T]> {
+        // chunks panics on a chunk size of zero, a grid with no columns has no cells to chunk
+        self.cells.chunks(self.columns.max(1))
+    }
+
+    /// The cells in column `x`, top to bottom. Like `row`, it panics when `x` is off the grid,
+    /// instead of quietly starting on a later row.
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        assert!(
+            x < self.columns,
+            "column {} is off a grid {} columns wide",
+            x,
+            self.columns
+        );
+
+        self.cells.iter().skip(x).step_by(self.columns.max(1))
+    }
+
+    /// The cell that `xy` falls in when the grid is laid out with `layout`
+    pub fn cell_at(&self, layout: &CellLayout, xy: Point2) -> Option<(usize, usize)> {
+        let (x, y) = layout.world_to_cell(xy);
+
+        if self.contains(x, y) {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Self {
+            columns: 0,
+            rows: 0,
+            cells: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(columns: usize, rows: usize, cell: T) -> Self {
+        Self {
+            columns,
+            rows,
+            cells: vec![cell; columns * rows],
+        }
+    }
+}
+
+/// Where a grid's cells are in a drawing or a window. Cell (0, 0) has its corner at `origin`
+/// and columns and rows go the same way as x and y, so the same layout code works whichever way
+/// y points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellLayout {
+    pub origin: Point2,
+    pub cell_wh: Vec2,
+}
+
+impl CellLayout {
+    pub fn new(origin: Point2, cell_wh: Vec2) -> Self {
+        Self { origin, cell_wh }
+    }
+
+    /// The corner of a cell closest to the origin
+    pub fn cell_to_world(&self, x: usize, y: usize) -> Point2 {
+        self.origin + vec2(x as f32, y as f32) * self.cell_wh
+    }
+
+    pub fn cell_center(&self, x: usize, y: usize) -> Point2 {
+        self.cell_to_world(x, y) + self.cell_wh / 2.0
+    }
+
+    pub fn cell_rect(&self, x: usize, y: usize) -> Rect {
+        let corner = self.cell_to_world(x, y);
+
+        Rect::from_corners(corner, corner + self.cell_wh)
+    }
+
+    /// The column and row `xy` falls in, which may be outside of any grid
+    pub fn world_to_cell(&self, xy: Point2) -> (isize, isize) {
+        let cell = ((xy - self.origin) / self.cell_wh).floor();
+
+        (cell.x as isize, cell.y as isize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookups() {
+        let grid = Grid::from_fn(4, 3, |x, y| x * 10 + y);
+
+        assert_eq!(grid.get(3, 2), Some(&32));
+        assert_eq!(grid.get(4, 0), None);
+        assert_eq!(grid.get_wrapping(-1, 3), &30);
+        assert_eq!(grid.get_clamped(-5, 7), &2);
+        assert_eq!(grid.row(1), &[1, 11, 21, 31]);
+        assert_eq!(
+            grid.column(2).cloned().collect::<Vec<_>>(),
+            vec![20, 21, 22]
+        );
+        assert_eq!(
+            grid.neighbours_4(0, 0).collect::<Vec<_>>(),
+            vec![(1, 0), (0, 1)]
+        );
+        assert_eq!(grid.neighbours_8(1, 1).count(), 8);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_columns_off_the_grid_panic() {
+        Grid::new(4, 3, 0).column(4);
+    }
+
+    #[test]
+    fn test_cell_layout_round_trips() {
+        let grid = Grid::new(5, 5, ());
+        let layout = CellLayout::new(pt2(-50.0, -50.0), vec2(20.0, 20.0));
+
+        assert_eq!(
+            grid.cell_at(&layout, layout.cell_center(3, 1)),
+            Some((3, 1))
+        );
+        assert_eq!(grid.cell_at(&layout, pt2(-50.1, 0.0)), None);
+        assert_eq!(layout.cell_rect(0, 0).top(), -30.0);
+    }
+}
@@ -0,0 +1,345 @@
+//! Undo and redo. A generator describes the changes it allows as edits that know how to put
+//! themselves back, and a [`History`] keeps the ones that have been made. Histories can be saved
+//! next to the file they belong to, so a hand-curated piece can be rebuilt from its params and
+//! the edits made on top of them. Histories that are going to be replayed like that can't forget
+//! their oldest edits, so they're made with [`History::unlimited`].
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// How many edits are kept before the oldest are forgotten
+const DEFAULT_LIMIT: usize = 256;
+
+/// A reversible change to a `T`
+pub trait Edit<T> {
+    fn apply(&self, target: &mut T);
+
+    fn revert(&self, target: &mut T);
+
+    /// Fold `next` into this edit so that it's undone along with it, returning whether it was.
+    /// Dragging a slider makes an edit per frame but should only take one undo to put back.
+    fn merge(&mut self, _next: &Self) -> bool {
+        false
+    }
+}
+
+/// Swapping a whole value for another, the simplest edit for a generator's params
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replace<V> {
+    pub before: V,
+    pub after: V,
+}
+
+impl<V: Clone> Edit<V> for Replace<V> {
+    fn apply(&self, target: &mut V) {
+        *target = self.after.clone();
+    }
+
+    fn revert(&self, target: &mut V) {
+        *target = self.before.clone();
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        self.after = next.after.clone();
+
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct History<E> {
+    /// How many edits are kept, or `None` to keep them all
+    limit: Option<usize>,
+    done: Vec<E>,
+    undone: Vec<E>,
+    /// Whether the next edit may be merged into the last one, see [`History::seal`]
+    #[serde(skip)]
+    is_open: bool,
+}
+
+impl<E> Default for History<E> {
+    fn default() -> Self {
+        Self::with_limit(DEFAULT_LIMIT)
+    }
+}
+
+impl<E> History<E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            limit: Some(limit.max(1)),
+            done: Vec::new(),
+            undone: Vec::new(),
+            is_open: false,
+        }
+    }
+
+    /// A history that keeps every edit, so that replaying it always rebuilds the whole piece
+    pub fn unlimited() -> Self {
+        Self {
+            limit: None,
+            ..Self::default()
+        }
+    }
+
+    /// Keep every edit from now on, for histories loaded from files saved with a limit
+    pub fn remove_limit(&mut self) {
+        self.limit = None;
+    }
+
+    /// Make `edit` to `target` and remember it
+    pub fn apply<T>(&mut self, edit: E, target: &mut T)
+    where
+        E: Edit<T>,
+    {
+        edit.apply(target);
+        self.record(edit);
+    }
+
+    /// Make `edit` to `target` and remember it as an edit of its own, one that nothing before or
+    /// after it merges into, like picking a new seed
+    pub fn apply_alone<T>(&mut self, edit: E, target: &mut T)
+    where
+        E: Edit<T>,
+    {
+        edit.apply(target);
+        self.record_alone(edit);
+    }
+
+    /// Remember an edit that has already been made, see [`History::apply_alone`]
+    pub fn record_alone<T>(&mut self, edit: E)
+    where
+        E: Edit<T>,
+    {
+        self.seal();
+        self.record(edit);
+        self.seal();
+    }
+
+    /// Remember an edit that has already been made. Anything that was undone can't be redone
+    /// afterwards.
+    pub fn record<T>(&mut self, edit: E)
+    where
+        E: Edit<T>,
+    {
+        self.undone.clear();
+
+        if self.is_open {
+            if let Some(last) = self.done.last_mut() {
+                if last.merge(&edit) {
+                    return;
+                }
+            }
+        }
+
+        self.done.push(edit);
+        self.is_open = true;
+
+        if let Some(limit) = self.limit {
+            if self.done.len() > limit {
+                let _ = self.done.remove(0);
+            }
+        }
+    }
+
+    /// Stop the next edit merging into the last one, for when the mouse is let go of a slider
+    pub fn seal(&mut self) {
+        self.is_open = false;
+    }
+
+    /// Put back the last edit, returning whether there was one
+    pub fn undo<T>(&mut self, target: &mut T) -> bool
+    where
+        E: Edit<T>,
+    {
+        self.is_open = false;
+
+        match self.done.pop() {
+            Some(edit) => {
+                edit.revert(target);
+                self.undone.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make the last undone edit again, returning whether there was one
+    pub fn redo<T>(&mut self, target: &mut T) -> bool
+    where
+        E: Edit<T>,
+    {
+        self.is_open = false;
+
+        match self.undone.pop() {
+            Some(edit) => {
+                edit.apply(target);
+                self.done.push(edit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make every remembered edit in order, to rebuild a piece from where it started
+    pub fn replay<T>(&self, target: &mut T)
+    where
+        E: Edit<T>,
+    {
+        for edit in self.done.iter() {
+            edit.apply(target);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// The edits that have been made, oldest first
+    pub fn edits(&self) -> &[E] {
+        &self.done
+    }
+
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+        self.is_open = false;
+    }
+}
+
+impl<E: Serialize> History<E> {
+    pub fn save(&self, file_path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+        let file_path = file_path.as_ref();
+        let contents = serde_json::to_string_pretty(self)?;
+
+        std::fs::write(file_path, contents).with_context(|| {
+            format!(
+                "couldn't write history file '{}'",
+                file_path.to_string_lossy()
+            )
+        })
+    }
+}
+
+impl<E: DeserializeOwned> History<E> {
+    pub fn load(file_path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let file_path = file_path.as_ref();
+        let contents = std::fs::read_to_string(file_path).with_context(|| {
+            format!(
+                "couldn't read history file '{}'",
+                file_path.to_string_lossy()
+            )
+        })?;
+
+        serde_json::from_str(&contents).with_context(|| {
+            format!(
+                "'{}' isn't a valid history file",
+                file_path.to_string_lossy()
+            )
+        })
+    }
+}
+
+/// Where the history for `file_path` is kept, e.g. "dune.toml" keeps its history in
+/// "dune.history.json"
+pub fn history_path_for(file_path: impl AsRef<Path>) -> PathBuf {
+    file_path.as_ref().with_extension("history.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slider_drags_undo_in_one_step() {
+        let mut value = 0;
+        let mut history = History::new();
+
+        for next in 1..=5 {
+            history.apply(
+                Replace {
+                    before: value,
+                    after: next,
+                },
+                &mut value,
+            );
+        }
+        history.seal();
+        history.apply(
+            Replace {
+                before: value,
+                after: 10,
+            },
+            &mut value,
+        );
+
+        assert!(history.undo(&mut value));
+        assert_eq!(value, 5);
+        assert!(history.undo(&mut value));
+        assert_eq!(value, 0);
+        assert!(!history.undo(&mut value));
+
+        assert!(history.redo(&mut value));
+        assert_eq!(value, 5);
+    }
+
+    #[test]
+    fn test_a_drag_after_a_new_seed_undoes_on_its_own() {
+        let mut value = 0;
+        let mut history = History::new();
+
+        // a new seed, then a slider dragged through a few values
+        history.apply_alone(
+            Replace {
+                before: value,
+                after: 1,
+            },
+            &mut value,
+        );
+        for next in 2..=4 {
+            history.apply(
+                Replace {
+                    before: value,
+                    after: next,
+                },
+                &mut value,
+            );
+        }
+
+        assert!(history.undo(&mut value));
+        assert_eq!(value, 1);
+        assert!(history.undo(&mut value));
+        assert_eq!(value, 0);
+    }
+
+    #[test]
+    fn test_unlimited_histories_replay_every_edit() {
+        let mut value = 0;
+        let mut history = History::unlimited();
+
+        for next in 1..=DEFAULT_LIMIT + 10 {
+            history.apply(
+                Replace {
+                    before: value,
+                    after: next,
+                },
+                &mut value,
+            );
+            history.seal();
+        }
+
+        let mut replayed = 0;
+        history.replay(&mut replayed);
+        assert_eq!(history.edits().len(), DEFAULT_LIMIT + 10);
+        assert_eq!(replayed, value);
+        assert_eq!(history.edits()[0].before, 0);
+    }
+}
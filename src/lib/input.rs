@@ -0,0 +1,548 @@
+//! Mouse and keyboard state for the generators, fed from window events and read once a frame.
+//!
+//! Keys are looked up in [`KeyBindings`] to turn them into [`Action`]s, so every generator
+//! answers to the same keys for the same things. The built-in bindings can be changed with a
+//! TOML file named by `KEY_BINDINGS` (or `key_bindings.toml`), mapping key names to action names:
+//!
+//! ```toml
+//! R = "regenerate"
+//! F5 = "regenerate"
+//! Z = "none"
+//! ```
+
+use crate::config::config;
+use crate::export::ExportFormat;
+use anyhow::Context;
+use log::{info, warn};
+use nannou::event::MouseScrollDelta;
+use nannou::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+const DEFAULT_KEY_BINDINGS_PATH: &str = "key_bindings.toml";
+/// How far, in points, one notch of a scroll wheel scrolls
+const SCROLL_LINE_HEIGHT: f32 = 20.0;
+
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+pub enum ButtonState {
+    JustPressed,
+    HeldDown,
+    JustReleased,
+    Released,
+}
+
+impl ButtonState {
+    pub fn update(&mut self, button_is_pressed: bool) {
+        use ButtonState::*;
+
+        if button_is_pressed {
+            match self {
+                JustPressed => *self = HeldDown,
+                JustReleased | Released => *self = JustPressed,
+                _ => (),
+            }
+        } else {
+            match self {
+                JustReleased => *self = Released,
+                JustPressed | HeldDown => *self = JustReleased,
+                _ => (),
+            }
+        }
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        *self == ButtonState::JustPressed || *self == ButtonState::HeldDown
+    }
+
+    pub fn is_just_pressed(&self) -> bool {
+        *self == ButtonState::JustPressed
+    }
+
+    pub fn is_just_released(&self) -> bool {
+        *self == ButtonState::JustReleased
+    }
+}
+
+impl Default for ButtonState {
+    fn default() -> Self {
+        ButtonState::Released
+    }
+}
+
+/// Something a key can be bound to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Build the drawing again from the current parameters
+    Regenerate,
+    /// Pick a new seed at random and regenerate
+    NewSeed,
+    ExportSvg,
+    ExportDxf,
+    /// Export in each of the formats the config lists, generators see this as an `ExportSvg`,
+    /// `ExportDxf` or both
+    Export,
+    /// Switch between the drawing and how it will look on paper
+    TogglePaperPreview,
+    Undo,
+    Redo,
+    /// Zoom and pan so the whole drawing is in the window
+    FitToWindow,
+    /// Go back to showing the drawing at 1:1
+    ActualSize,
+    /// An action only one generator knows about, named in its own bindings
+    Custom(String),
+}
+
+impl Action {
+    pub fn custom(name: &str) -> Self {
+        Action::Custom(name.to_owned())
+    }
+
+    /// The name used for the action in key binding files
+    pub fn name(&self) -> &str {
+        match self {
+            Action::Regenerate => "regenerate",
+            Action::NewSeed => "new seed",
+            Action::ExportSvg => "export svg",
+            Action::ExportDxf => "export dxf",
+            Action::Export => "export",
+            Action::TogglePaperPreview => "toggle paper preview",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::FitToWindow => "fit to window",
+            Action::ActualSize => "actual size",
+            Action::Custom(name) => name,
+        }
+    }
+
+    fn from_name(name: &str) -> Self {
+        [
+            Action::Regenerate,
+            Action::NewSeed,
+            Action::ExportSvg,
+            Action::ExportDxf,
+            Action::Export,
+            Action::TogglePaperPreview,
+            Action::Undo,
+            Action::Redo,
+            Action::FitToWindow,
+            Action::ActualSize,
+        ]
+        .iter()
+        .find(|action| action.name() == name)
+        .cloned()
+        .unwrap_or_else(|| Action::custom(name))
+    }
+}
+
+/// Which key does what
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    bindings: HashMap<Key, Action>,
+}
+
+impl KeyBindings {
+    /// No keys bound to anything
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// The built-in bindings with any changes from the key bindings file
+    pub fn load() -> Self {
+        Self::default().load_overrides()
+    }
+
+    /// Apply the changes in the file named by `KEY_BINDINGS` (or `key_bindings.toml`) on top of
+    /// these bindings, leaving them as they are if there's no such file or it can't be read
+    pub fn load_overrides(mut self) -> Self {
+        let path =
+            std::env::var("KEY_BINDINGS").unwrap_or_else(|_| DEFAULT_KEY_BINDINGS_PATH.to_owned());
+        let path = Path::new(&path);
+
+        if !path.exists() {
+            info!(
+                "no key bindings found at '{}', using the built-in bindings",
+                path.to_string_lossy()
+            );
+            return self;
+        }
+
+        let result = std::fs::read_to_string(path)
+            .with_context(|| format!("couldn't read key bindings '{}'", path.to_string_lossy()))
+            .and_then(|contents| {
+                self.apply_toml(&contents).with_context(|| {
+                    format!("couldn't load key bindings '{}'", path.to_string_lossy())
+                })
+            });
+        if let Err(err) = result {
+            warn!("{:?}, using the built-in bindings", err);
+        }
+
+        self
+    }
+
+    /// Bind the keys listed in `contents`, a table of key names to action names. An action of
+    /// "none" unbinds the key. Nothing changes unless every line is understood.
+    pub fn apply_toml(&mut self, contents: &str) -> Result<(), anyhow::Error> {
+        let table: BTreeMap<String, String> = toml::from_str(contents)?;
+        let mut changes = Vec::with_capacity(table.len());
+
+        for (key_name, action_name) in table.iter() {
+            let key = key_from_name(key_name)
+                .with_context(|| format!("'{}' isn't a key that can be bound", key_name))?;
+            let action = if action_name == "none" {
+                None
+            } else {
+                let action = Action::from_name(action_name);
+                // generators' own actions can be bound too, so only a typo in a built-in one is
+                // caught here
+                if let Action::Custom(_) = action {
+                    warn!(
+                        "'{}' isn't a built-in action, '{}' only does something in generators that have it",
+                        action_name, key_name
+                    );
+                }
+
+                Some(action)
+            };
+
+            changes.push((key, action));
+        }
+
+        for (key, action) in changes {
+            match action {
+                Some(action) => self.bind(key, action),
+                None => self.unbind(key),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn bind(&mut self, key: Key, action: Action) {
+        self.bindings.insert(key, action);
+    }
+
+    pub fn unbind(&mut self, key: Key) {
+        self.bindings.remove(&key);
+    }
+
+    /// Like [`KeyBindings::bind`] but for building up bindings, e.g. a generator's own keys
+    pub fn with(mut self, key: Key, action: Action) -> Self {
+        self.bind(key, action);
+        self
+    }
+
+    pub fn action_for(&self, key: Key) -> Option<&Action> {
+        self.bindings.get(&key)
+    }
+
+    pub fn keys_for(&self, action: &Action) -> Vec<Key> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::empty()
+            .with(Key::R, Action::Regenerate)
+            .with(Key::N, Action::NewSeed)
+            .with(Key::S, Action::ExportSvg)
+            .with(Key::D, Action::ExportDxf)
+            .with(Key::E, Action::Export)
+            .with(Key::V, Action::TogglePaperPreview)
+            .with(Key::Z, Action::Undo)
+            .with(Key::Y, Action::Redo)
+            .with(Key::F, Action::FitToWindow)
+            .with(Key::Key0, Action::ActualSize)
+    }
+}
+
+/// A mouse drag, from the button going down until it comes back up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drag {
+    pub button: MouseButton,
+    /// Where the mouse was when the button went down
+    pub start: Point2,
+}
+
+pub struct Input {
+    bindings: KeyBindings,
+    mouse_xy: Point2,
+    /// Where the mouse was at the last update, for working out how far it's moved since
+    last_mouse_xy: Point2,
+    mouse_delta: Vec2,
+    /// The buttons and keys that are down right now, as reported by window events
+    buttons_down: HashSet<MouseButton>,
+    keys_down: HashSet<Key>,
+    /// The buttons and keys as of the last update
+    buttons: HashMap<MouseButton, ButtonState>,
+    keys: HashMap<Key, ButtonState>,
+    drag: Option<Drag>,
+    /// Scrolling since the last update
+    pending_scroll: Vec2,
+    scroll: Vec2,
+    /// Actions from keys pressed since the last update
+    pending_actions: Vec<Action>,
+    actions: Vec<Action>,
+}
+
+impl Input {
+    pub fn new(bindings: KeyBindings) -> Self {
+        Self {
+            bindings,
+            mouse_xy: Point2::default(),
+            last_mouse_xy: Point2::default(),
+            mouse_delta: Vec2::default(),
+            buttons_down: HashSet::new(),
+            keys_down: HashSet::new(),
+            buttons: HashMap::new(),
+            keys: HashMap::new(),
+            drag: None,
+            pending_scroll: Vec2::default(),
+            scroll: Vec2::default(),
+            pending_actions: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Pass every window event through here, from the window's `event` function
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::MouseMoved(xy) => self.mouse_xy = *xy,
+            WindowEvent::MousePressed(button) => {
+                self.buttons_down.insert(*button);
+
+                if self.drag.is_none() {
+                    self.drag = Some(Drag {
+                        button: *button,
+                        start: self.mouse_xy,
+                    });
+                }
+            }
+            WindowEvent::MouseReleased(button) => {
+                self.buttons_down.remove(button);
+
+                if self.drag.map(|drag| drag.button) == Some(*button) {
+                    self.drag = None;
+                }
+            }
+            WindowEvent::MouseWheel(delta, _) => {
+                self.pending_scroll += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => vec2(*x, *y) * SCROLL_LINE_HEIGHT,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        vec2(position.x as f32, position.y as f32)
+                    }
+                };
+            }
+            WindowEvent::KeyPressed(key) => {
+                // held keys repeat, but a held key shouldn't export a file for every repeat
+                if self.keys_down.insert(*key) {
+                    match self.bindings.action_for(*key) {
+                        Some(Action::Export) => {
+                            for format in config().export_formats.value.iter() {
+                                self.pending_actions.push(match format {
+                                    ExportFormat::Svg => Action::ExportSvg,
+                                    ExportFormat::Dxf => Action::ExportDxf,
+                                });
+                            }
+                        }
+                        Some(action) => self.pending_actions.push(action.clone()),
+                        None => (),
+                    }
+                }
+            }
+            WindowEvent::KeyReleased(key) => {
+                self.keys_down.remove(key);
+            }
+            _ => (),
+        }
+    }
+
+    /// Move on to a new frame, call this once at the start of every update
+    pub fn update(&mut self) {
+        for button in self.buttons_down.iter() {
+            self.buttons.entry(*button).or_default();
+        }
+        for (button, state) in self.buttons.iter_mut() {
+            state.update(self.buttons_down.contains(button));
+        }
+
+        for key in self.keys_down.iter() {
+            self.keys.entry(*key).or_default();
+        }
+        for (key, state) in self.keys.iter_mut() {
+            state.update(self.keys_down.contains(key));
+        }
+
+        self.mouse_delta = self.mouse_xy - self.last_mouse_xy;
+        self.last_mouse_xy = self.mouse_xy;
+        self.scroll = std::mem::take(&mut self.pending_scroll);
+        self.actions = std::mem::take(&mut self.pending_actions);
+    }
+
+    /// Where the mouse is, in window coordinates
+    pub fn mouse_xy(&self) -> Point2 {
+        self.mouse_xy
+    }
+
+    /// How far the mouse moved between the last two updates
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    pub fn button(&self, button: MouseButton) -> ButtonState {
+        self.buttons.get(&button).cloned().unwrap_or_default()
+    }
+
+    pub fn key(&self, key: Key) -> ButtonState {
+        self.keys.get(&key).cloned().unwrap_or_default()
+    }
+
+    /// The drag that's happening right now, if any
+    pub fn drag(&self) -> Option<Drag> {
+        self.drag
+    }
+
+    /// How far the wheel scrolled between the last two updates, in points
+    pub fn scroll(&self) -> Vec2 {
+        self.scroll
+    }
+
+    /// The actions whose keys were pressed between the last two updates, in the order they were
+    /// pressed
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    pub fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut KeyBindings {
+        &mut self.bindings
+    }
+}
+
+/// The key for a name like "R", "7", "F5" or "Space"
+fn key_from_name(name: &str) -> Option<Key> {
+    const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Key0,
+        Key::Key1,
+        Key::Key2,
+        Key::Key3,
+        Key::Key4,
+        Key::Key5,
+        Key::Key6,
+        Key::Key7,
+        Key::Key8,
+        Key::Key9,
+    ];
+    const FUNCTION_KEYS: [Key; 12] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+    ];
+    const OTHER_KEYS: [(&str, Key); 14] = [
+        ("space", Key::Space),
+        ("escape", Key::Escape),
+        ("return", Key::Return),
+        ("enter", Key::Return),
+        ("tab", Key::Tab),
+        ("backspace", Key::Back),
+        ("delete", Key::Delete),
+        ("left", Key::Left),
+        ("right", Key::Right),
+        ("up", Key::Up),
+        ("down", Key::Down),
+        ("minus", Key::Minus),
+        ("equals", Key::Equals),
+        ("comma", Key::Comma),
+    ];
+
+    let name = name.trim().to_lowercase();
+    let mut chars = name.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(letter @ 'a'..='z'), None) => Some(LETTERS[(letter as u8 - b'a') as usize]),
+        (Some(digit @ '0'..='9'), None) => Some(DIGITS[(digit as u8 - b'0') as usize]),
+        (Some('f'), Some(_)) => {
+            let number: usize = name[1..].parse().ok()?;
+            FUNCTION_KEYS.get(number.checked_sub(1)?).cloned()
+        }
+        _ => OTHER_KEYS
+            .iter()
+            .find(|(other_name, _)| *other_name == name)
+            .map(|(_, key)| *key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings_file_overrides_defaults() {
+        let mut bindings = KeyBindings::default();
+        bindings
+            .apply_toml("F5 = \"regenerate\"\nZ = \"none\"\nt = \"longer lines\"\n")
+            .unwrap();
+
+        assert_eq!(bindings.action_for(Key::F5), Some(&Action::Regenerate));
+        assert_eq!(bindings.action_for(Key::R), Some(&Action::Regenerate));
+        assert_eq!(bindings.action_for(Key::Z), None);
+        assert_eq!(
+            bindings.action_for(Key::T),
+            Some(&Action::custom("longer lines"))
+        );
+
+        // a bad key leaves everything as it was
+        assert!(bindings
+            .apply_toml("F5 = \"none\"\nHyper = \"undo\"\n")
+            .is_err());
+        assert_eq!(bindings.action_for(Key::F5), Some(&Action::Regenerate));
+    }
+}
@@ -0,0 +1,565 @@
+use crate::geometry::Drawing;
+use crate::pen_change::{format_duration, PenChangePrompt, PlotterSpeeds};
+use crate::plotter::{PlotCommand, Plotter, FLATTENING_TOLERANCE_MM};
+use anyhow::{bail, Context};
+use log::{debug, info};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where the carriage waits while pens get swapped. Home is in the corner, out of the way.
+pub const PARK_POSITION: Point2 = Vec2::ZERO;
+
+/// One pen-down stroke, already converted to plotter millimeters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobPath {
+    pub layer: String,
+    pub points: Vec<(f32, f32)>,
+}
+
+impl JobPath {
+    pub fn points(&self) -> impl Iterator<Item = Point2> + '_ {
+        self.points.iter().map(|(x, y)| pt2(*x, *y))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    /// Every path has been plotted
+    Finished,
+    /// Plotting stopped because it was asked to, it can be picked back up with `run`
+    Paused,
+}
+
+/// Lets another thread (or a UI button) ask a running job to stop after the path it's plotting
+#[derive(Debug, Clone, Default)]
+pub struct PauseHandle(Arc<AtomicBool>);
+
+impl PauseHandle {
+    pub fn pause(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn clear(&self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// A plot that remembers how far along it got. The paths are written to the job file once and
+/// how many have been plotted is written to a small progress file beside it after every path, so
+/// that a dry pen, a crash or a power cut only costs the path that was being drawn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlotJob {
+    paths: Vec<JobPath>,
+    /// The order to plot `paths` in, usually the output of an optimizer
+    order: Vec<usize>,
+    /// How many entries of `order` have been plotted, as of the last time the whole job was
+    /// saved. The progress file has the latest count.
+    #[serde(default)]
+    completed: usize,
+    #[serde(skip)]
+    file_path: PathBuf,
+    /// Whether the job file has the current paths and order
+    #[serde(skip)]
+    is_saved: bool,
+}
+
+impl PlotJob {
+    /// Create a job for every shape in `drawing`, plotted layer by layer in the order the shapes
+    /// were added. `mm_per_unit` converts drawing units into millimeters on paper.
+    pub fn new(file_path: impl Into<PathBuf>, drawing: &Drawing, mm_per_unit: f32) -> Self {
+        let tolerance = FLATTENING_TOLERANCE_MM / mm_per_unit;
+        let paths: Vec<_> = drawing
+            .layers
+            .iter()
+            .flat_map(|layer| {
+                layer.shapes.iter().map(move |shape| JobPath {
+                    layer: layer.name.clone(),
+                    points: shape
+                        .to_polyline(tolerance)
+                        .into_iter()
+                        .map(|xy| (xy.x * mm_per_unit, xy.y * mm_per_unit))
+                        .collect(),
+                })
+            })
+            .filter(|path| !path.points.is_empty())
+            .collect();
+        let order = (0..paths.len()).collect();
+
+        Self {
+            paths,
+            order,
+            completed: 0,
+            file_path: file_path.into(),
+            is_saved: false,
+        }
+    }
+
+    /// Use a different plotting order, e.g. one that keeps pen-up travel short. Paths stay
+    /// grouped by layer so each pen only has to be put in once, only the order within a layer
+    /// changes.
+    pub fn with_order(mut self, mut order: Vec<usize>) -> Result<Self, anyhow::Error> {
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+
+        if !sorted.iter().cloned().eq(0..self.paths.len()) {
+            bail!(
+                "a plot order must list each of the job's {} paths exactly once",
+                self.paths.len()
+            );
+        }
+
+        let layer_names = self.layer_names();
+        order.sort_by_key(|index| {
+            layer_names
+                .iter()
+                .position(|name| *name == self.paths[*index].layer)
+        });
+
+        self.order = order;
+        self.is_saved = false;
+        Ok(self)
+    }
+
+    /// The names of the job's layers in the order they first appear
+    pub fn layer_names(&self) -> Vec<String> {
+        let mut layer_names: Vec<String> = Vec::new();
+
+        for path in self.paths.iter() {
+            if !layer_names.contains(&path.layer) {
+                layer_names.push(path.layer.clone());
+            }
+        }
+
+        layer_names
+    }
+
+    pub fn load(file_path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let file_path = file_path.as_ref();
+        let contents = std::fs::read_to_string(file_path)
+            .with_context(|| format!("couldn't read job file '{}'", file_path.to_string_lossy()))?;
+        let mut job: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("'{}' isn't a valid job file", file_path.to_string_lossy()))?;
+        job.file_path = file_path.to_path_buf();
+        job.is_saved = true;
+
+        let progress_path = job.progress_path();
+        if progress_path.exists() {
+            let progress = std::fs::read_to_string(&progress_path).with_context(|| {
+                format!(
+                    "couldn't read progress file '{}'",
+                    progress_path.to_string_lossy()
+                )
+            })?;
+            job.completed = progress.trim().parse().with_context(|| {
+                format!(
+                    "'{}' isn't a valid progress file",
+                    progress_path.to_string_lossy()
+                )
+            })?;
+        }
+
+        if job.completed > job.order.len() {
+            bail!(
+                "job file '{}' claims more paths were completed than it contains",
+                file_path.to_string_lossy()
+            );
+        }
+
+        Ok(job)
+    }
+
+    /// Write the whole job to its file, and its progress beside it. Both files are replaced in
+    /// one step, so that losing power part way through saving never leaves a half-written job
+    /// behind.
+    pub fn save(&mut self) -> Result<(), anyhow::Error> {
+        let contents = serde_json::to_string(self)?;
+        write_atomically(&self.file_path, contents.as_bytes())
+            .with_context(|| format!("couldn't save job '{}'", self.file_path.to_string_lossy()))?;
+        self.is_saved = true;
+
+        self.save_progress()
+    }
+
+    /// Write how many paths have been plotted, which is all that changes while a job runs
+    pub fn save_progress(&self) -> Result<(), anyhow::Error> {
+        let progress_path = self.progress_path();
+
+        write_atomically(&progress_path, format!("{}\n", self.completed).as_bytes()).with_context(
+            || {
+                format!(
+                    "couldn't save progress to '{}'",
+                    progress_path.to_string_lossy()
+                )
+            },
+        )
+    }
+
+    pub fn file_path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// Where the progress is kept, `plot.progress` for `plot.json`
+    pub fn progress_path(&self) -> PathBuf {
+        self.file_path.with_extension("progress")
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// How many paths have been plotted so far
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.completed >= self.order.len()
+    }
+
+    /// The paths in the order they get plotted
+    pub fn ordered_paths(&self) -> impl Iterator<Item = &JobPath> + '_ {
+        self.order.iter().map(move |index| &self.paths[*index])
+    }
+
+    /// The next path to be plotted, if there are any left
+    pub fn next_path(&self) -> Option<&JobPath> {
+        self.order
+            .get(self.completed)
+            .map(|index| &self.paths[*index])
+    }
+
+    /// How long each of the layers that haven't been finished yet should take to plot
+    pub fn layer_estimates(&self, speeds: &PlotterSpeeds) -> Vec<(String, Duration)> {
+        let mut estimates = Vec::new();
+        let mut index = self.completed;
+
+        while index < self.order.len() {
+            let layer = self.paths[self.order[index]].layer.clone();
+            let count = self.layer_paths_from(index).count();
+
+            estimates.push((
+                layer,
+                speeds.estimate(PARK_POSITION, self.layer_paths_from(index)),
+            ));
+            index += count;
+        }
+
+        estimates
+    }
+
+    /// The paths from `index` up to the end of the layer that path belongs to
+    fn layer_paths_from(&self, index: usize) -> impl Iterator<Item = &JobPath> + '_ {
+        let layer = self
+            .order
+            .get(index)
+            .map(|path_index| self.paths[*path_index].layer.as_str());
+
+        self.order[index.min(self.order.len())..]
+            .iter()
+            .map(move |path_index| &self.paths[*path_index])
+            .take_while(move |path| Some(path.layer.as_str()) == layer)
+    }
+
+    /// Continue from the `index`th path (counting from zero) the next time the job is run
+    pub fn restart_from(&mut self, index: usize) -> Result<(), anyhow::Error> {
+        if index > self.order.len() {
+            bail!(
+                "can't restart from path {}, the job only has {} paths",
+                index,
+                self.order.len()
+            );
+        }
+
+        self.completed = index;
+        self.save_progress()
+    }
+
+    /// Plot the last `count` completed paths again, e.g. after swapping a pen that ran dry
+    pub fn replot_last(&mut self, count: usize) -> Result<(), anyhow::Error> {
+        self.restart_from(self.completed.saturating_sub(count))
+    }
+
+    /// Plot paths until the job is finished or `pause_handle` is paused. Progress is saved
+    /// after every path.
+    pub fn run(
+        &mut self,
+        plotter: &mut impl Plotter,
+        pause_handle: &PauseHandle,
+    ) -> Result<JobStatus, anyhow::Error> {
+        pause_handle.clear();
+        if !self.is_saved {
+            self.save()?;
+        }
+        info!(
+            "resuming job '{}' at path {} of {}",
+            self.file_path.to_string_lossy(),
+            self.completed,
+            self.order.len()
+        );
+
+        while let Some(path) = self.next_path() {
+            if pause_handle.is_paused() {
+                plotter.execute(PlotCommand::PenUp)?;
+                info!("job paused after {} paths", self.completed);
+                return Ok(JobStatus::Paused);
+            }
+
+            plot_path(plotter, path)?;
+            self.completed += 1;
+            self.save_progress()?;
+            debug!("finished path {} of {}", self.completed, self.order.len());
+        }
+
+        Ok(JobStatus::Finished)
+    }
+
+    /// Plot the job one layer at a time. Before each layer the carriage is parked and `prompt` is
+    /// asked to confirm the right pen is in. Progress is saved after every path just like `run`.
+    pub fn run_layers(
+        &mut self,
+        plotter: &mut impl Plotter,
+        prompt: &mut impl PenChangePrompt,
+        speeds: &PlotterSpeeds,
+        pause_handle: &PauseHandle,
+    ) -> Result<JobStatus, anyhow::Error> {
+        pause_handle.clear();
+        if !self.is_saved {
+            self.save()?;
+        }
+        let layer_names = self.layer_names();
+        let mut current_layer = None;
+
+        for (layer, estimate) in self.layer_estimates(speeds) {
+            info!(
+                "layer '{}' should take {}",
+                layer,
+                format_duration(estimate)
+            );
+        }
+
+        while let Some(path) = self.next_path() {
+            if pause_handle.is_paused() {
+                plotter.execute(PlotCommand::PenUp)?;
+                info!("job paused after {} paths", self.completed);
+                return Ok(JobStatus::Paused);
+            }
+
+            if current_layer.as_ref() != Some(&path.layer) {
+                let estimate =
+                    speeds.estimate(PARK_POSITION, self.layer_paths_from(self.completed));
+                park(plotter)?;
+
+                if !prompt.confirm_pen_change(&path.layer, estimate)? {
+                    info!("job stopped before layer '{}'", path.layer);
+                    return Ok(JobStatus::Paused);
+                }
+
+                let pen = layer_names
+                    .iter()
+                    .position(|name| *name == path.layer)
+                    .unwrap_or_default();
+                plotter.execute(PlotCommand::SelectPen(pen))?;
+                current_layer = Some(path.layer.clone());
+            }
+
+            plot_path(plotter, path)?;
+            self.completed += 1;
+            self.save_progress()?;
+            debug!("finished path {} of {}", self.completed, self.order.len());
+        }
+
+        park(plotter)?;
+
+        Ok(JobStatus::Finished)
+    }
+}
+
+/// Replace the file at `path` with `contents` in one step. The new contents are flushed to disk
+/// before they replace the old, otherwise a power cut can leave the rename done but the file empty.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), anyhow::Error> {
+    // `plot.json.tmp`, so the job and its progress don't share one
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    let temporary_path = PathBuf::from(temporary_path);
+
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    std::fs::rename(&temporary_path, path)?;
+
+    // the rename itself lives in the directory, which only some platforms let us flush
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    if let Ok(directory) = File::open(directory) {
+        let _ = directory.sync_all();
+    }
+
+    Ok(())
+}
+
+fn park(plotter: &mut impl Plotter) -> Result<(), anyhow::Error> {
+    plotter.execute(PlotCommand::PenUp)?;
+    plotter.execute(PlotCommand::MoveTo(PARK_POSITION))
+}
+
+pub(crate) fn plot_path(plotter: &mut impl Plotter, path: &JobPath) -> Result<(), anyhow::Error> {
+    let mut points = path.points();
+
+    if let Some(start) = points.next() {
+        plotter.execute(PlotCommand::PenUp)?;
+        plotter.execute(PlotCommand::MoveTo(start))?;
+        plotter.execute(PlotCommand::PenDown)?;
+
+        for xy in points {
+            plotter.execute(PlotCommand::MoveTo(xy))?;
+        }
+
+        plotter.execute(PlotCommand::PenUp)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Shape;
+    use crate::plotter::Simulator;
+
+    /// Stands in for a plotter that gets unplugged after drawing a few lines
+    struct UnpluggedPlotter {
+        simulator: Simulator,
+        moves_left: usize,
+    }
+
+    impl Plotter for UnpluggedPlotter {
+        fn execute(&mut self, command: PlotCommand) -> Result<(), anyhow::Error> {
+            if let PlotCommand::MoveTo(_) = command {
+                if self.moves_left == 0 {
+                    bail!("the plotter was unplugged");
+                }
+
+                self.moves_left -= 1;
+            }
+
+            self.simulator.execute(command)
+        }
+    }
+
+    fn test_drawing() -> Drawing {
+        let mut drawing = Drawing::new(100.0, 100.0);
+        let layer = drawing.layer_mut("lines");
+
+        for index in 0..5 {
+            let y = index as f32 * 10.0;
+            layer.push(Shape::Polyline(vec![pt2(0.0, y), pt2(50.0, y)]));
+        }
+
+        drawing
+    }
+
+    #[test]
+    fn test_resume_after_crash() {
+        let file_path = std::env::temp_dir().join("plotlings-test-resume-after-crash.json");
+        let mut job = PlotJob::new(&file_path, &test_drawing(), 1.0)
+            .with_order(vec![4, 3, 2, 1, 0])
+            .unwrap();
+        let pause_handle = PauseHandle::default();
+
+        // Each line takes two moves, so this dies half way through the third line
+        let mut plotter = UnpluggedPlotter {
+            simulator: Simulator::new(),
+            moves_left: 5,
+        };
+        assert!(job.run(&mut plotter, &pause_handle).is_err());
+
+        let mut job = PlotJob::load(&file_path).unwrap();
+        assert_eq!(job.completed(), 2);
+
+        let mut simulator = Simulator::new();
+        assert_eq!(
+            job.run(&mut simulator, &pause_handle).unwrap(),
+            JobStatus::Finished
+        );
+        assert_eq!(simulator.drawing().shape_count(), 3);
+        assert!(PlotJob::load(&file_path).unwrap().is_finished());
+
+        job.replot_last(2).unwrap();
+        assert_eq!(job.next_path().unwrap().points[0], (0.0, 10.0));
+        // only the progress is saved while plotting, the paths were saved once before it started
+        let saved: PlotJob =
+            serde_json::from_str(&std::fs::read_to_string(&file_path).unwrap()).unwrap();
+        assert_eq!(saved.completed, 0);
+        assert_eq!(PlotJob::load(&file_path).unwrap().completed(), 3);
+
+        let _ = std::fs::remove_file(job.progress_path());
+        let _ = std::fs::remove_file(&file_path);
+    }
+
+    #[derive(Default)]
+    struct MockPenChangePrompt {
+        layers: Vec<String>,
+    }
+
+    impl PenChangePrompt for MockPenChangePrompt {
+        fn confirm_pen_change(
+            &mut self,
+            layer: &str,
+            _estimate: Duration,
+        ) -> Result<bool, anyhow::Error> {
+            self.layers.push(layer.to_owned());
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_pen_change_between_layers() {
+        let file_path = std::env::temp_dir().join("plotlings-test-pen-change.json");
+        let mut drawing = test_drawing();
+        drawing
+            .layer_mut("red")
+            .push(Shape::Polyline(vec![pt2(0.0, 0.0), pt2(0.0, 50.0)]));
+        // Interleave the layers to check they get grouped back together
+        let mut job = PlotJob::new(&file_path, &drawing, 1.0)
+            .with_order(vec![5, 0, 1, 2, 3, 4])
+            .unwrap();
+        let mut prompt = MockPenChangePrompt::default();
+        let mut simulator = Simulator::new();
+
+        let estimates = job.layer_estimates(&PlotterSpeeds::default());
+        assert_eq!(estimates.len(), 2);
+
+        let status = job
+            .run_layers(
+                &mut simulator,
+                &mut prompt,
+                &PlotterSpeeds::default(),
+                &PauseHandle::default(),
+            )
+            .unwrap();
+
+        assert_eq!(status, JobStatus::Finished);
+        assert_eq!(prompt.layers, vec!["lines", "red"]);
+        assert_eq!(simulator.drawing().layers.len(), 2);
+        assert_eq!(simulator.position(), PARK_POSITION);
+
+        let _ = std::fs::remove_file(job.progress_path());
+        let _ = std::fs::remove_file(&file_path);
+    }
+}
@@ -0,0 +1,184 @@
+//! A record of everything that's been exported. Every export appends a line of JSON to
+//! `journal.jsonl` in the export directory saying which generator made it, with what seed and
+//! params, and how much plotting it'll take. The params are also saved as a preset beside the
+//! export, `2021-07-04-plotling.toml` for `2021-07-04-plotling.svg`, so any piece can be opened
+//! again with `--preset`. The `gallery` command turns the journal into a page to browse.
+
+use crate::config::config;
+use crate::geometry::Drawing;
+use crate::optimize::DrawingStats;
+use crate::pen::PIXELS_PER_MM;
+use anyhow::Context;
+use chrono::Local;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const JOURNAL_FILENAME: &str = "journal.jsonl";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// When it was exported, as RFC 3339
+    pub exported_at: String,
+    /// The name of the binary that made it
+    pub generator: String,
+    pub seed: Option<u64>,
+    pub params: serde_json::Value,
+    pub path: PathBuf,
+    /// The preset saved beside the export, if it could be
+    pub preset: Option<PathBuf>,
+    pub width_mm: f32,
+    pub height_mm: f32,
+    pub stats: DrawingStats,
+}
+
+impl JournalEntry {
+    /// The command that opens the generator with this entry's params
+    pub fn reopen_command(&self) -> String {
+        match &self.preset {
+            Some(preset) => format!(
+                "cargo run --release --bin {} -- --preset {}",
+                self.generator,
+                preset.to_string_lossy()
+            ),
+            None => format!("cargo run --release --bin {}", self.generator),
+        }
+    }
+}
+
+/// The journal in the export directory
+pub fn journal_path() -> PathBuf {
+    config().export_directory.value.join(JOURNAL_FILENAME)
+}
+
+/// Note down the export at `path` in the journal and save `params` as a preset beside it.
+/// `generator` is usually `env!("CARGO_BIN_NAME")`.
+pub fn record_export<P: Serialize>(
+    path: &Path,
+    generator: &str,
+    seed: Option<u64>,
+    params: &P,
+    drawing: &Drawing,
+) -> Result<(), anyhow::Error> {
+    record_export_to(&journal_path(), path, generator, seed, params, drawing)
+}
+
+fn record_export_to<P: Serialize>(
+    journal_path: &Path,
+    path: &Path,
+    generator: &str,
+    seed: Option<u64>,
+    params: &P,
+    drawing: &Drawing,
+) -> Result<(), anyhow::Error> {
+    let preset_path = path.with_extension("toml");
+    let preset = match save_preset(&preset_path, params) {
+        Ok(()) => Some(preset_path),
+        Err(err) => {
+            warn!("{:?}", err);
+            None
+        }
+    };
+
+    let entry = JournalEntry {
+        exported_at: Local::now().to_rfc3339(),
+        generator: generator.to_owned(),
+        seed,
+        params: serde_json::to_value(params).context("couldn't note down the params")?,
+        path: path.to_owned(),
+        preset,
+        width_mm: drawing.width / PIXELS_PER_MM,
+        height_mm: drawing.height / PIXELS_PER_MM,
+        stats: DrawingStats::of(drawing, 1.0 / PIXELS_PER_MM),
+    };
+
+    append(journal_path, &entry)
+}
+
+pub fn append(journal_path: &Path, entry: &JournalEntry) -> Result<(), anyhow::Error> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| {
+            format!(
+                "couldn't write to the journal '{}'",
+                journal_path.to_string_lossy()
+            )
+        })
+}
+
+/// Every entry in the journal, oldest first. Lines that can't be read are skipped with a warning
+/// so one bad line doesn't hide the rest.
+pub fn read(journal_path: &Path) -> Result<Vec<JournalEntry>, anyhow::Error> {
+    let contents = std::fs::read_to_string(journal_path).with_context(|| {
+        format!(
+            "couldn't read the journal '{}'",
+            journal_path.to_string_lossy()
+        )
+    })?;
+
+    Ok(contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(index, line)| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                warn!("skipping line {} of the journal: {}", index + 1, err);
+                None
+            }
+        })
+        .collect())
+}
+
+fn save_preset<P: Serialize>(path: &Path, params: &P) -> Result<(), anyhow::Error> {
+    // going through a `Value` puts nested tables after plain values, which TOML insists on
+    let preset = toml::to_string(&toml::Value::try_from(params)?)?;
+
+    std::fs::write(path, preset)
+        .with_context(|| format!("couldn't save preset '{}'", path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::unused_path_in;
+
+    #[derive(Serialize)]
+    struct TestParams {
+        seed: u64,
+    }
+
+    #[test]
+    fn test_exports_on_the_same_day_keep_their_own_presets() {
+        let directory = std::env::temp_dir().join("plotlings-test-export-presets");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        let journal_path = directory.join(JOURNAL_FILENAME);
+        let drawing = Drawing::new(100.0, 100.0);
+
+        for (extension, seed) in [("svg", 1), ("dxf", 2)].iter() {
+            let path = unused_path_in(&directory, "2021-07-04", extension);
+            std::fs::write(&path, "").unwrap();
+            let params = TestParams { seed: *seed };
+            record_export_to(&journal_path, &path, "test", Some(*seed), &params, &drawing).unwrap();
+        }
+
+        let entries = read(&journal_path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_ne!(entries[0].preset, entries[1].preset);
+        for entry in entries.iter() {
+            let preset = std::fs::read_to_string(entry.preset.as_ref().unwrap()).unwrap();
+            assert_eq!(preset.trim(), format!("seed = {}", entry.seed.unwrap()));
+        }
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -1,13 +1,35 @@
-pub mod interval;
+pub mod camera;
+pub mod clock;
+pub mod config;
+pub mod curve;
+pub mod dxf;
+pub mod export;
+pub mod gallery;
+pub mod geometry;
+pub mod grid;
+pub mod history;
+pub mod input;
+pub mod job;
+pub mod journal;
+pub mod noise;
+pub mod optimize;
+pub mod paper;
+pub mod pen;
+pub mod pen_change;
+pub mod plotter;
+pub mod preset;
+pub mod preview;
+pub mod sampling;
+pub mod scaffold;
+pub mod seed;
+pub mod smoothing;
+pub mod spatial;
 pub mod svg;
+pub mod svg_import;
+pub mod worker;
 
 use std::ops::{Add, Div, Mul, Range, Sub};
 
-pub fn get_wrapping_index(x: isize, y: isize, width: usize, height: usize) -> usize {
-    let (width, height) = (width as isize, height as isize);
-    (((y + height) % height) * width + ((x + width) % width)) as usize
-}
-
 pub fn map_t_of_range_a_to_range_b<T>(t: T, range_a: Range<T>, range_b: Range<T>) -> T
 where
     T: Copy + Sub<Output = T> + Div<Output = T> + Add<Output = T> + Mul<Output = T>,
@@ -63,39 +85,3 @@ impl Interpolate for f32 {
         }
     }
 }
-
-#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
-pub enum MouseButtonState {
-    JustPressed,
-    HeldDown,
-    JustReleased,
-    Released,
-}
-
-impl MouseButtonState {
-    pub fn update(&mut self, button_is_pressed: bool) {
-        use MouseButtonState::*;
-
-        if button_is_pressed {
-            match self {
-                JustPressed => *self = HeldDown,
-                JustReleased | Released => *self = JustPressed,
-                _ => (),
-            }
-        } else {
-            match self {
-                JustReleased => *self = Released,
-                JustPressed | HeldDown => *self = JustReleased,
-                _ => (),
-            }
-        }
-    }
-
-    pub fn is_pressed(&self) -> bool {
-        *self == MouseButtonState::JustPressed || *self == MouseButtonState::HeldDown
-    }
-
-    pub fn is_just_pressed(&self) -> bool {
-        *self == MouseButtonState::JustPressed
-    }
-}
@@ -0,0 +1,243 @@
+//! Seeded 2D noise for the generators, layered into fractal Brownian motion and optionally
+//! domain warped. Coordinates are in drawing units, `scale` turns them into noise space, and
+//! values come out roughly between -1 and 1, [`to_unit`] and [`remap`] move them elsewhere.
+
+use crate::map_t_of_range_a_to_range_b;
+use crate::preset::{check_finite, check_range};
+use ::noise::{NoiseFn, OpenSimplex, Perlin, Seedable, SuperSimplex, Value, Worley};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::ops::{Range, RangeInclusive};
+
+/// How far apart, in noise space, the samples used to estimate a slope are
+const SLOPE_EPSILON: f64 = 1e-3;
+/// Offsets for the second warp sample so it doesn't just repeat the first
+const WARP_OFFSET: [f64; 2] = [5.2, 1.3];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NoiseKind {
+    Perlin,
+    OpenSimplex,
+    SuperSimplex,
+    Value,
+    /// Cellular noise, a patchwork of flat cells rather than rolling hills
+    Worley,
+}
+
+impl NoiseKind {
+    /// Every kind, in the order they're listed in the generators' UIs
+    pub const ALL: [NoiseKind; 5] = [
+        NoiseKind::Perlin,
+        NoiseKind::OpenSimplex,
+        NoiseKind::SuperSimplex,
+        NoiseKind::Value,
+        NoiseKind::Worley,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoiseKind::Perlin => "Perlin",
+            NoiseKind::OpenSimplex => "OpenSimplex",
+            NoiseKind::SuperSimplex => "SuperSimplex",
+            NoiseKind::Value => "Value",
+            NoiseKind::Worley => "Worley",
+        }
+    }
+
+    /// The names of every kind, for a drop down list
+    pub fn names() -> Vec<String> {
+        Self::ALL
+            .iter()
+            .map(|kind| kind.name().to_owned())
+            .collect()
+    }
+
+    /// Where this kind is in [`NoiseKind::ALL`]
+    pub fn index(&self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|kind| kind == self)
+            .unwrap_or_default()
+    }
+}
+
+/// Everything needed to build a [`NoiseField`], small enough to keep in a generator's params
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseParams {
+    pub kind: NoiseKind,
+    pub seed: u32,
+    /// Noise space per drawing unit, smaller values give broader features
+    pub scale: f64,
+    /// How many layers of noise are added together, 1 is plain noise
+    pub octaves: u8,
+    /// How much the frequency grows from one octave to the next
+    pub lacunarity: f64,
+    /// How much each octave's amplitude shrinks from the one before it
+    pub gain: f64,
+    /// How far the noise is pushed around by more noise, 0 turns domain warping off
+    pub warp: f64,
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::SuperSimplex,
+            seed: 0,
+            scale: 0.01,
+            octaves: 1,
+            lacunarity: 2.0,
+            gain: 0.5,
+            warp: 0.0,
+        }
+    }
+}
+
+impl NoiseParams {
+    /// Check the params are ones the field can be made from, with `scale` inside `scale_range`
+    pub fn check(&self, scale_range: RangeInclusive<f64>) -> Result<(), anyhow::Error> {
+        check_range("noise scale", self.scale, scale_range)?;
+        check_range("noise octaves", self.octaves, 1..=8)?;
+        check_finite("noise lacunarity", self.lacunarity as f32)?;
+        check_finite("noise gain", self.gain as f32)?;
+        check_finite("noise warp", self.warp as f32)
+    }
+}
+
+pub struct NoiseField {
+    params: NoiseParams,
+    source: Box<dyn NoiseFn<[f64; 2]> + Send + Sync>,
+}
+
+impl NoiseField {
+    pub fn new(params: NoiseParams) -> Self {
+        let seed = params.seed;
+        let source: Box<dyn NoiseFn<[f64; 2]> + Send + Sync> = match params.kind {
+            NoiseKind::Perlin => Box::new(Perlin::new().set_seed(seed)),
+            NoiseKind::OpenSimplex => Box::new(OpenSimplex::new().set_seed(seed)),
+            NoiseKind::SuperSimplex => Box::new(SuperSimplex::new().set_seed(seed)),
+            NoiseKind::Value => Box::new(Value::new().set_seed(seed)),
+            NoiseKind::Worley => Box::new(Worley::new().set_seed(seed)),
+        };
+
+        Self { params, source }
+    }
+
+    pub fn params(&self) -> &NoiseParams {
+        &self.params
+    }
+
+    /// The noise at `xy`, roughly between -1 and 1
+    pub fn get(&self, xy: Vec2) -> f32 {
+        let point = [
+            xy.x as f64 * self.params.scale,
+            xy.y as f64 * self.params.scale,
+        ];
+
+        self.warped(point) as f32
+    }
+
+    /// A direction to flow in at `xy` with no sources or sinks, so paths following it swirl
+    /// around each other instead of bunching up. It's the curl of the noise, treating the noise
+    /// as a stream function.
+    pub fn curl(&self, xy: Vec2) -> Vec2 {
+        let [x, y] = [
+            xy.x as f64 * self.params.scale,
+            xy.y as f64 * self.params.scale,
+        ];
+        let slope_x = (self.warped([x + SLOPE_EPSILON, y]) - self.warped([x - SLOPE_EPSILON, y]))
+            / (2.0 * SLOPE_EPSILON);
+        let slope_y = (self.warped([x, y + SLOPE_EPSILON]) - self.warped([x, y - SLOPE_EPSILON]))
+            / (2.0 * SLOPE_EPSILON);
+
+        vec2(slope_y as f32, -slope_x as f32)
+    }
+
+    /// The angle of the noise at `xy`, for flow fields that follow the noise directly
+    pub fn angle(&self, xy: Vec2) -> f32 {
+        self.get(xy) * PI
+    }
+
+    fn warped(&self, point: [f64; 2]) -> f64 {
+        if self.params.warp == 0.0 {
+            return self.fbm(point);
+        }
+
+        let offset = [
+            self.fbm(point),
+            self.fbm([point[0] + WARP_OFFSET[0], point[1] + WARP_OFFSET[1]]),
+        ];
+
+        self.fbm([
+            point[0] + self.params.warp * offset[0],
+            point[1] + self.params.warp * offset[1],
+        ])
+    }
+
+    /// Fractal Brownian motion, octaves of noise at rising frequencies and falling amplitudes
+    /// added together and scaled back down so the result stays between -1 and 1
+    fn fbm(&self, point: [f64; 2]) -> f64 {
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..self.params.octaves.max(1) {
+            total += self
+                .source
+                .get([point[0] * frequency, point[1] * frequency])
+                * amplitude;
+            total_amplitude += amplitude;
+            amplitude *= self.params.gain;
+            frequency *= self.params.lacunarity;
+        }
+
+        total / total_amplitude
+    }
+}
+
+/// Move a noise value from -1..1 to 0..1
+pub fn to_unit(value: f32) -> f32 {
+    ((value + 1.0) / 2.0).max(0.0).min(1.0)
+}
+
+/// Move a noise value from -1..1 to `range`
+pub fn remap(value: f32, range: Range<f32>) -> f32 {
+    map_t_of_range_a_to_range_b(value.max(-1.0).min(1.0), -1.0..1.0, range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeds_give_different_noise() {
+        let params = NoiseParams {
+            octaves: 4,
+            warp: 1.0,
+            ..Default::default()
+        };
+        let a = NoiseField::new(params);
+        let b = NoiseField::new(NoiseParams { seed: 1, ..params });
+        let xy = vec2(123.4, 56.7);
+
+        assert_eq!(a.get(xy), NoiseField::new(params).get(xy));
+        assert_ne!(a.get(xy), b.get(xy));
+        assert!(a.get(xy).abs() <= 1.0);
+    }
+
+    #[test]
+    fn test_curl_is_divergence_free() {
+        let field = NoiseField::new(NoiseParams {
+            octaves: 3,
+            ..Default::default()
+        });
+        let (xy, h) = (vec2(40.0, 70.0), 0.5);
+        let divergence = (field.curl(xy + vec2(h, 0.0)).x - field.curl(xy - vec2(h, 0.0)).x
+            + field.curl(xy + vec2(0.0, h)).y
+            - field.curl(xy - vec2(0.0, h)).y)
+            / (2.0 * h);
+
+        assert!(divergence.abs() < 1e-2, "divergence was {}", divergence);
+    }
+}
@@ -0,0 +1,585 @@
+//! Post-processing that makes a drawing quicker and cleaner to plot. Every step takes a drawing
+//! and returns a new one with the same layers, so they can be chained in whatever order suits.
+
+use crate::curve::distance_to_segment;
+use crate::geometry::{Drawing, Layer, Shape};
+use crate::paper::Paper;
+use crate::plotter::{plot_drawing, Simulator};
+use crate::spatial::{KdTreeIndex, SpatialIndex};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How many of the closest start points `reorder` looks at first, it looks further only if
+/// they all belong to shapes that have already been plotted
+const NEAREST_STARTS_BATCH: usize = 8;
+
+/// Scale the page of `drawing` to fit on `paper` inside `margin` (in drawing units), centered.
+/// The result's page is the paper.
+pub fn fit_to_paper(drawing: &Drawing, paper: Paper, margin: f32) -> Drawing {
+    let paper_wh = paper.wh();
+    let page_wh = vec2(drawing.width, drawing.height);
+    let printable_wh = paper_wh - vec2(margin, margin) * 2.0;
+
+    let scale = if page_wh.x > 0.0 && page_wh.y > 0.0 {
+        (printable_wh.x / page_wh.x).min(printable_wh.y / page_wh.y)
+    } else {
+        1.0
+    };
+    let offset = (paper_wh - page_wh * scale) / 2.0;
+
+    map_layers(drawing, paper_wh, |layer| {
+        layer
+            .shapes
+            .iter()
+            .map(|shape| shape.transformed(scale, offset))
+            .collect()
+    })
+}
+
+/// Cut away everything outside of `rect`. Shapes that cross the edge are flattened to within
+/// `tolerance` and split where they leave the rect.
+pub fn clip(drawing: &Drawing, rect: Rect, tolerance: f32) -> Drawing {
+    let page_wh = vec2(drawing.width, drawing.height);
+
+    map_layers(drawing, page_wh, |layer| {
+        let mut shapes = Vec::new();
+
+        for shape in layer.shapes.iter() {
+            let bounds = match shape.bounding_rect() {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+
+            if contains_rect(&rect, &bounds) {
+                shapes.push(shape.clone());
+            } else if rect.overlap(bounds).is_some() {
+                shapes.extend(
+                    clip_polyline(&shape.to_polyline(tolerance), &rect)
+                        .into_iter()
+                        .map(Shape::Polyline),
+                );
+            }
+        }
+
+        shapes
+    })
+}
+
+/// Join polylines that carry on from where another one stops, within `tolerance`, so the pen
+/// doesn't have to lift between them. Polylines may be reversed to make them join up.
+pub fn merge(drawing: &Drawing, tolerance: f32) -> Drawing {
+    let page_wh = vec2(drawing.width, drawing.height);
+    let is_near = |a: Point2, b: Point2| a.distance_squared(b) <= tolerance * tolerance;
+
+    map_layers(drawing, page_wh, |layer| {
+        let mut shapes = Vec::new();
+        let mut paths = Vec::new();
+
+        for shape in layer.shapes.iter() {
+            match shape {
+                Shape::Polyline(points) if points.len() > 1 => paths.push(points.clone()),
+                Shape::Polyline(_) => {}
+                _ => shapes.push(shape.clone()),
+            }
+        }
+
+        while !paths.is_empty() {
+            let mut path = paths.remove(0);
+
+            loop {
+                let end = path[path.len() - 1];
+                let start = path[0];
+
+                if let Some(index) = paths.iter().position(|next| is_near(end, next[0])) {
+                    path.extend(paths.remove(index).into_iter().skip(1));
+                } else if let Some(index) = paths
+                    .iter()
+                    .position(|next| is_near(end, next[next.len() - 1]))
+                {
+                    path.extend(paths.remove(index).into_iter().rev().skip(1));
+                } else if let Some(index) = paths
+                    .iter()
+                    .position(|prev| is_near(start, prev[prev.len() - 1]))
+                {
+                    let mut prev = paths.remove(index);
+                    prev.extend(path.into_iter().skip(1));
+                    path = prev;
+                } else if let Some(index) = paths.iter().position(|prev| is_near(start, prev[0])) {
+                    let mut prev: Vec<_> = paths.remove(index).into_iter().rev().collect();
+                    prev.extend(path.into_iter().skip(1));
+                    path = prev;
+                } else {
+                    break;
+                }
+            }
+
+            shapes.push(Shape::Polyline(path));
+        }
+
+        shapes
+    })
+}
+
+/// Drop points that don't change a polyline's or polygon's course by more than `tolerance`
+/// (Ramer-Douglas-Peucker). Circles are left alone.
+pub fn simplify(drawing: &Drawing, tolerance: f32) -> Drawing {
+    let page_wh = vec2(drawing.width, drawing.height);
+
+    map_layers(drawing, page_wh, |layer| {
+        layer
+            .shapes
+            .iter()
+            .map(|shape| match shape {
+                Shape::Polyline(points) => Shape::Polyline(simplify_points(points, tolerance)),
+                Shape::Polygon(points) if points.len() > 3 => {
+                    let closed: Vec<_> = points.iter().chain(points.first()).cloned().collect();
+                    let mut simplified = simplify_points(&closed, tolerance);
+                    simplified.pop();
+
+                    if simplified.len() < 3 {
+                        shape.clone()
+                    } else {
+                        Shape::Polygon(simplified)
+                    }
+                }
+                _ => shape.clone(),
+            })
+            .collect()
+    })
+}
+
+/// Put the shapes of each layer in an order that keeps pen-up travel short, always going to the
+/// closest shape next. Polylines may be reversed and polygons started from a different corner.
+pub fn reorder(drawing: &Drawing) -> Drawing {
+    let page_wh = vec2(drawing.width, drawing.height);
+    let mut position = pt2(0.0, 0.0);
+
+    map_layers(drawing, page_wh, |layer| {
+        let mut remaining: Vec<Option<Shape>> = layer.shapes.iter().cloned().map(Some).collect();
+        let mut starts = StartPoints::new(&layer.shapes);
+        let mut ordered = Vec::with_capacity(remaining.len());
+
+        while let Some((index, start)) = starts.closest(position) {
+            starts.take(index);
+
+            if let Some(shape) = remaining[index].take() {
+                let shape = turned_to(shape, start);
+                position = end_point(&shape).unwrap_or(position);
+                ordered.push(shape);
+            }
+        }
+
+        // shapes with nowhere to start from go last, in the order they were in
+        ordered.extend(remaining.into_iter().flatten());
+
+        ordered
+    })
+}
+
+/// A drawing for each layer, for plotting or exporting one pen at a time
+pub fn split_layers(drawing: &Drawing) -> Vec<Drawing> {
+    drawing
+        .layers
+        .iter()
+        .map(|layer| Drawing {
+            width: drawing.width,
+            height: drawing.height,
+            layers: vec![layer.clone()],
+        })
+        .collect()
+}
+
+/// How much plotting a drawing takes, measured by plotting it on a [`Simulator`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DrawingStats {
+    pub layers: usize,
+    pub paths: usize,
+    pub pen_down_mm: f32,
+    pub pen_up_mm: f32,
+    pub pen_lifts: usize,
+}
+
+impl DrawingStats {
+    pub fn of(drawing: &Drawing, mm_per_unit: f32) -> Self {
+        let mut simulator = Simulator::new();
+        plot_drawing(&mut simulator, drawing, mm_per_unit)
+            .expect("plotting on a simulator can't fail");
+
+        Self {
+            layers: drawing.layers.len(),
+            paths: drawing.shape_count(),
+            pen_down_mm: simulator.pen_down_distance(),
+            pen_up_mm: simulator.pen_up_distance(),
+            pen_lifts: simulator.pen_lifts(),
+        }
+    }
+}
+
+impl fmt::Display for DrawingStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} layers, {} paths, {:.1}mm pen down, {:.1}mm pen up, {} pen lifts",
+            self.layers, self.paths, self.pen_down_mm, self.pen_up_mm, self.pen_lifts
+        )
+    }
+}
+
+fn map_layers(
+    drawing: &Drawing,
+    page_wh: Vec2,
+    mut map_shapes: impl FnMut(&Layer) -> Vec<Shape>,
+) -> Drawing {
+    Drawing {
+        width: page_wh.x,
+        height: page_wh.y,
+        layers: drawing
+            .layers
+            .iter()
+            .map(|layer| Layer {
+                name: layer.name.clone(),
+                shapes: map_shapes(layer),
+            })
+            .collect(),
+    }
+}
+
+fn contains_rect(outer: &Rect, inner: &Rect) -> bool {
+    outer.left() <= inner.left()
+        && outer.right() >= inner.right()
+        && outer.bottom() <= inner.bottom()
+        && outer.top() >= inner.top()
+}
+
+/// Split a polyline into the pieces of it that are inside `rect`
+fn clip_polyline(points: &[Point2], rect: &Rect) -> Vec<Vec<Point2>> {
+    let mut pieces: Vec<Vec<Point2>> = Vec::new();
+
+    for segment in points.windows(2) {
+        if let Some((a, b)) = clip_segment(segment[0], segment[1], rect) {
+            match pieces.last_mut() {
+                Some(piece) if piece.last() == Some(&a) => piece.push(b),
+                _ => pieces.push(vec![a, b]),
+            }
+        }
+    }
+
+    pieces
+}
+
+/// Liang-Barsky line clipping
+fn clip_segment(a: Point2, b: Point2, rect: &Rect) -> Option<(Point2, Point2)> {
+    let delta = b - a;
+    let mut t_enter = 0.0f32;
+    let mut t_exit = 1.0f32;
+
+    for (p, q) in [
+        (-delta.x, a.x - rect.left()),
+        (delta.x, rect.right() - a.x),
+        (-delta.y, a.y - rect.bottom()),
+        (delta.y, rect.top() - a.y),
+    ]
+    .iter()
+    {
+        if *p == 0.0 {
+            if *q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+
+            if *p < 0.0 {
+                t_enter = t_enter.max(t);
+            } else {
+                t_exit = t_exit.min(t);
+            }
+        }
+    }
+
+    if t_enter > t_exit {
+        return None;
+    }
+
+    // keep the original points where they weren't clipped so pieces still join up exactly
+    let start = if t_enter > 0.0 {
+        a + delta * t_enter
+    } else {
+        a
+    };
+    let end = if t_exit < 1.0 { a + delta * t_exit } else { b };
+
+    Some((start, end))
+}
+
+fn simplify_points(points: &[Point2], tolerance: f32) -> Vec<Point2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let first = points[0];
+    let last = points[points.len() - 1];
+    let (index, distance) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(index, xy)| (index + 1, distance_to_segment(*xy, first, last)))
+        .fold((0, 0.0), |furthest, candidate| {
+            if candidate.1 > furthest.1 {
+                candidate
+            } else {
+                furthest
+            }
+        });
+
+    if distance <= tolerance {
+        return vec![first, last];
+    }
+
+    let mut simplified = simplify_points(&points[..=index], tolerance);
+    simplified.pop();
+    simplified.extend(simplify_points(&points[index..], tolerance));
+
+    simplified
+}
+
+/// How a shape is turned around before it's plotted, so that it starts at a particular point
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Start {
+    AsIs,
+    Reversed,
+    /// Polygons can start from any of their corners
+    Corner(usize),
+}
+
+/// Every point the shapes of a layer could be started from, indexed so that the closest one can
+/// be found without looking at all of them
+struct StartPoints {
+    /// The shape each point starts and how it has to be turned to start there
+    starts: Vec<(usize, Start)>,
+    points: Vec<Point2>,
+    index: KdTreeIndex,
+    is_taken: Vec<bool>,
+    starts_per_shape: Vec<usize>,
+    /// How many of the indexed points belong to shapes that have been taken
+    taken_starts: usize,
+}
+
+impl StartPoints {
+    fn new(shapes: &[Shape]) -> Self {
+        let mut starts = Vec::new();
+        let mut points = Vec::new();
+        let mut starts_per_shape = Vec::with_capacity(shapes.len());
+
+        for (shape_index, shape) in shapes.iter().enumerate() {
+            let shape_starts = start_points(shape);
+            starts_per_shape.push(shape_starts.len());
+
+            for (xy, start) in shape_starts {
+                starts.push((shape_index, start));
+                points.push(xy);
+            }
+        }
+
+        Self {
+            index: KdTreeIndex::new(&points),
+            starts,
+            points,
+            is_taken: vec![false; shapes.len()],
+            starts_per_shape,
+            taken_starts: 0,
+        }
+    }
+
+    /// The closest start to `xy` of a shape that hasn't been taken yet
+    fn closest(&mut self, xy: Point2) -> Option<(usize, Start)> {
+        // once most of the index is taken, skipping over it costs more than rebuilding it
+        if self.taken_starts * 2 > self.starts.len() {
+            self.rebuild();
+        }
+
+        let mut k = NEAREST_STARTS_BATCH;
+        loop {
+            let found = self.index.nearest(xy, k);
+            let closest = found
+                .iter()
+                .map(|index| self.starts[*index])
+                .find(|(shape_index, _)| !self.is_taken[*shape_index]);
+
+            if closest.is_some() || found.len() < k {
+                return closest;
+            }
+
+            k *= 2;
+        }
+    }
+
+    fn take(&mut self, shape_index: usize) {
+        if !self.is_taken[shape_index] {
+            self.is_taken[shape_index] = true;
+            self.taken_starts += self.starts_per_shape[shape_index];
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let is_taken = &self.is_taken;
+        let (starts, points): (Vec<_>, Vec<_>) = self
+            .starts
+            .iter()
+            .zip(self.points.iter())
+            .filter(|((shape_index, _), _)| !is_taken[*shape_index])
+            .map(|(start, xy)| (*start, *xy))
+            .unzip();
+
+        self.index = KdTreeIndex::new(&points);
+        self.starts = starts;
+        self.points = points;
+        self.taken_starts = 0;
+    }
+}
+
+/// Where `shape` could start and how it has to be turned to start there
+fn start_points(shape: &Shape) -> Vec<(Point2, Start)> {
+    match shape {
+        Shape::Polyline(points) if !points.is_empty() => vec![
+            (points[0], Start::AsIs),
+            (points[points.len() - 1], Start::Reversed),
+        ],
+        Shape::Polygon(points) => points
+            .iter()
+            .enumerate()
+            .map(|(index, xy)| (*xy, Start::Corner(index)))
+            .collect(),
+        _ => start_point(shape)
+            .map(|xy| (xy, Start::AsIs))
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn turned_to(shape: Shape, start: Start) -> Shape {
+    match (shape, start) {
+        (Shape::Polyline(mut points), Start::Reversed) => {
+            points.reverse();
+            Shape::Polyline(points)
+        }
+        (Shape::Polygon(mut points), Start::Corner(index)) => {
+            points.rotate_left(index);
+            Shape::Polygon(points)
+        }
+        (shape, _) => shape,
+    }
+}
+
+fn start_point(shape: &Shape) -> Option<Point2> {
+    match shape {
+        Shape::Polyline(points) | Shape::Polygon(points) => points.first().cloned(),
+        // circles are plotted starting from their rightmost point
+        Shape::Circle { center, radius } => Some(*center + vec2(*radius, 0.0)),
+        Shape::Path { start, .. } => Some(*start),
+    }
+}
+
+fn end_point(shape: &Shape) -> Option<Point2> {
+    match shape {
+        Shape::Polyline(points) => points.last().cloned(),
+        Shape::Path {
+            start,
+            segments,
+            is_closed: false,
+        } => Some(
+            segments
+                .iter()
+                .fold(*start, |from, segment| segment.end(from)),
+        ),
+        Shape::Polygon(_) | Shape::Circle { .. } | Shape::Path { .. } => start_point(shape),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_then_simplify() {
+        let mut drawing = Drawing::new(100.0, 100.0);
+        let layer = drawing.layer_mut("lines");
+        layer.push(Shape::Polyline(vec![pt2(0.0, 0.0), pt2(5.0, 0.0)]));
+        layer.push(Shape::Polyline(vec![pt2(10.0, 0.0), pt2(5.0, 0.0)]));
+        layer.push(Shape::Polyline(vec![pt2(10.0, 0.0), pt2(10.0, 10.0)]));
+
+        let merged = merge(&drawing, 0.01);
+        assert_eq!(merged.shape_count(), 1);
+
+        let simplified = simplify(&merged, 0.01);
+        assert_eq!(
+            simplified.layers[0].shapes,
+            vec![Shape::Polyline(vec![
+                pt2(0.0, 0.0),
+                pt2(10.0, 0.0),
+                pt2(10.0, 10.0)
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_clip_splits_lines_at_the_edge() {
+        let mut drawing = Drawing::new(100.0, 100.0);
+        drawing.layer_mut("lines").push(Shape::Polyline(vec![
+            pt2(5.0, 5.0),
+            pt2(20.0, 5.0),
+            pt2(20.0, 8.0),
+            pt2(5.0, 8.0),
+        ]));
+
+        let clipped = clip(
+            &drawing,
+            Rect::from_corners(pt2(0.0, 0.0), pt2(10.0, 10.0)),
+            0.1,
+        );
+
+        assert_eq!(
+            clipped.layers[0].shapes,
+            vec![
+                Shape::Polyline(vec![pt2(5.0, 5.0), pt2(10.0, 5.0)]),
+                Shape::Polyline(vec![pt2(10.0, 8.0), pt2(5.0, 8.0)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reorder_shortens_travel() {
+        let mut drawing = Drawing::new(100.0, 100.0);
+        let layer = drawing.layer_mut("lines");
+        layer.push(Shape::Polyline(vec![pt2(90.0, 0.0), pt2(100.0, 0.0)]));
+        layer.push(Shape::Polyline(vec![pt2(10.0, 0.0), pt2(0.0, 0.0)]));
+        layer.push(Shape::Polyline(vec![pt2(50.0, 0.0), pt2(40.0, 0.0)]));
+
+        let before = DrawingStats::of(&drawing, 1.0);
+        let after = DrawingStats::of(&reorder(&drawing), 1.0);
+
+        assert_eq!(before.pen_down_mm, after.pen_down_mm);
+        assert!(after.pen_up_mm < before.pen_up_mm);
+    }
+
+    #[test]
+    fn test_reorder_starts_shapes_at_the_closest_point() {
+        let mut drawing = Drawing::new(100.0, 100.0);
+        let layer = drawing.layer_mut("lines");
+        layer.push(Shape::Polygon(vec![
+            pt2(70.0, 60.0),
+            pt2(80.0, 60.0),
+            pt2(60.0, 50.0),
+        ]));
+        layer.push(Shape::Polyline(vec![pt2(50.0, 50.0), pt2(0.0, 0.0)]));
+
+        let reordered = reorder(&drawing);
+
+        assert_eq!(
+            reordered.layers[0].shapes,
+            vec![
+                Shape::Polyline(vec![pt2(0.0, 0.0), pt2(50.0, 50.0)]),
+                Shape::Polygon(vec![pt2(60.0, 50.0), pt2(70.0, 60.0), pt2(80.0, 60.0)]),
+            ]
+        );
+    }
+}
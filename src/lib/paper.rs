@@ -0,0 +1,71 @@
+use crate::pen::PIXELS_PER_MM;
+use anyhow::{anyhow, Context};
+use nannou::prelude::*;
+
+/// A sheet of paper, in portrait unless it's been turned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Paper {
+    pub width_mm: f32,
+    pub height_mm: f32,
+}
+
+impl Paper {
+    pub const A3: Paper = Paper::new(297.0, 420.0);
+    pub const A4: Paper = Paper::new(210.0, 297.0);
+    pub const A5: Paper = Paper::new(148.0, 210.0);
+    pub const LETTER: Paper = Paper::new(215.9, 279.4);
+    pub const TABLOID: Paper = Paper::new(279.4, 431.8);
+
+    pub const fn new(width_mm: f32, height_mm: f32) -> Self {
+        Self {
+            width_mm,
+            height_mm,
+        }
+    }
+
+    /// Read a paper size by name (`a4`, `letter`, etc.) or as `<width>x<height>` in millimeters,
+    /// e.g. `300x200`
+    pub fn from_name(name: &str) -> Result<Self, anyhow::Error> {
+        let name = name.trim().to_lowercase();
+
+        match name.as_str() {
+            "a3" => Ok(Self::A3),
+            "a4" => Ok(Self::A4),
+            "a5" => Ok(Self::A5),
+            "letter" => Ok(Self::LETTER),
+            "tabloid" => Ok(Self::TABLOID),
+            _ => {
+                let (width, height) = name
+                    .trim_end_matches("mm")
+                    .split_once('x')
+                    .ok_or_else(|| anyhow!("'{}' isn't a paper size I know of", name))?;
+                let size = |size: &str| {
+                    size.trim()
+                        .parse::<f32>()
+                        .with_context(|| format!("'{}' isn't a paper size I know of", name))
+                };
+
+                Ok(Self::new(size(width)?, size(height)?))
+            }
+        }
+    }
+
+    /// The same paper turned on its side, the long edge always ends up along x
+    pub fn landscape(&self) -> Self {
+        Self::new(
+            self.width_mm.max(self.height_mm),
+            self.width_mm.min(self.height_mm),
+        )
+    }
+
+    /// The size of the paper in drawing units
+    pub fn wh(&self) -> Vec2 {
+        vec2(self.width_mm, self.height_mm) * PIXELS_PER_MM
+    }
+}
+
+impl Default for Paper {
+    fn default() -> Self {
+        Self::A4
+    }
+}
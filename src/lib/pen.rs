@@ -0,0 +1,175 @@
+use crate::config::config;
+use anyhow::Context;
+use log::{info, warn};
+use nannou::color::{named, Srgb};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use svg::node::element::Group;
+
+/// Drawing units are SVG user units, which Inkscape (and so the AxiDraw extension) treats as CSS
+/// pixels at 96 DPI
+pub const PIXELS_PER_MM: f32 = 96.0 / 25.4;
+
+/// Where pen profiles are read from unless the config says otherwise
+pub const DEFAULT_PEN_PROFILES_PATH: &str = "pens.toml";
+
+/// A pen we own and what it puts on paper
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PenProfile {
+    pub name: String,
+    pub tip_width_mm: f32,
+    /// Any SVG colour, e.g. `black` or `#d81b60`
+    pub colour: String,
+    /// How fast the pen can be dragged before the line starts skipping
+    pub max_speed_mm_per_second: f32,
+}
+
+impl PenProfile {
+    pub fn new(name: &str, tip_width_mm: f32, colour: &str, max_speed_mm_per_second: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            tip_width_mm,
+            colour: colour.to_owned(),
+            max_speed_mm_per_second,
+        }
+    }
+
+    /// The stroke weight that draws this pen's line at its true size on screen
+    pub fn stroke_weight(&self) -> f32 {
+        self.tip_width_mm * PIXELS_PER_MM
+    }
+
+    pub fn svg_stroke_width(&self) -> String {
+        format!("{}mm", self.tip_width_mm)
+    }
+
+    /// The pen's colour for drawing on screen, black if the colour couldn't be understood
+    pub fn nannou_colour(&self) -> Srgb<u8> {
+        parse_colour(&self.colour).unwrap_or_else(|| Srgb::new(0, 0, 0))
+    }
+
+    /// Set the stroke of an SVG group to match this pen
+    pub fn style_svg_group(&self, group: Group) -> Group {
+        group
+            .set("fill", "none")
+            .set("stroke", self.colour.as_str())
+            .set("stroke-width", self.svg_stroke_width())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PenProfilesFile {
+    #[serde(default, rename = "pen")]
+    pens: Vec<PenProfile>,
+}
+
+/// All the pens available to the generators
+#[derive(Debug, Clone, PartialEq)]
+pub struct PenLibrary {
+    pens: Vec<PenProfile>,
+}
+
+impl PenLibrary {
+    /// Load the pens listed in the file named by the config (`pens.toml` by default), falling back to
+    /// the built-in pens if there's no such file or it can't be read
+    pub fn load() -> Self {
+        let path = config().pen_profiles.value.as_path();
+
+        if !path.exists() {
+            info!(
+                "no pen profiles found at '{}', using the built-in pens",
+                path.to_string_lossy()
+            );
+            return Self::default();
+        }
+
+        match Self::from_file(path) {
+            Ok(library) => library,
+            Err(err) => {
+                warn!("{:?}, using the built-in pens", err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, anyhow::Error> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("couldn't read pen profiles '{}'", path.to_string_lossy()))?;
+
+        Self::from_toml(&contents)
+            .with_context(|| format!("couldn't load pen profiles '{}'", path.to_string_lossy()))
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, anyhow::Error> {
+        let file: PenProfilesFile = toml::from_str(contents)?;
+
+        if file.pens.is_empty() {
+            anyhow::bail!("at least one [[pen]] must be listed");
+        }
+
+        Ok(Self { pens: file.pens })
+    }
+
+    pub fn pens(&self) -> &[PenProfile] {
+        &self.pens
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.pens.iter().map(|pen| pen.name.clone()).collect()
+    }
+
+    /// Get a pen by its position in the library, wrapping around so that a stale index still
+    /// gets a pen
+    pub fn get(&self, index: usize) -> &PenProfile {
+        &self.pens[index % self.pens.len()]
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.pens.iter().position(|pen| pen.name == name)
+    }
+
+    /// Find a pen by name, or the first pen if there's no pen with that name
+    pub fn index_of_or_first(&self, name: &str) -> usize {
+        self.index_of(name).unwrap_or_default()
+    }
+}
+
+impl Default for PenLibrary {
+    fn default() -> Self {
+        Self {
+            pens: vec![
+                PenProfile::new("0.3mm Marker", 0.3, "black", 40.0),
+                PenProfile::new("1mm Marker", 1.0, "black", 40.0),
+                PenProfile::new("Molotow Marker", 4.0, "black", 20.0),
+            ],
+        }
+    }
+}
+
+fn parse_colour(colour: &str) -> Option<Srgb<u8>> {
+    let colour = colour.trim();
+
+    match colour.strip_prefix('#') {
+        // `len` counts bytes, slicing a hex string with anything but ASCII in it could panic
+        Some(hex) if hex.len() == 6 && hex.is_ascii() => {
+            let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+
+            Some(Srgb::new(channel(0)?, channel(2)?, channel(4)?))
+        }
+        Some(_) => None,
+        None => named::from_str(&colour.to_lowercase()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colours_are_parsed_or_rejected() {
+        assert_eq!(parse_colour(" #D81B60 "), Some(Srgb::new(0xd8, 0x1b, 0x60)));
+        assert_eq!(parse_colour("Black"), Some(Srgb::new(0, 0, 0)));
+        assert_eq!(parse_colour("#12345"), None);
+        assert_eq!(parse_colour("#ab€c"), None);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::job::JobPath;
+use crate::pen::{PenLibrary, PenProfile};
+use anyhow::{anyhow, Context};
+use nannou::prelude::*;
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+/// Asks whoever is standing next to the plotter to swap pens before the next layer is plotted
+pub trait PenChangePrompt {
+    /// Returns `false` if the plot should stop instead of continuing with the next layer
+    fn confirm_pen_change(
+        &mut self,
+        layer: &str,
+        estimate: Duration,
+    ) -> Result<bool, anyhow::Error>;
+}
+
+/// Prompts on the terminal the plot was started from
+pub struct CliPenChangePrompt;
+
+impl PenChangePrompt for CliPenChangePrompt {
+    fn confirm_pen_change(
+        &mut self,
+        layer: &str,
+        estimate: Duration,
+    ) -> Result<bool, anyhow::Error> {
+        print!(
+            "Put in the pen for layer '{}' (about {}) and press enter, or type 'q' to stop: ",
+            layer,
+            format_duration(estimate)
+        );
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut answer)
+            .context("couldn't read an answer from the terminal")?;
+
+        Ok(!answer.trim().eq_ignore_ascii_case("q"))
+    }
+}
+
+/// Anything longer than this is as good as forever
+const MAX_ESTIMATE_SECONDS: f32 = 1e15;
+
+/// How quickly a plotter gets things done, used to estimate how long a plot will take
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlotterSpeeds {
+    pub pen_down_mm_per_second: f32,
+    pub pen_up_mm_per_second: f32,
+    /// The time it takes to lower and then raise the pen again
+    pub pen_lift_seconds: f32,
+}
+
+impl PlotterSpeeds {
+    /// The default speeds, slowed down to what the pen called `pen_name` in the pen profiles can
+    /// keep up with
+    pub fn for_pen(pen_name: Option<&str>) -> Result<Self, anyhow::Error> {
+        let speeds = Self::default();
+
+        match pen_name {
+            Some(pen_name) => {
+                let pens = PenLibrary::load();
+                let pen = pens
+                    .index_of(pen_name)
+                    .map(|index| pens.get(index))
+                    .ok_or_else(|| anyhow!("there's no pen called '{}'", pen_name))?;
+
+                Ok(speeds.limited_to(pen))
+            }
+            None => Ok(speeds),
+        }
+    }
+
+    /// These speeds, but drawing no faster than `pen` can go without its line skipping
+    pub fn limited_to(&self, pen: &PenProfile) -> Self {
+        Self {
+            pen_down_mm_per_second: self.pen_down_mm_per_second.min(pen.max_speed_mm_per_second),
+            ..*self
+        }
+    }
+
+    /// Estimate how long it takes to plot `paths` in order, starting from `start`
+    pub fn estimate<'a>(
+        &self,
+        start: Point2,
+        paths: impl IntoIterator<Item = &'a JobPath>,
+    ) -> Duration {
+        let mut seconds = 0.0;
+        let mut position = start;
+
+        for path in paths {
+            let mut points = path.points();
+
+            if let Some(first) = points.next() {
+                seconds += position.distance(first) / self.pen_up_mm_per_second;
+                seconds += self.pen_lift_seconds;
+                position = first;
+            }
+
+            for xy in points {
+                seconds += position.distance(xy) / self.pen_down_mm_per_second;
+                position = xy;
+            }
+        }
+
+        // speeds of zero or less never get there, which doesn't fit in a `Duration`
+        if (0.0..MAX_ESTIMATE_SECONDS).contains(&seconds) {
+            Duration::from_secs_f32(seconds)
+        } else {
+            Duration::MAX
+        }
+    }
+}
+
+impl Default for PlotterSpeeds {
+    // Roughly what an AxiDraw V3 manages with its default settings
+    fn default() -> Self {
+        Self {
+            pen_down_mm_per_second: 25.0,
+            pen_up_mm_per_second: 75.0,
+            pen_lift_seconds: 0.4,
+        }
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    format!(
+        "{}h {:02}m {:02}s",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_with_a_speed_of_zero_take_forever() {
+        let path = JobPath {
+            layer: "lines".to_owned(),
+            points: vec![(0.0, 0.0), (10.0, 0.0)],
+        };
+        let speeds = PlotterSpeeds {
+            pen_down_mm_per_second: 0.0,
+            ..PlotterSpeeds::default()
+        };
+
+        assert_eq!(speeds.estimate(pt2(0.0, 0.0), &[path]), Duration::MAX);
+    }
+}
@@ -0,0 +1,93 @@
+//! Reads the EiBotBoard commands an AxiDraw gets sent over serial. Only the commands that move the
+//! carriage or the pen are interpreted, everything else (queries, servo configuration, etc.) is
+//! skipped.
+
+use super::PlotCommand;
+use anyhow::{anyhow, Context};
+use log::debug;
+use nannou::prelude::*;
+
+/// An AxiDraw V3 at the default 16x microstepping moves 2032 steps per inch
+pub const STEPS_PER_MM: f32 = 80.0;
+
+pub fn parse(input: &str) -> Result<Vec<PlotCommand>, anyhow::Error> {
+    let mut commands = Vec::new();
+    let mut position = pt2(0.0, 0.0);
+    let mut pen_is_down = false;
+
+    for (line_index, line) in input
+        .split(|c| c == '\n' || c == '\r')
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+    {
+        let mut fields = line.split(',').map(str::trim);
+        let command = fields.next().unwrap_or_default().to_ascii_uppercase();
+        let arguments: Vec<&str> = fields.collect();
+        let argument = |index: usize| -> Result<f32, anyhow::Error> {
+            let argument = arguments.get(index).ok_or_else(|| {
+                anyhow!(
+                    "EBB command on line {} is missing argument {}",
+                    line_index + 1,
+                    index + 1
+                )
+            })?;
+
+            argument.parse().with_context(|| {
+                format!(
+                    "argument {} of the EBB command on line {} isn't a number",
+                    index + 1,
+                    line_index + 1
+                )
+            })
+        };
+
+        match command.as_str() {
+            // Stepper move with the AxiDraw's mixed-axis geometry, motor 1 is x + y and motor
+            // 2 is x - y
+            "SM" => {
+                let motor_1 = argument(1)?;
+                let motor_2 = if arguments.len() > 2 {
+                    argument(2)?
+                } else {
+                    0.0
+                };
+                let steps = vec2(motor_1 + motor_2, motor_1 - motor_2) / 2.0;
+
+                position += steps / STEPS_PER_MM;
+                commands.push(PlotCommand::MoveTo(position));
+            }
+            // Stepper move given directly in x and y steps
+            "XM" => {
+                let steps = vec2(argument(1)?, argument(2)?);
+
+                position += steps / STEPS_PER_MM;
+                commands.push(PlotCommand::MoveTo(position));
+            }
+            "HM" => {
+                position = pt2(0.0, 0.0);
+                commands.push(PlotCommand::MoveTo(position));
+            }
+            // 1 raises the pen, 0 lowers it
+            "SP" => {
+                pen_is_down = argument(0)? == 0.0;
+                commands.push(pen_command(pen_is_down));
+            }
+            "TP" => {
+                pen_is_down = !pen_is_down;
+                commands.push(pen_command(pen_is_down));
+            }
+            _ => debug!("ignoring EBB command '{}'", line),
+        }
+    }
+
+    Ok(commands)
+}
+
+fn pen_command(pen_is_down: bool) -> PlotCommand {
+    if pen_is_down {
+        PlotCommand::PenDown
+    } else {
+        PlotCommand::PenUp
+    }
+}
@@ -0,0 +1,205 @@
+//! Reads the subset of G-code that pen plotter firmwares (GRBL and friends) actually get sent.
+//! The pen is lowered by `M3`, raised by `M5`, or moved along the Z axis where anything at or
+//! below zero counts as the pen touching the paper. Written G-code sticks to `M3`/`M5`.
+
+use super::{PlotCommand, Plotter};
+use crate::pen_change::PlotterSpeeds;
+use anyhow::{anyhow, Context};
+use log::debug;
+use nannou::prelude::*;
+use std::io::Write;
+
+const MILLIMETERS_PER_INCH: f32 = 25.4;
+
+pub fn parse(input: &str) -> Result<Vec<PlotCommand>, anyhow::Error> {
+    let mut commands = Vec::new();
+    let mut position = pt2(0.0, 0.0);
+    let mut is_absolute = true;
+    let mut units_to_mm = 1.0;
+
+    for (line_index, line) in input.lines().enumerate() {
+        let words = words_of_line(line)
+            .with_context(|| format!("couldn't read G-code line {}", line_index + 1))?;
+        let mut x = None;
+        let mut y = None;
+        let mut z = None;
+        let mut is_move = false;
+
+        for (letter, value) in words {
+            match (letter, value as i32) {
+                ('G', 0) | ('G', 1) => is_move = true,
+                ('G', 20) => units_to_mm = MILLIMETERS_PER_INCH,
+                ('G', 21) => units_to_mm = 1.0,
+                ('G', 28) => {
+                    commands.push(PlotCommand::PenUp);
+                    position = pt2(0.0, 0.0);
+                    commands.push(PlotCommand::MoveTo(position));
+                }
+                ('G', 90) => is_absolute = true,
+                ('G', 91) => is_absolute = false,
+                ('M', 3) => commands.push(PlotCommand::PenDown),
+                ('M', 5) => commands.push(PlotCommand::PenUp),
+                ('T', pen) => commands.push(PlotCommand::SelectPen(pen.max(0) as usize)),
+                ('X', _) => x = Some(value * units_to_mm),
+                ('Y', _) => y = Some(value * units_to_mm),
+                ('Z', _) => z = Some(value * units_to_mm),
+                _ => debug!("ignoring G-code word {}{}", letter, value),
+            }
+        }
+
+        // A bare coordinate continues the last motion mode; plotter G-code never uses anything
+        // but linear moves so treat it as one
+        if x.is_some() || y.is_some() || z.is_some() {
+            is_move = true;
+        }
+
+        if !is_move {
+            continue;
+        }
+
+        if let Some(z) = z {
+            let pen_command = if z <= 0.0 {
+                PlotCommand::PenDown
+            } else {
+                PlotCommand::PenUp
+            };
+
+            commands.push(pen_command);
+        }
+
+        if x.is_some() || y.is_some() {
+            position = if is_absolute {
+                pt2(x.unwrap_or(position.x), y.unwrap_or(position.y))
+            } else {
+                position + vec2(x.unwrap_or(0.0), y.unwrap_or(0.0))
+            };
+
+            commands.push(PlotCommand::MoveTo(position));
+        }
+    }
+
+    Ok(commands)
+}
+
+/// Write plot commands as G-code in millimeters, drawing at the pen-down speed of `speeds`
+pub fn write(commands: &[PlotCommand], speeds: &PlotterSpeeds) -> String {
+    let mut lines = vec!["G21".to_owned(), "G90".to_owned(), "M5".to_owned()];
+    let mut pen_is_down = false;
+
+    for command in commands.iter() {
+        lines.push(line_of(*command, &mut pen_is_down, speeds));
+    }
+
+    lines.push("M5".to_owned());
+    lines.push("G0 X0 Y0".to_owned());
+    lines.push(String::new());
+
+    lines.join("\n")
+}
+
+/// Sends commands to a G-code plotter as they're carried out instead of collecting them into a
+/// file, e.g. to a GRBL board's serial port. Output is flushed every time the pen comes up so a
+/// finished path has been handed over before a job notes it down as plotted.
+pub struct GCodeStream<W: Write> {
+    output: W,
+    speeds: PlotterSpeeds,
+    pen_is_down: bool,
+}
+
+impl<W: Write> GCodeStream<W> {
+    pub fn new(mut output: W, speeds: PlotterSpeeds) -> Result<Self, anyhow::Error> {
+        output.write_all(b"G21\nG90\nM5\n")?;
+        output.flush()?;
+
+        Ok(Self {
+            output,
+            speeds,
+            pen_is_down: false,
+        })
+    }
+}
+
+impl<W: Write> Plotter for GCodeStream<W> {
+    fn execute(&mut self, command: PlotCommand) -> Result<(), anyhow::Error> {
+        let line = line_of(command, &mut self.pen_is_down, &self.speeds);
+        writeln!(self.output, "{}", line).context("couldn't send G-code to the plotter")?;
+
+        if command == PlotCommand::PenUp {
+            self.output
+                .flush()
+                .context("couldn't send G-code to the plotter")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn line_of(command: PlotCommand, pen_is_down: &mut bool, speeds: &PlotterSpeeds) -> String {
+    let feed_rate = speeds.pen_down_mm_per_second * 60.0;
+
+    match command {
+        PlotCommand::PenUp => {
+            *pen_is_down = false;
+            "M5".to_owned()
+        }
+        PlotCommand::PenDown => {
+            *pen_is_down = true;
+            "M3".to_owned()
+        }
+        PlotCommand::MoveTo(xy) if *pen_is_down => {
+            format!("G1 X{:.3} Y{:.3} F{:.0}", xy.x, xy.y, feed_rate)
+        }
+        PlotCommand::MoveTo(xy) => format!("G0 X{:.3} Y{:.3}", xy.x, xy.y),
+        PlotCommand::SelectPen(pen) => format!("T{}", pen),
+    }
+}
+
+fn words_of_line(line: &str) -> Result<Vec<(char, f32)>, anyhow::Error> {
+    let line = strip_comments(line);
+    let mut words = Vec::new();
+    let mut chars = line.chars().filter(|c| !c.is_whitespace()).peekable();
+
+    while let Some(letter) = chars.next() {
+        let letter = letter.to_ascii_uppercase();
+        if !letter.is_ascii_alphabetic() {
+            return Err(anyhow!("expected a letter but found '{}'", letter));
+        }
+
+        let mut number = String::new();
+        while let Some(c) = chars.peek() {
+            if c.is_ascii_digit() || *c == '.' || *c == '-' || *c == '+' {
+                number.push(*c);
+                let _ = chars.next();
+            } else {
+                break;
+            }
+        }
+
+        let value = number
+            .parse()
+            .with_context(|| format!("'{}{}' isn't a valid G-code word", letter, number))?;
+
+        words.push((letter, value));
+    }
+
+    Ok(words)
+}
+
+fn strip_comments(line: &str) -> String {
+    let line = line.split(';').next().unwrap_or_default();
+    let mut stripped = String::with_capacity(line.len());
+    let mut is_in_comment = false;
+
+    for c in line.chars() {
+        match c {
+            '(' => is_in_comment = true,
+            ')' => is_in_comment = false,
+            // Some senders wrap programs in `%` lines
+            '%' => (),
+            _ if !is_in_comment => stripped.push(c),
+            _ => (),
+        }
+    }
+
+    stripped
+}
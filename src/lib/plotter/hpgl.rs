@@ -0,0 +1,129 @@
+//! Reads and writes the HPGL instructions that pen plotters have understood since the 80s.
+//! Coordinates are in plotter units. They're read as is, so parsed commands keep HPGL's y axis,
+//! but HPGL's y points up the page where a drawing's points down, so commands plotted from a
+//! drawing are flipped against the page height when they're written.
+
+use super::PlotCommand;
+use anyhow::Context;
+use log::debug;
+use nannou::prelude::*;
+
+/// One plotter unit is 0.025mm
+pub const PLOTTER_UNITS_PER_MM: f32 = 40.0;
+
+pub fn parse(input: &str) -> Result<Vec<PlotCommand>, anyhow::Error> {
+    let mut commands = Vec::new();
+    let mut position = pt2(0.0, 0.0);
+    let mut is_absolute = true;
+
+    for instruction in input
+        .split(|c| c == ';' || c == '\n' || c == '\r')
+        .map(str::trim)
+        .filter(|instruction| !instruction.is_empty())
+    {
+        if instruction.len() < 2 || !instruction.is_char_boundary(2) {
+            debug!("ignoring HPGL instruction '{}'", instruction);
+            continue;
+        }
+
+        let (mnemonic, arguments) = instruction.split_at(2);
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        let arguments = parse_arguments(arguments)
+            .with_context(|| format!("couldn't read HPGL instruction '{}'", instruction))?;
+
+        match mnemonic.as_str() {
+            "IN" => {
+                is_absolute = true;
+                position = pt2(0.0, 0.0);
+                commands.push(PlotCommand::PenUp);
+            }
+            "PA" | "PR" | "PU" | "PD" => {
+                match mnemonic.as_str() {
+                    "PA" => is_absolute = true,
+                    "PR" => is_absolute = false,
+                    "PU" => commands.push(PlotCommand::PenUp),
+                    _ => commands.push(PlotCommand::PenDown),
+                }
+
+                for xy in arguments.chunks_exact(2) {
+                    let xy = pt2(xy[0], xy[1]) / PLOTTER_UNITS_PER_MM;
+                    position = if is_absolute { xy } else { position + xy };
+
+                    commands.push(PlotCommand::MoveTo(position));
+                }
+            }
+            "SP" => {
+                let pen = arguments.first().cloned().unwrap_or(0.0);
+
+                // `SP0` means "put the pen away", not "select pen zero". HPGL pens are numbered
+                // from 1 where ours are numbered from 0.
+                if pen < 1.0 {
+                    commands.push(PlotCommand::PenUp);
+                } else {
+                    commands.push(PlotCommand::SelectPen(pen as usize - 1));
+                }
+            }
+            _ => debug!("ignoring HPGL instruction '{}'", instruction),
+        }
+    }
+
+    Ok(commands)
+}
+
+/// Write plot commands as HPGL, flipping positions measured down from the top of a page
+/// `page_height_mm` tall so they're measured up from the bottom. HPGL numbers its pens from 1, so
+/// pen 0 is `SP1`.
+pub fn write(commands: &[PlotCommand], page_height_mm: f32) -> String {
+    let mut instructions = vec!["IN".to_owned(), "PU".to_owned()];
+
+    for command in commands.iter() {
+        match command {
+            PlotCommand::PenUp => instructions.push("PU".to_owned()),
+            PlotCommand::PenDown => instructions.push("PD".to_owned()),
+            PlotCommand::MoveTo(xy) => {
+                let xy = pt2(xy.x, page_height_mm - xy.y) * PLOTTER_UNITS_PER_MM;
+                instructions.push(format!("PA{:.0},{:.0}", xy.x, xy.y));
+            }
+            PlotCommand::SelectPen(pen) => instructions.push(format!("SP{}", pen + 1)),
+        }
+    }
+
+    instructions.push("PU".to_owned());
+    instructions.push("SP0".to_owned());
+
+    instructions.join(";\n") + ";\n"
+}
+
+fn parse_arguments(arguments: &str) -> Result<Vec<f32>, anyhow::Error> {
+    arguments
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|argument| !argument.is_empty())
+        .map(|argument| {
+            argument
+                .parse()
+                .with_context(|| format!("'{}' isn't a number", argument))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{Drawing, Shape};
+    use crate::plotter::plot_drawing;
+
+    #[test]
+    fn test_the_top_of_the_drawing_is_written_at_the_top_of_the_page() {
+        let mut drawing = Drawing::new(100.0, 200.0);
+        drawing
+            .layer_mut("lines")
+            .push(Shape::Polyline(vec![pt2(10.0, 5.0), pt2(20.0, 5.0)]));
+        let mut commands = Vec::new();
+        plot_drawing(&mut commands, &drawing, 1.0).unwrap();
+
+        let hpgl = write(&commands, drawing.height);
+
+        // 5mm down from the top of a 200mm page is 195mm up from the bottom
+        assert!(hpgl.contains("PA400,7800;\nPD;\nPA800,7800;"));
+    }
+}
@@ -0,0 +1,130 @@
+pub mod ebb;
+pub mod gcode;
+pub mod hpgl;
+mod simulator;
+
+pub use simulator::{Move, Simulator};
+
+use crate::geometry::Drawing;
+use crate::pen_change::PlotterSpeeds;
+use anyhow::bail;
+use nannou::prelude::*;
+use std::path::Path;
+
+/// How closely curves are followed when a drawing is turned into plot commands, in millimeters
+pub const FLATTENING_TOLERANCE_MM: f32 = 0.1;
+
+/// The handful of things every plotter we talk to knows how to do. Positions are in millimeters
+/// from the plotter's home position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlotCommand {
+    PenUp,
+    PenDown,
+    MoveTo(Point2),
+    /// Switch to a different pen. Only plotters with a carousel (or a person standing next to
+    /// the plotter) can actually act on this.
+    SelectPen(usize),
+}
+
+/// Anything that can carry out plot commands, be it real hardware or a stand-in for it
+pub trait Plotter {
+    fn execute(&mut self, command: PlotCommand) -> Result<(), anyhow::Error>;
+
+    fn execute_all(&mut self, commands: &[PlotCommand]) -> Result<(), anyhow::Error> {
+        for command in commands.iter() {
+            self.execute(*command)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Collecting commands in a `Vec` is how they get written to a file instead of sent to a plotter
+impl Plotter for Vec<PlotCommand> {
+    fn execute(&mut self, command: PlotCommand) -> Result<(), anyhow::Error> {
+        self.push(command);
+        Ok(())
+    }
+}
+
+/// Plot every shape in `drawing`, layer by layer with a pen per layer, in the order the shapes
+/// are in. `mm_per_unit` converts drawing units into millimeters on paper.
+pub fn plot_drawing(
+    plotter: &mut impl Plotter,
+    drawing: &Drawing,
+    mm_per_unit: f32,
+) -> Result<(), anyhow::Error> {
+    let tolerance = FLATTENING_TOLERANCE_MM / mm_per_unit;
+
+    for (pen, layer) in drawing.layers.iter().enumerate() {
+        plotter.execute(PlotCommand::SelectPen(pen))?;
+
+        for shape in layer.shapes.iter() {
+            let mut points = shape
+                .to_polyline(tolerance)
+                .into_iter()
+                .map(|xy| xy * mm_per_unit);
+
+            if let Some(start) = points.next() {
+                plotter.execute(PlotCommand::PenUp)?;
+                plotter.execute(PlotCommand::MoveTo(start))?;
+                plotter.execute(PlotCommand::PenDown)?;
+
+                for xy in points {
+                    plotter.execute(PlotCommand::MoveTo(xy))?;
+                }
+            }
+        }
+    }
+
+    plotter.execute(PlotCommand::PenUp)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandFormat {
+    GCode,
+    Hpgl,
+    Ebb,
+}
+
+impl CommandFormat {
+    pub fn from_path(path: &Path) -> Result<Self, anyhow::Error> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        match extension.as_deref() {
+            Some("gcode") | Some("nc") | Some("ngc") => Ok(CommandFormat::GCode),
+            Some("hpgl") | Some("plt") => Ok(CommandFormat::Hpgl),
+            Some("ebb") => Ok(CommandFormat::Ebb),
+            _ => bail!(
+                "couldn't tell what kind of plot commands '{}' contains from its extension",
+                path.to_string_lossy()
+            ),
+        }
+    }
+
+    pub fn parse(&self, input: &str) -> Result<Vec<PlotCommand>, anyhow::Error> {
+        match self {
+            CommandFormat::GCode => gcode::parse(input),
+            CommandFormat::Hpgl => hpgl::parse(input),
+            CommandFormat::Ebb => ebb::parse(input),
+        }
+    }
+
+    /// Write commands plotted from a drawing `page_height_mm` tall, which formats with y pointing
+    /// up the page need to flip against
+    pub fn write(
+        &self,
+        commands: &[PlotCommand],
+        speeds: &PlotterSpeeds,
+        page_height_mm: f32,
+    ) -> Result<String, anyhow::Error> {
+        match self {
+            CommandFormat::GCode => Ok(gcode::write(commands, speeds)),
+            CommandFormat::Hpgl => Ok(hpgl::write(commands, page_height_mm)),
+            CommandFormat::Ebb => bail!("writing EBB commands isn't supported, they're sent to the AxiDraw as they're plotted"),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use super::{PlotCommand, Plotter};
+use crate::geometry::{Drawing, Shape};
+use nannou::prelude::*;
+
+/// A straight line the carriage travelled, with or without the pen touching the paper
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Move {
+    pub from: Point2,
+    pub to: Point2,
+    pub pen_is_down: bool,
+    pub pen: usize,
+}
+
+impl Move {
+    pub fn length(&self) -> f32 {
+        self.from.distance(self.to)
+    }
+}
+
+/// A plotter that only exists in memory. It keeps track of where the pen is and records every
+/// move it makes so that the plot can be checked, previewed or turned back into a drawing.
+#[derive(Debug, Clone, Default)]
+pub struct Simulator {
+    position: Point2,
+    pen_is_down: bool,
+    pen: usize,
+    pen_lifts: usize,
+    moves: Vec<Move>,
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn position(&self) -> Point2 {
+        self.position
+    }
+
+    pub fn pen_is_down(&self) -> bool {
+        self.pen_is_down
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// How many times the pen went from touching the paper to not touching it
+    pub fn pen_lifts(&self) -> usize {
+        self.pen_lifts
+    }
+
+    pub fn pen_down_distance(&self) -> f32 {
+        self.moves
+            .iter()
+            .filter(|m| m.pen_is_down)
+            .map(Move::length)
+            .sum()
+    }
+
+    pub fn pen_up_distance(&self) -> f32 {
+        self.moves
+            .iter()
+            .filter(|m| !m.pen_is_down)
+            .map(Move::length)
+            .sum()
+    }
+
+    /// Rebuild the drawing that the plotter put on paper. Every unbroken run of pen-down moves
+    /// becomes a polyline on a layer named after the pen that drew it.
+    pub fn drawing(&self) -> Drawing {
+        let (width, height) = self
+            .moves
+            .iter()
+            .fold((0.0f32, 0.0f32), |(width, height), m| {
+                (width.max(m.to.x), height.max(m.to.y))
+            });
+        let mut drawing = Drawing::new(width, height);
+        let mut stroke: Vec<Point2> = Vec::new();
+        let mut stroke_pen = 0;
+
+        for m in self.moves.iter() {
+            let continues_stroke =
+                m.pen_is_down && m.pen == stroke_pen && stroke.last() == Some(&m.from);
+
+            if !continues_stroke && stroke.len() > 1 {
+                drawing
+                    .layer_mut(&pen_layer_name(stroke_pen))
+                    .push(Shape::Polyline(std::mem::take(&mut stroke)));
+            }
+
+            if !continues_stroke {
+                stroke.clear();
+            }
+
+            if m.pen_is_down {
+                if stroke.is_empty() {
+                    stroke.push(m.from);
+                    stroke_pen = m.pen;
+                }
+
+                stroke.push(m.to);
+            }
+        }
+
+        if stroke.len() > 1 {
+            drawing
+                .layer_mut(&pen_layer_name(stroke_pen))
+                .push(Shape::Polyline(stroke));
+        }
+
+        drawing
+    }
+}
+
+impl Plotter for Simulator {
+    fn execute(&mut self, command: PlotCommand) -> Result<(), anyhow::Error> {
+        match command {
+            PlotCommand::PenUp => {
+                if self.pen_is_down {
+                    self.pen_lifts += 1;
+                }
+
+                self.pen_is_down = false;
+            }
+            PlotCommand::PenDown => self.pen_is_down = true,
+            PlotCommand::MoveTo(xy) => {
+                if xy != self.position {
+                    self.moves.push(Move {
+                        from: self.position,
+                        to: xy,
+                        pen_is_down: self.pen_is_down,
+                        pen: self.pen,
+                    });
+                }
+
+                self.position = xy;
+            }
+            PlotCommand::SelectPen(pen) => self.pen = pen,
+        }
+
+        Ok(())
+    }
+}
+
+fn pen_layer_name(pen: usize) -> String {
+    format!("pen-{}", pen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plotter::CommandFormat;
+
+    #[test]
+    fn test_gcode_square() {
+        let commands = CommandFormat::GCode
+            .parse("G21\nG90\nG0 X10 Y10\nM3\nG1 X20\nG1 Y20 ; down\nG1 X10 (left)\nM5\nG0 X0 Y0")
+            .unwrap();
+        let mut simulator = Simulator::new();
+        simulator.execute_all(&commands).unwrap();
+
+        assert_eq!(simulator.pen_down_distance(), 30.0);
+        assert_eq!(simulator.pen_lifts(), 1);
+        assert_eq!(simulator.drawing().shape_count(), 1);
+        assert!(!simulator.pen_is_down());
+    }
+
+    #[test]
+    fn test_hpgl_and_ebb_agree() {
+        let hpgl = CommandFormat::Hpgl
+            .parse("IN;SP1;PU0,0;PD400,0,400,400;PU;")
+            .unwrap();
+        // 10mm along x is 800 steps on both motors, 10mm along y is 800 steps in opposite directions
+        let ebb = CommandFormat::Ebb
+            .parse("SP,0\nSM,100,800,800\nSM,100,800,-800\nSP,1")
+            .unwrap();
+
+        let mut from_hpgl = Simulator::new();
+        from_hpgl.execute_all(&hpgl).unwrap();
+        let mut from_ebb = Simulator::new();
+        from_ebb.execute_all(&ebb).unwrap();
+
+        assert_eq!(from_hpgl.position(), pt2(10.0, 10.0));
+        assert_eq!(from_hpgl.position(), from_ebb.position());
+        assert_eq!(from_hpgl.pen_down_distance(), from_ebb.pen_down_distance());
+    }
+}
@@ -0,0 +1,201 @@
+//! Presets are a generator's params kept in a TOML file. A generator can watch its preset and
+//! rebuild whenever the file is saved, so params can be tweaked from a text editor next to the
+//! running window. Params types are expected to use `#[serde(default)]` so that a preset only
+//! has to list the params it changes. They also implement [`Validate`], so that a preset with a
+//! value the generator can't cope with is turned away like one that doesn't parse.
+
+use anyhow::{bail, Context};
+use log::{info, warn};
+use nannou::prelude::*;
+use serde::de::DeserializeOwned;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+/// How often the file is checked for changes, in seconds
+const POLL_INTERVAL: f32 = 0.25;
+const BANNER_HEIGHT: f32 = 60.0;
+const BANNER_PADDING: f32 = 10.0;
+
+/// Params that can tell when they're outside what their generator can cope with, usually the
+/// ranges of the sliders that set them
+pub trait Validate {
+    fn validate(&self) -> Result<(), anyhow::Error>;
+}
+
+/// Check that the param called `name` is within `range`. NaN never is.
+pub fn check_range<T: PartialOrd + Display>(
+    name: &str,
+    value: T,
+    range: RangeInclusive<T>,
+) -> Result<(), anyhow::Error> {
+    if !range.contains(&value) {
+        bail!(
+            "{} is {}, it should be between {} and {}",
+            name,
+            value,
+            range.start(),
+            range.end()
+        );
+    }
+
+    Ok(())
+}
+
+/// Check that the param called `name` is a number, for params without a range of their own
+pub fn check_finite(name: &str, value: f32) -> Result<(), anyhow::Error> {
+    if !value.is_finite() {
+        bail!("{} is {}, it should be a number", name, value);
+    }
+
+    Ok(())
+}
+
+/// Check that the range called `name` has something in it to pick values from
+pub fn check_not_empty(name: &str, range: &Range<f32>) -> Result<(), anyhow::Error> {
+    if !(range.start < range.end) || !range.start.is_finite() || !range.end.is_finite() {
+        bail!(
+            "{} is {}..{}, it should start before it ends",
+            name,
+            range.start,
+            range.end
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct PresetFile<P> {
+    path: PathBuf,
+    /// When the file was last changed as of the last time it was read
+    modified: Option<SystemTime>,
+    last_checked: Option<Instant>,
+    /// Why the file couldn't be used, cleared once it can be
+    error: Option<String>,
+    params: PhantomData<P>,
+}
+
+impl<P> PresetFile<P> {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            modified: None,
+            last_checked: None,
+            error: None,
+            params: PhantomData,
+        }
+    }
+
+    /// The preset named by the `--preset <file>` command line option, or by `PRESET` if it isn't
+    /// given. Generators don't watch anything unless they're given a preset.
+    pub fn from_args() -> Option<Self> {
+        std::env::args_os()
+            .skip_while(|arg| arg != "--preset")
+            .nth(1)
+            .or_else(|| std::env::var_os("PRESET"))
+            .map(Self::new)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Why the file couldn't be read or parsed the last time it changed
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn set_error(&mut self, error: String) {
+        if self.error.as_ref() != Some(&error) {
+            warn!("{}", error);
+            self.error = Some(error);
+        }
+    }
+}
+
+/// Wait for events, an energy-efficient option for pure-GUI apps, unless there's a `preset`. It has
+/// to be checked for changes even when nothing happens in the window.
+pub fn set_loop_mode_for<P>(app: &App, preset: &Option<PresetFile<P>>) {
+    if preset.is_none() {
+        app.set_loop_mode(LoopMode::Wait);
+    }
+}
+
+impl<P: DeserializeOwned + Validate> PresetFile<P> {
+    /// The params in the file if it has changed since they were last returned, the first call
+    /// always reads it. A file that can't be read or parsed, or has params out of range, returns
+    /// nothing and sets
+    /// [`PresetFile::error`] until it's fixed, so a half-typed edit never takes the window down.
+    pub fn poll(&mut self) -> Option<P> {
+        let now = Instant::now();
+        let is_due = self
+            .last_checked
+            .map_or(true, |last| (now - last).as_secs_f32() >= POLL_INTERVAL);
+        if !is_due {
+            return None;
+        }
+        self.last_checked = Some(now);
+
+        let modified = match std::fs::metadata(&self.path).and_then(|meta| meta.modified()) {
+            Ok(modified) => modified,
+            Err(err) => {
+                // read it again as soon as it's back
+                self.modified = None;
+                self.set_error(format!(
+                    "couldn't read preset '{}': {}",
+                    self.path.to_string_lossy(),
+                    err
+                ));
+                return None;
+            }
+        };
+        if self.modified == Some(modified) {
+            return None;
+        }
+        self.modified = Some(modified);
+
+        match self.load() {
+            Ok(params) => {
+                self.error = None;
+                info!("loaded preset '{}'", self.path.to_string_lossy());
+                Some(params)
+            }
+            Err(err) => {
+                self.set_error(format!("{:#}", err));
+                None
+            }
+        }
+    }
+
+    pub fn load(&self) -> Result<P, anyhow::Error> {
+        let contents = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("couldn't read preset '{}'", self.path.to_string_lossy()))?;
+
+        let params: P = toml::from_str(&contents)
+            .with_context(|| format!("'{}' isn't a valid preset", self.path.to_string_lossy()))?;
+        params.validate().with_context(|| {
+            format!("'{}' has a param out of range", self.path.to_string_lossy())
+        })?;
+
+        Ok(params)
+    }
+}
+
+/// Draw `message` on a red banner along the bottom of the window, out of the way of the UI
+pub fn draw_error_banner(draw: &Draw, window_rect: Rect, message: &str) {
+    let banner = Rect::from_w_h(window_rect.w(), BANNER_HEIGHT).align_bottom_of(window_rect);
+
+    draw.rect()
+        .xy(banner.xy())
+        .wh(banner.wh())
+        .color(rgba(0.7, 0.1, 0.1, 0.9));
+    draw.text(message)
+        .xy(banner.xy())
+        .wh(banner.pad(BANNER_PADDING).wh())
+        .left_justify()
+        .font_size(14)
+        .color(WHITE);
+}
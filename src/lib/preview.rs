@@ -0,0 +1,309 @@
+//! A preview of how a drawing will come out on paper: the sheet at its real size, its margins,
+//! how far the plotter can reach and every stroke at the width of the pen that draws it. Anything
+//! the pen can't put on the sheet is drawn in red so it can be fixed before it's plotted.
+//!
+//! The paper and how the drawing is placed on it come from the config and the environment:
+//!
+//! - the paper in the [config](crate::config), a paper name or `<width>x<height>` in
+//!   millimeters, see [`Paper::from_name`]
+//! - `PAPER_MARGIN_MM`, the blank space kept around the edge of the sheet
+//! - `PLOTTER_TRAVEL_MM`, as `<x>x<y>`, how far the plotter can move from the top left corner
+//! - `PREVIEW_SCALE`, `fit` to fit the drawing inside the margins like `plotopt --paper` does, or
+//!   how many millimeters on paper a millimeter of the drawing becomes
+
+use crate::config::config;
+use crate::geometry::{bounding_rect_of_points, Drawing, Layer, Shape};
+use crate::paper::Paper;
+use crate::pen::{PenProfile, PIXELS_PER_MM};
+use crate::plotter::FLATTENING_TOLERANCE_MM;
+use anyhow::{anyhow, Context};
+use log::warn;
+use nannou::prelude::*;
+
+const DEFAULT_MARGIN_MM: f32 = 10.0;
+const WARNING_FONT_SIZE: u32 = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperPreview {
+    /// The sheet, it's turned on its side for drawings wider than they are tall
+    pub paper: Paper,
+    pub margin_mm: f32,
+    /// How far the plotter can move along x and y, no limit if it's `None`
+    pub travel_mm: Option<Vec2>,
+    /// The scale the drawing is plotted at, `None` scales it to fit inside the margins
+    pub scale: Option<f32>,
+}
+
+impl Default for PaperPreview {
+    fn default() -> Self {
+        Self {
+            paper: Paper::default(),
+            margin_mm: DEFAULT_MARGIN_MM,
+            travel_mm: None,
+            scale: None,
+        }
+    }
+}
+
+/// Where a drawing ends up on the sheet, in drawing units with the origin in the top left corner
+/// of the sheet
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    /// The paper turned the way the drawing is plotted on it
+    pub paper: Paper,
+    pub scale: f32,
+    pub offset: Vec2,
+    /// The part of the sheet the pen is allowed to draw on
+    pub printable: Rect,
+}
+
+impl Placement {
+    /// Whether all of `shape`, including the width of the pen's line, stays in the printable area
+    pub fn is_printable(&self, shape: &Shape, pen: &PenProfile) -> bool {
+        let placed = shape.transformed(self.scale, self.offset);
+        let half_width = pen.stroke_weight() / 2.0;
+
+        placed.bounding_rect().map_or(true, |bounds| {
+            bounds.left() - half_width >= self.printable.left()
+                && bounds.right() + half_width <= self.printable.right()
+                && bounds.bottom() - half_width >= self.printable.bottom()
+                && bounds.top() + half_width <= self.printable.top()
+        })
+    }
+}
+
+impl PaperPreview {
+    /// The preview described by the config and environment, anything that isn't set or can't
+    /// be read is left at its default
+    pub fn from_env() -> Self {
+        let mut preview = Self {
+            paper: config().paper.value,
+            ..Self::default()
+        };
+
+        if let Ok(margin) = std::env::var("PAPER_MARGIN_MM") {
+            match margin.trim().parse::<f32>() {
+                Ok(margin) if margin >= 0.0 => preview.margin_mm = margin,
+                _ => warn!("'{}' isn't a margin, using {}mm", margin, preview.margin_mm),
+            }
+        }
+
+        if let Ok(travel) = std::env::var("PLOTTER_TRAVEL_MM") {
+            match parse_travel(&travel) {
+                Ok(travel) => preview.travel_mm = Some(travel),
+                Err(err) => warn!("{:?}, assuming the plotter reaches the whole sheet", err),
+            }
+        }
+
+        if let Ok(scale) = std::env::var("PREVIEW_SCALE") {
+            match scale.trim() {
+                "fit" => preview.scale = None,
+                scale => match scale.parse::<f32>() {
+                    Ok(scale) if scale > 0.0 => preview.scale = Some(scale),
+                    _ => warn!("'{}' isn't a scale, fitting to the paper instead", scale),
+                },
+            }
+        }
+
+        preview
+    }
+
+    /// Work out how `drawing` sits on the sheet
+    pub fn placement(&self, drawing: &Drawing) -> Placement {
+        let paper = if drawing.width > drawing.height {
+            self.paper.landscape()
+        } else {
+            Paper::new(
+                self.paper.width_mm.min(self.paper.height_mm),
+                self.paper.width_mm.max(self.paper.height_mm),
+            )
+        };
+        let paper_wh = paper.wh();
+        let margin = self.margin_mm * PIXELS_PER_MM;
+
+        let mut printable_wh = (paper_wh - vec2(margin, margin) * 2.0).max(Vec2::ZERO);
+        if let Some(travel_mm) = self.travel_mm {
+            // the plotter's home is the top left corner of the sheet
+            let travel_wh = travel_mm * PIXELS_PER_MM - vec2(margin, margin);
+            printable_wh = printable_wh.min(travel_wh.max(Vec2::ZERO));
+        }
+        let printable = Rect::from_corners(pt2(margin, margin), pt2(margin, margin) + printable_wh);
+
+        let page_wh = vec2(drawing.width, drawing.height);
+        let scale = match self.scale {
+            Some(scale) => scale,
+            None if page_wh.x > 0.0 && page_wh.y > 0.0 => {
+                (printable_wh.x / page_wh.x).min(printable_wh.y / page_wh.y)
+            }
+            None => 1.0,
+        };
+        let offset = printable.xy() - page_wh * scale / 2.0;
+
+        Placement {
+            paper,
+            scale,
+            offset,
+            printable,
+        }
+    }
+
+    /// Draw the sheet with `drawing` on it, centred on the origin of `draw` at one drawing unit
+    /// per point. Each layer is drawn with the pen `pen_for_layer` picks for it.
+    pub fn draw<'p>(
+        &self,
+        draw: &Draw,
+        drawing: &Drawing,
+        pen_for_layer: impl Fn(&Layer) -> &'p PenProfile,
+    ) {
+        let placement = self.placement(drawing);
+        let paper_wh = placement.paper.wh();
+        // paper coordinates have y pointing down from the top left corner, the window's has y
+        // pointing up from the middle
+        let to_window = |xy: Point2| pt2(xy.x - paper_wh.x / 2.0, paper_wh.y / 2.0 - xy.y);
+        let rect_to_window = |rect: Rect| {
+            Rect::from_corners(
+                to_window(pt2(rect.left(), rect.bottom())),
+                to_window(pt2(rect.right(), rect.top())),
+            )
+        };
+
+        draw.rect()
+            .xy(pt2(4.0, -4.0))
+            .wh(paper_wh)
+            .color(rgba(0.0, 0.0, 0.0, 0.2));
+        draw.rect().x_y(0.0, 0.0).wh(paper_wh).color(WHITE);
+
+        let margin_rect = rect_to_window(Rect::from_corners(pt2(0.0, 0.0), paper_wh))
+            .pad(self.margin_mm * PIXELS_PER_MM);
+        draw.rect()
+            .xy(margin_rect.xy())
+            .wh(margin_rect.wh())
+            .no_fill()
+            .stroke(LIGHTSKYBLUE)
+            .stroke_weight(1.0);
+
+        if let Some(travel_mm) = self.travel_mm {
+            let travel_rect =
+                rect_to_window(Rect::from_corners(pt2(0.0, 0.0), travel_mm * PIXELS_PER_MM));
+            draw.rect()
+                .xy(travel_rect.xy())
+                .wh(travel_rect.wh())
+                .no_fill()
+                .stroke(ORANGE)
+                .stroke_weight(1.0);
+        }
+
+        let tolerance = FLATTENING_TOLERANCE_MM * PIXELS_PER_MM;
+        let mut outside_count = 0;
+
+        for layer in drawing.layers.iter() {
+            let pen = pen_for_layer(layer);
+
+            // the border is the edge of the page, which fitting to the paper puts right on the
+            // margin, so it's only there to show where the page ends
+            let is_border = layer.name == "border";
+
+            for shape in layer.shapes.iter() {
+                let is_printable = is_border || placement.is_printable(shape, pen);
+                if !is_printable {
+                    outside_count += 1;
+                }
+                let colour = if is_printable {
+                    pen.nannou_colour()
+                } else {
+                    srgb(255, 0, 0)
+                };
+
+                let points: Vec<Point2> = shape
+                    .transformed(placement.scale, placement.offset)
+                    .to_polyline(tolerance)
+                    .into_iter()
+                    .map(to_window)
+                    .collect();
+
+                // a pen that only touches down still leaves a dot the size of its tip
+                let is_dot = bounding_rect_of_points(&points)
+                    .map_or(false, |bounds| bounds.w() == 0.0 && bounds.h() == 0.0);
+                if is_dot {
+                    draw.ellipse()
+                        .xy(points[0])
+                        .radius(pen.stroke_weight() / 2.0)
+                        .color(colour);
+                } else {
+                    draw.polyline()
+                        .stroke_weight(pen.stroke_weight())
+                        .points(points)
+                        .color(colour);
+                }
+            }
+        }
+
+        if outside_count > 0 {
+            let label = format!(
+                "{} shape{} outside the printable area",
+                outside_count,
+                if outside_count == 1 { "" } else { "s" }
+            );
+            let label_rect = Rect::from_w_h(paper_wh.x, WARNING_FONT_SIZE as f32 * 2.0)
+                .align_bottom_of(Rect::from_wh(paper_wh))
+                .shift_y(-(WARNING_FONT_SIZE as f32) * 2.0);
+            draw.text(&label)
+                .xy(label_rect.xy())
+                .wh(label_rect.wh())
+                .font_size(WARNING_FONT_SIZE)
+                .color(RED);
+        }
+    }
+}
+
+fn parse_travel(travel: &str) -> Result<Vec2, anyhow::Error> {
+    let (x, y) = travel
+        .trim()
+        .trim_end_matches("mm")
+        .split_once('x')
+        .ok_or_else(|| {
+            anyhow!(
+                "'{}' isn't a plotter travel, it should look like 300x218",
+                travel
+            )
+        })?;
+    let size = |size: &str| {
+        size.trim()
+            .parse::<f32>()
+            .with_context(|| format!("'{}' isn't a plotter travel", travel))
+    };
+
+    Ok(vec2(size(x)?, size(y)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometry_past_the_travel_limit_is_not_printable() {
+        let preview = PaperPreview {
+            paper: Paper::A4,
+            margin_mm: 10.0,
+            travel_mm: Some(vec2(100.0, 300.0)),
+            scale: Some(1.0),
+        };
+        let drawing = Drawing::new(100.0 * PIXELS_PER_MM, 200.0 * PIXELS_PER_MM);
+        let pen = PenProfile::new("fineliner", 0.3, "black", 100.0);
+        let placement = preview.placement(&drawing);
+
+        let middle = Shape::Circle {
+            center: pt2(50.0, 100.0) * PIXELS_PER_MM,
+            radius: 5.0 * PIXELS_PER_MM,
+        };
+        let right_edge = Shape::Polyline(vec![
+            pt2(0.0, 100.0) * PIXELS_PER_MM,
+            pt2(100.0, 100.0) * PIXELS_PER_MM,
+        ]);
+
+        assert!(placement.is_printable(&middle, &pen));
+        // centred between the margin and the travel limit the drawing runs from 5mm to 105mm,
+        // past both
+        assert!(!placement.is_printable(&right_edge, &pen));
+    }
+}
@@ -0,0 +1,108 @@
+//! Poisson disk sampling, scattering points so no two are closer than a minimum distance but
+//! without the regular look of a grid. Everything is seeded, the same seed always gives the same
+//! points.
+//!
+//! The sampling itself is done by the `poisson` crate, which works in a unit square and on an
+//! older version of `rand` than the rest of the crate, so this module scales its points into
+//! drawing units and keeps its RNG out of sight.
+
+use crate::geometry::{bounding_rect_of_points, polygon_contains};
+use crate::seed::Seeds;
+use nalgebra::Vector2;
+use nannou::prelude::*;
+use poisson::{algorithm, Builder, Type};
+use rand::Rng;
+use rand_06::SeedableRng as _;
+
+/// Where points are scattered
+#[derive(Debug, Clone, PartialEq)]
+pub enum Region {
+    Rect(Rect),
+    Polygon(Vec<Point2>),
+}
+
+impl Region {
+    pub fn bounding_rect(&self) -> Option<Rect> {
+        match self {
+            Region::Rect(rect) => Some(*rect),
+            Region::Polygon(points) => bounding_rect_of_points(points),
+        }
+    }
+
+    pub fn contains(&self, xy: Point2) -> bool {
+        match self {
+            Region::Rect(rect) => rect.contains(xy),
+            Region::Polygon(points) => polygon_contains(points, xy),
+        }
+    }
+}
+
+/// Points spread evenly over `region`, no closer to each other than `min_distance`
+pub fn poisson_disk(region: &Region, min_distance: f32, seed: u64) -> Vec<Point2> {
+    let bounds = match region.bounding_rect() {
+        Some(bounds) if min_distance > 0.0 && bounds.w() > 0.0 && bounds.h() > 0.0 => bounds,
+        _ => return Vec::new(),
+    };
+
+    // the samples fill a unit square, which is stretched over the longer side of the region and
+    // then trimmed back to the region's shape
+    let side = bounds.w().max(bounds.h());
+    // the poisson crate's radius is half the distance between points, and it refuses radii that
+    // wouldn't fit a single disk
+    let radius = (min_distance / 2.0 / side).min(0.5) as f64;
+    let rng = rand_06::rngs::StdRng::seed_from_u64(seed);
+    let samples = Builder::<f64, Vector2<f64>>::with_radius(radius, Type::Normal)
+        .build(rng, algorithm::Ebeid)
+        .generate();
+
+    samples
+        .into_iter()
+        .map(|sample| {
+            pt2(bounds.left(), bounds.bottom()) + vec2(sample.x as f32, sample.y as f32) * side
+        })
+        .filter(|xy| region.contains(*xy))
+        .collect()
+}
+
+/// Points spread over `region` more thickly where `density` is higher. `density` is how likely
+/// a point is to be kept, from 0 for none to 1 for as many as fit `min_distance` apart.
+///
+/// This thins out an even sampling rather than growing the spacing, so sparse areas look a
+/// little clumpier than dense ones.
+pub fn variable_poisson_disk(
+    region: &Region,
+    min_distance: f32,
+    seed: u64,
+    density: impl Fn(Point2) -> f32,
+) -> Vec<Point2> {
+    // a different stream to the one the samples came from, so thinning doesn't follow placement
+    let mut rng = Seeds::new(seed).rng("thinning");
+
+    poisson_disk(region, min_distance, seed)
+        .into_iter()
+        .filter(|xy| rng.gen::<f32>() < density(*xy))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_stay_apart_and_inside() {
+        let triangle = vec![pt2(0.0, 0.0), pt2(200.0, 0.0), pt2(0.0, 100.0)];
+        let region = Region::Polygon(triangle);
+        let points = poisson_disk(&region, 10.0, 7);
+
+        assert!(points.len() > 20);
+        assert_eq!(points, poisson_disk(&region, 10.0, 7));
+
+        for (index, a) in points.iter().enumerate() {
+            assert!(region.contains(*a));
+
+            for b in points[index + 1..].iter() {
+                assert!(a.distance(*b) >= 10.0 - 1e-3);
+            }
+        }
+    }
+}
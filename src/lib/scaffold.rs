@@ -0,0 +1,225 @@
+//! Starting a new generator from the template in `templates/plotling`. The template is a small
+//! but complete plotling, with the camera, paper preview, pens, presets, exports and journal
+//! already wired up, so a new one only has to change what gets drawn.
+
+use anyhow::{anyhow, bail, Context};
+use std::path::{Path, PathBuf};
+
+/// The template's files and what they're called in the new generator
+const TEMPLATE_FILES: &[(&str, &str)] = &[
+    (
+        "main.rs",
+        include_str!("../../templates/plotling/main.rs.template"),
+    ),
+    (
+        "params.rs",
+        include_str!("../../templates/plotling/params.rs.template"),
+    ),
+    (
+        "system.rs",
+        include_str!("../../templates/plotling/system.rs.template"),
+    ),
+];
+
+/// Write a new generator called `name` to `src/bin/<name>/` in the crate at `crate_dir` and add it
+/// to the crate's `Cargo.toml`. Returns the new generator's directory.
+pub fn scaffold(crate_dir: &Path, name: &str) -> Result<PathBuf, anyhow::Error> {
+    check_name(name)?;
+
+    let bin_dir = crate_dir.join("src").join("bin");
+    let plotling_dir = bin_dir.join(name);
+    if plotling_dir.exists() || bin_dir.join(format!("{}.rs", name)).exists() {
+        bail!("there's already a binary called '{}'", name);
+    }
+
+    let manifest_path = crate_dir.join("Cargo.toml");
+    let manifest = std::fs::read_to_string(&manifest_path)
+        .with_context(|| format!("couldn't read '{}'", manifest_path.to_string_lossy()))?;
+    let manifest = register_bin(&manifest, name)?;
+
+    std::fs::create_dir_all(&plotling_dir).with_context(|| {
+        format!(
+            "couldn't create the directory '{}'",
+            plotling_dir.to_string_lossy()
+        )
+    })?;
+    for (filename, template) in TEMPLATE_FILES {
+        let path = plotling_dir.join(filename);
+        std::fs::write(&path, render(template, name))
+            .with_context(|| format!("couldn't write '{}'", path.to_string_lossy()))?;
+    }
+
+    std::fs::write(&manifest_path, manifest)
+        .with_context(|| format!("couldn't write '{}'", manifest_path.to_string_lossy()))?;
+
+    Ok(plotling_dir)
+}
+
+/// Generator names become a binary and a directory, so they're kept to snake case
+pub fn check_name(name: &str) -> Result<(), anyhow::Error> {
+    let starts_with_letter = name
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_ascii_lowercase());
+    let is_snake_case = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    if starts_with_letter && is_snake_case {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "'{}' isn't a good name for a generator, try something like 'spiral_lines'",
+            name
+        ))
+    }
+}
+
+/// Fill in the template's `{{name}}` and `{{title}}`, `spiral_lines` and `Spiral Lines`
+pub fn render(template: &str, name: &str) -> String {
+    template
+        .replace("{{name}}", name)
+        .replace("{{title}}", &title_of(name))
+}
+
+fn title_of(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Add a `[[bin]]` for `name` to `manifest`, among the others in alphabetical order. The manifest
+/// is edited as text so its comments and layout are kept.
+pub fn register_bin(manifest: &str, name: &str) -> Result<String, anyhow::Error> {
+    let parsed: toml::Value = toml::from_str(manifest).context("couldn't parse Cargo.toml")?;
+    let bins = parsed
+        .get("bin")
+        .and_then(|bins| bins.as_array())
+        .map(|bins| bins.as_slice())
+        .unwrap_or_default();
+    let names = bins
+        .iter()
+        .filter_map(|bin| bin.get("name").and_then(|name| name.as_str()));
+    if names.clone().any(|bin_name| bin_name == name) {
+        bail!("Cargo.toml already has a binary called '{}'", name);
+    }
+
+    let entry = format!("[[bin]]\nname = \"{}\"\n\n", name);
+    // the new entry goes before the first one that sorts after it, or after the last one
+    let insert_at = match (names.filter(|bin_name| *bin_name > name).min(), bins.last()) {
+        (Some(next), _) => Some(
+            bin_header_offset(manifest, next)
+                .ok_or_else(|| anyhow!("couldn't find the [[bin]] for '{}'", next))?,
+        ),
+        // whatever table follows the last [[bin]]
+        (None, Some(_)) => manifest
+            .rfind("[[bin]]")
+            .and_then(|last| next_table_offset(manifest, last)),
+        (None, None) => manifest.find("\n[dependencies]").map(|i| i + 1),
+    };
+
+    let mut manifest = manifest.to_owned();
+    match insert_at {
+        Some(insert_at) => manifest.insert_str(insert_at, &entry),
+        None => {
+            if !manifest.ends_with("\n\n") {
+                manifest.push('\n');
+            }
+            manifest.push_str(entry.trim_end());
+            manifest.push('\n');
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Where the `[[bin]]` header of the binary called `name` starts
+fn bin_header_offset(manifest: &str, name: &str) -> Option<usize> {
+    let name_line = format!("name = \"{}\"", name);
+    let name_at = manifest
+        .match_indices(&name_line)
+        .map(|(i, _)| i)
+        .find(|&i| manifest[..i].trim_end().ends_with("[[bin]]"))?;
+
+    manifest[..name_at].rfind("[[bin]]")
+}
+
+/// Where the first table header after `from` starts
+fn next_table_offset(manifest: &str, from: usize) -> Option<usize> {
+    let after_header = from + manifest[from..].find('\n')? + 1;
+
+    manifest[after_header..]
+        .match_indices("\n[")
+        .map(|(i, _)| after_header + i + 1)
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The template rendered as `plotling_template`, which `cargo test` compiles as an example
+    const EXAMPLE_FILES: &[(&str, &str)] = &[
+        (
+            "main.rs",
+            include_str!("../../examples/plotling_template/main.rs"),
+        ),
+        (
+            "params.rs",
+            include_str!("../../examples/plotling_template/params.rs"),
+        ),
+        (
+            "system.rs",
+            include_str!("../../examples/plotling_template/system.rs"),
+        ),
+    ];
+
+    const MANIFEST: &str = "[package]\nname = \"plotlings\"\n\n[[bin]]\nname = \"dune\"\n\n\
+                            [[bin]]\nname = \"maze\"\n\n[dependencies]\nanyhow = \"1.0.41\"\n";
+
+    #[test]
+    fn test_bins_are_registered_in_alphabetical_order() {
+        let manifest = register_bin(MANIFEST, "flow").unwrap();
+        assert!(manifest
+            .contains("name = \"dune\"\n\n[[bin]]\nname = \"flow\"\n\n[[bin]]\nname = \"maze\"\n"));
+
+        let manifest = register_bin(MANIFEST, "spiral_lines").unwrap();
+        assert!(manifest
+            .contains("name = \"maze\"\n\n[[bin]]\nname = \"spiral_lines\"\n\n[dependencies]\n"));
+
+        assert!(register_bin(MANIFEST, "maze").is_err());
+    }
+
+    #[test]
+    fn test_names_are_snake_case_and_titled() {
+        assert!(check_name("spiral_lines2").is_ok());
+        assert!(check_name("SpiralLines").is_err());
+        assert!(check_name("2d").is_err());
+        assert!(check_name("").is_err());
+
+        assert_eq!(
+            render("/// {{title}}, run `{{name}}`", "spiral_lines"),
+            "/// Spiral Lines, run `spiral_lines`"
+        );
+    }
+
+    #[test]
+    fn test_the_compiled_example_is_the_rendered_template() {
+        for ((filename, template), (_, example)) in TEMPLATE_FILES.iter().zip(EXAMPLE_FILES) {
+            assert!(
+                render(template, "plotling_template") == *example,
+                "examples/plotling_template/{} is out of date, render the template again with \
+                 `plotling_template` as the name",
+                filename
+            );
+        }
+    }
+}
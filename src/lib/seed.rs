@@ -0,0 +1,92 @@
+//! Named random streams split off one master seed. Each part of a generator that needs randomness
+//! asks for its own stream by name, e.g. "radii" or "walls", so drawing more or fewer values from
+//! one stream doesn't shift what the others give, and a whole drawing can be recreated from its
+//! master seed alone.
+
+use rand::{prelude::StdRng, Rng, SeedableRng};
+use std::ops::RangeInclusive;
+
+/// The 64 bit FNV-1a hash's constants, used to turn stream names into numbers
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Seeds {
+    master: u64,
+}
+
+impl Seeds {
+    pub fn new(master: u64) -> Self {
+        Self { master }
+    }
+
+    pub fn master(&self) -> u64 {
+        self.master
+    }
+
+    /// A master seed picked at random from `range`, so it still fits on the generator's seed
+    /// slider
+    pub fn random_master(range: RangeInclusive<u64>) -> u64 {
+        rand::thread_rng().gen_range(range)
+    }
+
+    /// The seed of the stream called `name`. It depends only on the master seed and the name, not
+    /// on which other streams have been asked for or in what order.
+    pub fn seed(&self, name: &str) -> u64 {
+        // the name's hashed by hand rather than with std's hasher, which is free to change
+        // between Rust versions and would change every drawing along with it
+        let name_hash = name.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        });
+
+        split_mix(split_mix(self.master) ^ name_hash)
+    }
+
+    /// A fresh RNG for the stream called `name`, starting from the beginning every time
+    pub fn rng(&self, name: &str) -> StdRng {
+        StdRng::seed_from_u64(self.seed(name))
+    }
+
+    /// The seed of the stream called `name` cut down to fit a [`NoiseParams`](crate::noise::NoiseParams)
+    pub fn noise_seed(&self, name: &str) -> u32 {
+        (self.seed(name) >> 32) as u32
+    }
+}
+
+/// Scramble `value` so that nearby inputs give unrelated outputs, from Vigna's SplitMix64
+fn split_mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streams_are_independent_and_repeatable() {
+        let seeds = Seeds::new(42);
+
+        assert_eq!(seeds.seed("radii"), Seeds::new(42).seed("radii"));
+        assert_ne!(seeds.seed("radii"), seeds.seed("positions"));
+        assert_ne!(seeds.seed("radii"), Seeds::new(43).seed("radii"));
+
+        let first: Vec<u32> = seeds
+            .rng("walls")
+            .sample_iter(rand::distributions::Standard)
+            .take(5)
+            .collect();
+        // using another stream in between doesn't move this one along
+        let _: u32 = seeds.rng("positions").gen();
+        let again: Vec<u32> = seeds
+            .rng("walls")
+            .sample_iter(rand::distributions::Standard)
+            .take(5)
+            .collect();
+
+        assert_eq!(first, again);
+    }
+}
@@ -0,0 +1,349 @@
+//! Ways of turning a handful of points into a smooth line through (or near) them. Every method
+//! works on open lines and closed loops, and the smoothed line can be resampled so its points are
+//! evenly spaced along it no matter how unevenly the original points were.
+
+use crate::preset::{check_range, Validate};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+use splines::{Interpolation, Key, Spline};
+
+/// How many points each span between two of the original points is sampled at before resampling
+const SAMPLES_PER_SPAN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Smoothing {
+    None,
+    /// Corner cutting, the line stays inside the original points and gets rounder with each
+    /// iteration. `ratio` is how far along each edge the cuts are made, up to 0.5.
+    Chaikin {
+        iterations: u8,
+        ratio: f32,
+    },
+    /// Passes through every original point
+    CatmullRom,
+    /// A uniform cubic B-spline, smoother than Catmull-Rom but it only passes near the points
+    BSpline,
+}
+
+impl Smoothing {
+    /// Every method, in the order they're listed in the generators' UIs
+    pub const ALL: [Smoothing; 4] = [
+        Smoothing::None,
+        Smoothing::Chaikin {
+            iterations: 4,
+            ratio: 0.25,
+        },
+        Smoothing::CatmullRom,
+        Smoothing::BSpline,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Smoothing::None => "No Smoothing",
+            Smoothing::Chaikin { .. } => "Chaikin",
+            Smoothing::CatmullRom => "Catmull-Rom",
+            Smoothing::BSpline => "B-Spline",
+        }
+    }
+
+    /// The names of every method, for a drop down list
+    pub fn names() -> Vec<String> {
+        Self::ALL
+            .iter()
+            .map(|method| method.name().to_owned())
+            .collect()
+    }
+
+    /// Where this method is in [`Smoothing::ALL`]
+    pub fn index(&self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|method| method.name() == self.name())
+            .unwrap_or_default()
+    }
+
+    /// Smooth `points`, then resample the result every `spacing` units along it. A `spacing` of
+    /// zero keeps the smoothed points as they are. Without smoothing the points are returned
+    /// untouched.
+    pub fn smooth(&self, points: &[Vec2], is_closed: bool, spacing: f32) -> Vec<Vec2> {
+        let smoothed = match *self {
+            Smoothing::None => return points.to_vec(),
+            Smoothing::Chaikin { iterations, ratio } => {
+                chaikin(points, is_closed, iterations, ratio)
+            }
+            Smoothing::CatmullRom => catmull_rom(points, is_closed, SAMPLES_PER_SPAN),
+            Smoothing::BSpline => b_spline(points, is_closed, SAMPLES_PER_SPAN),
+        };
+
+        if spacing > 0.0 {
+            resample(&smoothed, is_closed, spacing)
+        } else {
+            smoothed
+        }
+    }
+}
+
+/// Each Chaikin iteration doubles the points, and cuts past halfway cross over each other
+impl Validate for Smoothing {
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        match *self {
+            Smoothing::Chaikin { iterations, ratio } => {
+                check_range("smoothing iterations", iterations, 0..=8)?;
+                check_range("smoothing ratio", ratio, 0.0..=0.5)
+            }
+            Smoothing::None | Smoothing::CatmullRom | Smoothing::BSpline => Ok(()),
+        }
+    }
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Smoothing::None
+    }
+}
+
+/// Chaikin's corner cutting. Open lines keep their first and last points.
+pub fn chaikin(points: &[Vec2], is_closed: bool, iterations: u8, ratio: f32) -> Vec<Vec2> {
+    // cutting past the middle of an edge would cross the cut coming from the other end
+    let ratio = if ratio > 0.5 { 1.0 - ratio } else { ratio };
+    let mut points = points.to_vec();
+
+    for _ in 0..iterations {
+        if points.len() < 3 {
+            break;
+        }
+
+        let edge_count = if is_closed {
+            points.len()
+        } else {
+            points.len() - 1
+        };
+        let mut cut = Vec::with_capacity(edge_count * 2 + 2);
+
+        if !is_closed {
+            cut.push(points[0]);
+        }
+
+        for index in 0..edge_count {
+            let a = points[index];
+            let b = points[(index + 1) % points.len()];
+            let is_first_edge = !is_closed && index == 0;
+            let is_last_edge = !is_closed && index == edge_count - 1;
+
+            if !is_first_edge {
+                cut.push(a.lerp(b, ratio));
+            }
+
+            if !is_last_edge {
+                cut.push(b.lerp(a, ratio));
+            }
+        }
+
+        if !is_closed {
+            cut.push(points[points.len() - 1]);
+        }
+
+        points = cut;
+    }
+
+    points
+}
+
+/// A Catmull-Rom spline through every point, sampled `samples_per_span` times between each pair
+pub fn catmull_rom(points: &[Vec2], is_closed: bool, samples_per_span: usize) -> Vec<Vec2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    // Catmull-Rom needs a point either side of each span, so the ends get an extra point: the end
+    // repeated on open lines, the point from the other end on closed loops
+    let count = points.len();
+    let padded: Vec<Vec2> = if is_closed {
+        std::iter::once(points[count - 1])
+            .chain(points.iter().cloned())
+            .chain(points.iter().take(2).cloned())
+            .collect()
+    } else {
+        std::iter::once(points[0])
+            .chain(points.iter().cloned())
+            .chain(std::iter::once(points[count - 1]))
+            .collect()
+    };
+    let spline_of = |coordinate: fn(&Vec2) -> f32| {
+        Spline::from_vec(
+            padded
+                .iter()
+                .enumerate()
+                .map(|(index, xy)| {
+                    Key::new(
+                        index as f32 - 1.0,
+                        coordinate(xy),
+                        Interpolation::CatmullRom,
+                    )
+                })
+                .collect(),
+        )
+    };
+    let (x, y) = (spline_of(|xy| xy.x), spline_of(|xy| xy.y));
+
+    let span_count = if is_closed { count } else { count - 1 };
+    let mut smoothed = Vec::with_capacity(span_count * samples_per_span + 1);
+
+    for span in 0..span_count {
+        smoothed.push(points[span]);
+
+        for sample in 1..samples_per_span {
+            let t = span as f32 + sample as f32 / samples_per_span as f32;
+
+            if let (Some(x), Some(y)) = (x.sample(t), y.sample(t)) {
+                smoothed.push(vec2(x, y));
+            }
+        }
+    }
+
+    if !is_closed {
+        smoothed.push(points[count - 1]);
+    }
+
+    smoothed
+}
+
+/// A uniform cubic B-spline using the points as control points, sampled `samples_per_span` times
+/// per span. Open lines repeat their ends so the spline still starts and ends on them.
+pub fn b_spline(points: &[Vec2], is_closed: bool, samples_per_span: usize) -> Vec<Vec2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let count = points.len();
+    let control_points: Vec<Vec2> = if is_closed {
+        points.iter().cycle().take(count + 3).cloned().collect()
+    } else {
+        std::iter::repeat(points[0])
+            .take(2)
+            .chain(points.iter().cloned())
+            .chain(std::iter::repeat(points[count - 1]).take(2))
+            .collect()
+    };
+
+    let mut smoothed = Vec::new();
+
+    for window in control_points.windows(4) {
+        for sample in 0..samples_per_span {
+            let t = sample as f32 / samples_per_span as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            let mt = 1.0 - t;
+
+            smoothed.push(
+                (window[0] * mt * mt * mt
+                    + window[1] * (3.0 * t3 - 6.0 * t2 + 4.0)
+                    + window[2] * (-3.0 * t3 + 3.0 * t2 + 3.0 * t + 1.0)
+                    + window[3] * t3)
+                    / 6.0,
+            );
+        }
+    }
+
+    if !is_closed {
+        smoothed.push(points[count - 1]);
+    }
+
+    smoothed
+}
+
+/// Place points every `spacing` units along a line. Open lines keep their last point even when
+/// it's closer than `spacing` to the one before it.
+pub fn resample(points: &[Vec2], is_closed: bool, spacing: f32) -> Vec<Vec2> {
+    if points.len() < 2 || spacing <= 0.0 {
+        return points.to_vec();
+    }
+
+    let mut resampled = vec![points[0]];
+    // how far along the current edge the next point goes
+    let mut distance_to_next = spacing;
+    let edges = points
+        .windows(2)
+        .map(|edge| (edge[0], edge[1]))
+        .chain(if is_closed {
+            Some((points[points.len() - 1], points[0]))
+        } else {
+            None
+        });
+
+    for (a, b) in edges {
+        let length = a.distance(b);
+        let mut travelled = 0.0;
+
+        while length - travelled >= distance_to_next {
+            travelled += distance_to_next;
+            resampled.push(a.lerp(b, travelled / length));
+            distance_to_next = spacing;
+        }
+
+        distance_to_next -= length - travelled;
+    }
+
+    let last = points[points.len() - 1];
+    if is_closed {
+        // the loop comes back around to the first point on its own
+        if resampled.len() > 1 && resampled[resampled.len() - 1].distance(points[0]) < spacing * 0.5
+        {
+            resampled.pop();
+        }
+    } else if resampled[resampled.len() - 1] != last {
+        resampled.push(last);
+    }
+
+    resampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zigzag() -> Vec<Vec2> {
+        vec![
+            vec2(0.0, 0.0),
+            vec2(10.0, 10.0),
+            vec2(20.0, 0.0),
+            vec2(30.0, 10.0),
+        ]
+    }
+
+    #[test]
+    fn test_open_lines_keep_their_ends() {
+        let points = zigzag();
+
+        for method in Smoothing::ALL.iter() {
+            let smoothed = method.smooth(&points, false, 1.0);
+
+            assert_eq!(smoothed[0], points[0], "{}", method.name());
+            assert_eq!(smoothed[smoothed.len() - 1], points[3], "{}", method.name());
+        }
+    }
+
+    #[test]
+    fn test_catmull_rom_passes_through_points() {
+        let points = zigzag();
+        let smoothed = catmull_rom(&points, true, 8);
+
+        for xy in points.iter() {
+            assert!(smoothed.contains(xy));
+        }
+        assert_eq!(smoothed.len(), 4 * 8);
+    }
+
+    #[test]
+    fn test_resample_spaces_points_evenly() {
+        let resampled = resample(
+            &[vec2(0.0, 0.0), vec2(10.0, 0.0), vec2(10.0, 5.5)],
+            false,
+            2.0,
+        );
+
+        assert_eq!(resampled.len(), 9);
+        assert!(resampled[5].distance(vec2(10.0, 0.0)) < 1e-5);
+        assert!(resampled[6].distance(vec2(10.0, 2.0)) < 1e-5);
+        assert_eq!(resampled[8], vec2(10.0, 5.5));
+    }
+}
@@ -0,0 +1,242 @@
+//! Finding points near other points without comparing every pair. Both indexes are built from a
+//! slice of points and answer with indices into it, and both are cheap enough to rebuild every
+//! frame for things that move.
+//!
+//! [`KdTreeIndex`] copes with any spread of points. [`GridHash`] is faster when queries are about
+//! the same size as its cells, like neighbours of similarly sized circles.
+
+use kdtree::distance::squared_euclidean;
+use kdtree::KdTree;
+use nannou::prelude::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+pub trait SpatialIndex {
+    /// Every point no further than `radius` from `xy`, closest first
+    fn within_radius(&self, xy: Vec2, radius: f32) -> Vec<usize>;
+
+    /// The `k` points closest to `xy`, closest first
+    fn nearest(&self, xy: Vec2, k: usize) -> Vec<usize>;
+}
+
+pub struct KdTreeIndex {
+    tree: KdTree<f32, usize, [f32; 2]>,
+}
+
+impl KdTreeIndex {
+    /// Points that aren't finite can't be placed in the tree and are left out
+    pub fn new(points: &[Vec2]) -> Self {
+        let mut tree = KdTree::new(2);
+
+        for (index, xy) in points.iter().enumerate() {
+            // the only error is a coordinate that isn't finite
+            let _ = tree.add([xy.x, xy.y], index);
+        }
+
+        Self { tree }
+    }
+}
+
+impl SpatialIndex for KdTreeIndex {
+    fn within_radius(&self, xy: Vec2, radius: f32) -> Vec<usize> {
+        // distances are squared, so the radius is too
+        self.tree
+            .within(&[xy.x, xy.y], radius * radius, &squared_euclidean)
+            .map(|found| found.into_iter().map(|(_, index)| *index).collect())
+            .unwrap_or_default()
+    }
+
+    fn nearest(&self, xy: Vec2, k: usize) -> Vec<usize> {
+        self.tree
+            .nearest(&[xy.x, xy.y], k, &squared_euclidean)
+            .map(|found| found.into_iter().map(|(_, index)| *index).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Points bucketed into square cells, so a query only has to look through the cells it overlaps
+pub struct GridHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    points: Vec<Vec2>,
+    /// The lowest and highest cell coordinates that hold points
+    extent: Option<((i32, i32), (i32, i32))>,
+}
+
+impl GridHash {
+    pub fn new(points: &[Vec2], cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "a grid hash's cells need a size");
+
+        let mut grid = Self {
+            cell_size,
+            cells: HashMap::new(),
+            points: points.to_vec(),
+            extent: None,
+        };
+
+        for (index, xy) in points.iter().enumerate() {
+            grid.add_to_cell(index, *xy);
+        }
+
+        grid
+    }
+
+    /// Add a point after the grid's been built, for things that grow one point at a time.
+    /// Returns the point's index.
+    pub fn insert(&mut self, xy: Vec2) -> usize {
+        let index = self.points.len();
+        self.points.push(xy);
+        self.add_to_cell(index, xy);
+
+        index
+    }
+
+    fn add_to_cell(&mut self, index: usize, xy: Vec2) {
+        let cell = self.cell_of(xy);
+        self.cells.entry(cell).or_insert_with(Vec::new).push(index);
+        self.extent = Some(match self.extent {
+            Some((low, high)) => (
+                (low.0.min(cell.0), low.1.min(cell.1)),
+                (high.0.max(cell.0), high.1.max(cell.1)),
+            ),
+            None => (cell, cell),
+        });
+    }
+
+    fn cell_of(&self, xy: Vec2) -> (i32, i32) {
+        (
+            (xy.x / self.cell_size).floor() as i32,
+            (xy.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    /// The points in every cell exactly `ring` cells away from `center`, a square outline
+    fn ring(&self, center: (i32, i32), ring: i32) -> impl Iterator<Item = usize> + '_ {
+        (-ring..=ring)
+            .flat_map(move |dx| (-ring..=ring).map(move |dy| (dx, dy)))
+            .filter(move |(dx, dy)| dx.abs() == ring || dy.abs() == ring)
+            .filter_map(move |(dx, dy)| self.cells.get(&(center.0 + dx, center.1 + dy)))
+            .flat_map(|indices| indices.iter().cloned())
+    }
+
+    fn sort_by_distance(&self, xy: Vec2, indices: &mut Vec<usize>) {
+        indices.sort_by(|a, b| {
+            let (a, b) = (
+                self.points[*a].distance_squared(xy),
+                self.points[*b].distance_squared(xy),
+            );
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        });
+    }
+}
+
+impl SpatialIndex for GridHash {
+    fn within_radius(&self, xy: Vec2, radius: f32) -> Vec<usize> {
+        let (low, high) = (
+            self.cell_of(xy - vec2(radius, radius)),
+            self.cell_of(xy + vec2(radius, radius)),
+        );
+        let mut found = Vec::new();
+
+        for x in low.0..=high.0 {
+            for y in low.1..=high.1 {
+                if let Some(indices) = self.cells.get(&(x, y)) {
+                    found.extend(
+                        indices
+                            .iter()
+                            .filter(|index| self.points[**index].distance(xy) <= radius),
+                    );
+                }
+            }
+        }
+
+        self.sort_by_distance(xy, &mut found);
+
+        found
+    }
+
+    fn nearest(&self, xy: Vec2, k: usize) -> Vec<usize> {
+        let (low, high) = match self.extent {
+            Some(extent) if k > 0 => extent,
+            _ => return Vec::new(),
+        };
+        let center = self.cell_of(xy);
+        // once this many rings have been searched every cell holding a point has been seen
+        let last_ring = [
+            center.0 - low.0,
+            high.0 - center.0,
+            center.1 - low.1,
+            high.1 - center.1,
+        ]
+        .iter()
+        .cloned()
+        .fold(0, i32::max);
+        let mut found = Vec::new();
+
+        for ring in 0..=last_ring {
+            found.extend(self.ring(center, ring));
+
+            // anything in a further ring is at least this far away
+            let unsearched_distance = ring as f32 * self.cell_size;
+            if found.len() >= k {
+                self.sort_by_distance(xy, &mut found);
+
+                if self.points[found[k - 1]].distance(xy) <= unsearched_distance {
+                    break;
+                }
+            }
+        }
+
+        self.sort_by_distance(xy, &mut found);
+        found.truncate(k);
+
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force_nearest(points: &[Vec2], xy: Vec2, k: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..points.len()).collect();
+        indices.sort_by(|a, b| {
+            points[*a]
+                .distance(xy)
+                .partial_cmp(&points[*b].distance(xy))
+                .unwrap()
+        });
+        indices.truncate(k);
+        indices
+    }
+
+    #[test]
+    fn test_indexes_agree_with_brute_force() {
+        let points: Vec<Vec2> = (0..200)
+            .map(|index| {
+                let index = index as f32;
+                vec2((index * 37.0) % 101.0, (index * 53.0) % 97.0 - 40.0)
+            })
+            .collect();
+        let kd_tree = KdTreeIndex::new(&points);
+        let grid = GridHash::new(&points, 7.5);
+
+        for xy in [vec2(0.13, 0.71), vec2(50.37, 10.29), vec2(-80.3, 300.7)].iter() {
+            let expected = brute_force_nearest(&points, *xy, 5);
+            assert_eq!(kd_tree.nearest(*xy, 5), expected);
+            assert_eq!(grid.nearest(*xy, 5), expected);
+
+            let mut within: Vec<usize> = (0..points.len())
+                .filter(|index| points[*index].distance(*xy) <= 20.0)
+                .collect();
+            within.sort_unstable();
+            let mut from_kd_tree = kd_tree.within_radius(*xy, 20.0);
+            from_kd_tree.sort_unstable();
+            let mut from_grid = grid.within_radius(*xy, 20.0);
+            from_grid.sort_unstable();
+
+            assert_eq!(from_kd_tree, within);
+            assert_eq!(from_grid, within);
+        }
+    }
+}
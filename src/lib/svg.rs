@@ -16,3 +16,9 @@ pub fn svg_circle(xy: Point2, radius: f32) -> Ellipse {
         .set("rx", radius)
         .set("ry", radius)
 }
+
+/// The `transform` that puts SVG elements made in window coordinates, with the origin in the middle
+/// and y pointing up, onto a `width` by `height` page
+pub fn svg_window_transform(width: f32, height: f32) -> String {
+    format!("translate({} {}) scale(1 -1)", width * 0.5, height * 0.5)
+}
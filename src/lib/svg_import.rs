@@ -0,0 +1,811 @@
+//! Reads SVG files made by other programs (or old plotling exports) into a [`Drawing`] so they
+//! can go through the same post-processing as the generators' output. Only the geometry is kept,
+//! strokes, fills and text are ignored and every curve is flattened into straight segments.
+//!
+//! Drawing units are CSS pixels at 96 DPI like everywhere else, so an A4 SVG with a `width` of
+//! `210mm` imports as a drawing roughly 794 units wide whatever its `viewBox` says.
+
+use crate::curve::{flatten_cubic, flatten_quadratic};
+use crate::geometry::{Drawing, Shape};
+use crate::pen::PIXELS_PER_MM;
+use anyhow::{anyhow, bail, Context};
+use log::debug;
+use nannou::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+use std::path::Path;
+use svg::node::element::path::{Command, Data, Position};
+use svg::node::element::tag::Type;
+use svg::parser::Event;
+
+/// The layer shapes that aren't inside any top level group end up on
+pub const DEFAULT_LAYER_NAME: &str = "default";
+
+/// Elements whose contents are never drawn directly
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "clipPath", "defs", "desc", "marker", "mask", "metadata", "pattern", "style", "symbol", "text",
+    "title",
+];
+
+pub fn open(path: &Path, tolerance: f32) -> Result<Drawing, anyhow::Error> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("couldn't read SVG '{}'", path.to_string_lossy()))?;
+
+    read(&contents, tolerance)
+        .with_context(|| format!("couldn't import SVG '{}'", path.to_string_lossy()))
+}
+
+/// Parse the contents of an SVG file. Curves are split into straight segments that stray no
+/// further than `tolerance` drawing units from the true curve.
+pub fn read(contents: &str, tolerance: f32) -> Result<Drawing, anyhow::Error> {
+    let mut drawing = Drawing::default();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut group_count = 0;
+
+    for event in svg::read(contents)? {
+        let (name, kind, attributes) = match event {
+            Event::Tag(name, kind, attributes) => (name, kind, attributes),
+            Event::Error(err) => bail!("{}", err),
+            _ => continue,
+        };
+        let attributes: HashMap<&str, &str> = attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), &***value))
+            .collect();
+
+        if kind == Type::End {
+            frames.pop();
+            continue;
+        }
+
+        let parent = frames.last().cloned().unwrap_or_default();
+        let is_hidden = attributes.get("display") == Some(&"none")
+            || attributes.get("style").map_or(false, |style| {
+                style.replace(' ', "").contains("display:none")
+            });
+        let mut frame = Frame {
+            is_skipped: parent.is_skipped || is_hidden || SKIPPED_ELEMENTS.contains(&name),
+            ..parent
+        };
+
+        if let Some(transform) = attributes.get("transform") {
+            frame.transform = frame.transform * Transform::parse(transform)?;
+        }
+
+        if name == "svg" && frames.is_empty() {
+            let (transform, width, height) = viewport(&attributes)?;
+            frame.transform = transform;
+            drawing.width = width;
+            drawing.height = height;
+        }
+
+        if name == "g" && frames.len() == 1 {
+            group_count += 1;
+            let layer = attributes
+                .get("inkscape:label")
+                .or_else(|| attributes.get("id"))
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("layer-{}", group_count));
+            frame.layer = Some(layer);
+        }
+
+        if !frame.is_skipped {
+            let shapes = shapes_from_element(name, &attributes, frame.transform, tolerance)
+                .with_context(|| format!("couldn't read a '{}' element", name))?;
+
+            if !shapes.is_empty() {
+                let layer = frame.layer.as_deref().unwrap_or(DEFAULT_LAYER_NAME);
+                drawing.layer_mut(layer).shapes.extend(shapes);
+            }
+        }
+
+        if kind == Type::Start {
+            frames.push(frame);
+        }
+    }
+
+    if drawing.width <= 0.0 || drawing.height <= 0.0 {
+        let extent = drawing
+            .layers
+            .iter()
+            .flat_map(|layer| layer.shapes.iter())
+            .filter_map(Shape::bounding_rect)
+            .fold(vec2(0.0, 0.0), |extent, rect| extent.max(rect.top_right()));
+        drawing.width = extent.x;
+        drawing.height = extent.y;
+    }
+
+    Ok(drawing)
+}
+
+#[derive(Debug, Clone, Default)]
+struct Frame {
+    transform: Transform,
+    layer: Option<String>,
+    is_skipped: bool,
+}
+
+/// Work out how the root `svg` element's user units map to drawing units, and how big the page is
+fn viewport(attributes: &HashMap<&str, &str>) -> Result<(Transform, f32, f32), anyhow::Error> {
+    let width = attributes
+        .get("width")
+        .and_then(|width| parse_length(width));
+    let height = attributes
+        .get("height")
+        .and_then(|height| parse_length(height));
+    let view_box = match attributes.get("viewBox") {
+        Some(view_box) => Some(parse_numbers(view_box)?),
+        None => None,
+    };
+
+    match view_box.as_deref() {
+        Some(&[min_x, min_y, view_box_width, view_box_height])
+            if view_box_width > 0.0 && view_box_height > 0.0 =>
+        {
+            let width = width.unwrap_or(view_box_width);
+            let height = height.unwrap_or(view_box_height);
+            let scale = vec2(width / view_box_width, height / view_box_height);
+            let transform =
+                Transform::scale(scale.x, scale.y) * Transform::translate(-min_x, -min_y);
+
+            Ok((transform, width, height))
+        }
+        _ => Ok((
+            Transform::default(),
+            width.unwrap_or_default(),
+            height.unwrap_or_default(),
+        )),
+    }
+}
+
+fn shapes_from_element(
+    name: &str,
+    attributes: &HashMap<&str, &str>,
+    transform: Transform,
+    tolerance: f32,
+) -> Result<Vec<Shape>, anyhow::Error> {
+    let number = |key: &str| -> f32 {
+        attributes
+            .get(key)
+            .and_then(|value| parse_length(value))
+            .unwrap_or_default()
+    };
+    let mut path = PathFlattener::new(transform, tolerance);
+
+    match name {
+        "path" => {
+            let data = attributes.get("d").copied().unwrap_or_default();
+            let data = Data::parse(data).map_err(|err| anyhow!("{}", err))?;
+            path.follow(&data)?;
+        }
+        "line" => {
+            path.move_to(pt2(number("x1"), number("y1")));
+            path.line_to(pt2(number("x2"), number("y2")));
+        }
+        "polyline" | "polygon" => {
+            let points = parse_numbers(attributes.get("points").copied().unwrap_or_default())?;
+
+            for (index, xy) in points.chunks_exact(2).enumerate() {
+                if index == 0 {
+                    path.move_to(pt2(xy[0], xy[1]));
+                } else {
+                    path.line_to(pt2(xy[0], xy[1]));
+                }
+            }
+
+            if name == "polygon" {
+                path.close();
+            }
+        }
+        "rect" => {
+            let (x, y, width, height) =
+                (number("x"), number("y"), number("width"), number("height"));
+            // a missing rx or ry takes the value of the other one
+            let (rx, ry) = match (attributes.contains_key("rx"), attributes.contains_key("ry")) {
+                (true, false) => (number("rx"), number("rx")),
+                (false, true) => (number("ry"), number("ry")),
+                _ => (number("rx"), number("ry")),
+            };
+            let (rx, ry) = (rx.min(width / 2.0), ry.min(height / 2.0));
+
+            if width > 0.0 && height > 0.0 {
+                path.move_to(pt2(x + rx, y));
+                path.line_to(pt2(x + width - rx, y));
+                path.corner_to(rx, ry, pt2(x + width, y + ry));
+                path.line_to(pt2(x + width, y + height - ry));
+                path.corner_to(rx, ry, pt2(x + width - rx, y + height));
+                path.line_to(pt2(x + rx, y + height));
+                path.corner_to(rx, ry, pt2(x, y + height - ry));
+                path.line_to(pt2(x, y + ry));
+                path.corner_to(rx, ry, pt2(x + rx, y));
+                path.close();
+            }
+        }
+        "circle" | "ellipse" => {
+            let center = pt2(number("cx"), number("cy"));
+            let (rx, ry) = if name == "circle" {
+                (number("r"), number("r"))
+            } else {
+                (number("rx"), number("ry"))
+            };
+
+            if rx > 0.0 && ry > 0.0 {
+                if let (true, Some(scale)) = (rx == ry, transform.uniform_scale()) {
+                    return Ok(vec![Shape::Circle {
+                        center: transform.apply(center),
+                        radius: rx * scale,
+                    }]);
+                }
+
+                path.move_to(center + vec2(rx, 0.0));
+                path.arc_to(rx, ry, 0.0, false, true, center - vec2(rx, 0.0));
+                path.arc_to(rx, ry, 0.0, false, true, center + vec2(rx, 0.0));
+                path.close();
+            }
+        }
+        "use" | "image" => debug!("ignoring '{}' element, they aren't supported", name),
+        _ => {}
+    }
+
+    Ok(path.finish())
+}
+
+/// Turns path commands into polylines and polygons in drawing units
+struct PathFlattener {
+    transform: Transform,
+    tolerance: f32,
+    /// Where the pen is, in the element's own coordinates
+    current: Point2,
+    subpath_start: Point2,
+    /// The control point a smooth curve command reflects
+    last_control: Option<Point2>,
+    points: Vec<Point2>,
+    shapes: Vec<Shape>,
+}
+
+impl PathFlattener {
+    fn new(transform: Transform, tolerance: f32) -> Self {
+        Self {
+            transform,
+            tolerance,
+            current: pt2(0.0, 0.0),
+            subpath_start: pt2(0.0, 0.0),
+            last_control: None,
+            points: Vec::new(),
+            shapes: Vec::new(),
+        }
+    }
+
+    fn follow(&mut self, data: &Data) -> Result<(), anyhow::Error> {
+        for command in data.iter() {
+            let (position, parameters) = match command {
+                Command::Close => {
+                    self.close();
+                    continue;
+                }
+                Command::Move(position, parameters)
+                | Command::Line(position, parameters)
+                | Command::HorizontalLine(position, parameters)
+                | Command::VerticalLine(position, parameters)
+                | Command::QuadraticCurve(position, parameters)
+                | Command::SmoothQuadraticCurve(position, parameters)
+                | Command::CubicCurve(position, parameters)
+                | Command::SmoothCubicCurve(position, parameters)
+                | Command::EllipticalArc(position, parameters) => (position, parameters),
+            };
+            let is_relative = *position == Position::Relative;
+            let stride = match command {
+                Command::HorizontalLine(..) | Command::VerticalLine(..) => 1,
+                Command::Move(..) | Command::Line(..) | Command::SmoothQuadraticCurve(..) => 2,
+                Command::QuadraticCurve(..) | Command::SmoothCubicCurve(..) => 4,
+                Command::CubicCurve(..) => 6,
+                _ => 7,
+            };
+
+            if parameters.is_empty() || parameters.len() % stride != 0 {
+                bail!(
+                    "path command has {} parameters, expected a multiple of {}",
+                    parameters.len(),
+                    stride
+                );
+            }
+
+            for (index, p) in parameters.chunks_exact(stride).enumerate() {
+                let origin = if is_relative {
+                    self.current
+                } else {
+                    pt2(0.0, 0.0)
+                };
+                let xy = |x_index: usize| origin + vec2(p[x_index], p[x_index + 1]);
+                let reflected_control = self
+                    .last_control
+                    .map_or(self.current, |control| self.current * 2.0 - control);
+
+                match command {
+                    // pairs after the first in a move are lines
+                    Command::Move(..) if index == 0 => self.move_to(xy(0)),
+                    Command::Move(..) | Command::Line(..) => self.line_to(xy(0)),
+                    Command::HorizontalLine(..) => {
+                        let x = if is_relative {
+                            self.current.x + p[0]
+                        } else {
+                            p[0]
+                        };
+                        self.line_to(pt2(x, self.current.y));
+                    }
+                    Command::VerticalLine(..) => {
+                        let y = if is_relative {
+                            self.current.y + p[0]
+                        } else {
+                            p[0]
+                        };
+                        self.line_to(pt2(self.current.x, y));
+                    }
+                    Command::QuadraticCurve(..) => self.quadratic_to(xy(0), xy(2)),
+                    Command::SmoothQuadraticCurve(..) => {
+                        self.quadratic_to(reflected_control, xy(0))
+                    }
+                    Command::CubicCurve(..) => self.cubic_to(xy(0), xy(2), xy(4)),
+                    Command::SmoothCubicCurve(..) => self.cubic_to(reflected_control, xy(0), xy(2)),
+                    Command::EllipticalArc(..) => {
+                        self.arc_to(p[0], p[1], p[2], p[3] != 0.0, p[4] != 0.0, xy(5))
+                    }
+                    Command::Close => unreachable!(),
+                }
+
+                // only a curve of the same kind can smoothly continue from a curve
+                let is_quadratic = matches!(
+                    command,
+                    Command::QuadraticCurve(..) | Command::SmoothQuadraticCurve(..)
+                );
+                let is_cubic = matches!(
+                    command,
+                    Command::CubicCurve(..) | Command::SmoothCubicCurve(..)
+                );
+                if !is_quadratic && !is_cubic {
+                    self.last_control = None;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn move_to(&mut self, xy: Point2) {
+        self.finish_subpath();
+        self.current = xy;
+        self.subpath_start = xy;
+        self.last_control = None;
+        self.points.push(self.transform.apply(xy));
+    }
+
+    fn line_to(&mut self, xy: Point2) {
+        self.last_control = None;
+
+        if self.points.is_empty() {
+            self.points.push(self.transform.apply(self.current));
+        } else if xy == self.current {
+            return;
+        }
+
+        self.current = xy;
+        self.points.push(self.transform.apply(xy));
+    }
+
+    /// Curves are flattened after they've been transformed, a transformed bezier curve is the
+    /// curve through its transformed control points
+    fn quadratic_to(&mut self, control: Point2, to: Point2) {
+        let from = self.start_curve();
+        let (c, end) = (self.transform.apply(control), self.transform.apply(to));
+
+        flatten_quadratic(from, c, end, self.tolerance, &mut self.points);
+        self.current = to;
+        self.last_control = Some(control);
+    }
+
+    fn cubic_to(&mut self, control_1: Point2, control_2: Point2, to: Point2) {
+        let from = self.start_curve();
+        let c1 = self.transform.apply(control_1);
+        let c2 = self.transform.apply(control_2);
+        let end = self.transform.apply(to);
+
+        flatten_cubic(from, c1, c2, end, self.tolerance, &mut self.points);
+        self.current = to;
+        self.last_control = Some(control_2);
+    }
+
+    /// An elliptical arc as described by the SVG spec's endpoint parameterization
+    fn arc_to(
+        &mut self,
+        rx: f32,
+        ry: f32,
+        x_axis_rotation_degrees: f32,
+        is_large_arc: bool,
+        is_sweep: bool,
+        to: Point2,
+    ) {
+        let from = self.current;
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+
+        if from == to {
+            return;
+        }
+
+        if rx == 0.0 || ry == 0.0 {
+            self.line_to(to);
+            return;
+        }
+
+        // https://www.w3.org/TR/SVG/implnote.html#ArcConversionEndpointToCenter
+        let angle = x_axis_rotation_degrees.to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let half_difference = (from - to) / 2.0;
+        let x1 = cos * half_difference.x + sin * half_difference.y;
+        let y1 = -sin * half_difference.x + cos * half_difference.y;
+
+        // radii that are too small are scaled up until the arc fits
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+        if is_large_arc == is_sweep {
+            coefficient = -coefficient;
+        }
+        let cx1 = coefficient * rx * y1 / ry;
+        let cy1 = -coefficient * ry * x1 / rx;
+        let midpoint = (from + to) / 2.0;
+        let center = pt2(cos * cx1 - sin * cy1, sin * cx1 + cos * cy1) + midpoint;
+
+        let start_angle = vec2((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let end_angle = vec2((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+        let theta = start_angle.y.atan2(start_angle.x);
+        let mut delta = end_angle.y.atan2(end_angle.x) - theta;
+        if is_sweep && delta < 0.0 {
+            delta += TAU;
+        } else if !is_sweep && delta > 0.0 {
+            delta -= TAU;
+        }
+
+        // flatten in the element's own coordinates, so the tolerance has to be shrunk by however
+        // much the transform scales things up
+        let radius = rx.max(ry) * self.transform.max_scale();
+        let max_angle = if self.tolerance > 0.0 && self.tolerance < radius {
+            2.0 * (1.0 - self.tolerance / radius).acos()
+        } else {
+            PI / 4.0
+        };
+        let segments = ((delta.abs() / max_angle).ceil() as usize).max(1);
+
+        let transform = self.transform;
+        self.push_curve(segments, |t| {
+            let angle = theta + delta * t;
+            let xy = vec2(rx * angle.cos(), ry * angle.sin());
+            transform.apply(center + vec2(cos * xy.x - sin * xy.y, sin * xy.x + cos * xy.y))
+        });
+        self.current = to;
+        self.last_control = None;
+    }
+
+    /// A quarter ellipse around a rounded rectangle's corner, or a sharp corner if it isn't
+    /// rounded
+    fn corner_to(&mut self, rx: f32, ry: f32, to: Point2) {
+        if rx > 0.0 && ry > 0.0 {
+            self.arc_to(rx, ry, 0.0, false, true, to);
+        } else {
+            self.line_to(to);
+        }
+    }
+
+    fn close(&mut self) {
+        if self.points.len() > 1 {
+            let first = self.points[0];
+            if self.points.last() == Some(&first) {
+                self.points.pop();
+            }
+
+            self.shapes
+                .push(Shape::Polygon(std::mem::take(&mut self.points)));
+        }
+
+        self.points.clear();
+        self.current = self.subpath_start;
+        self.last_control = None;
+    }
+
+    fn finish(mut self) -> Vec<Shape> {
+        self.finish_subpath();
+        self.shapes
+    }
+
+    /// Make sure the subpath has a starting point for a curve to continue from, and return it
+    fn start_curve(&mut self) -> Point2 {
+        if self.points.is_empty() {
+            self.points.push(self.transform.apply(self.current));
+        }
+
+        self.points[self.points.len() - 1]
+    }
+
+    /// Add the points along a curve from `t` just after 0 up to 1, the starting point is already
+    /// where the pen is
+    fn push_curve(&mut self, segments: usize, point_at: impl Fn(f32) -> Point2) {
+        self.start_curve();
+
+        for index in 1..=segments {
+            self.points.push(point_at(index as f32 / segments as f32));
+        }
+    }
+
+    fn finish_subpath(&mut self) {
+        if self.points.len() > 1 {
+            self.shapes
+                .push(Shape::Polyline(std::mem::take(&mut self.points)));
+        }
+
+        self.points.clear();
+    }
+}
+
+/// A 2D affine transform, `[a, b, c, d, e, f]` as in the SVG `matrix()` transform
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transform([f32; 6]);
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+    }
+}
+
+impl std::ops::Mul for Transform {
+    type Output = Self;
+
+    /// The transform that applies `other` and then `self`
+    fn mul(self, other: Self) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let [oa, ob, oc, od, oe, of] = other.0;
+
+        Self([
+            a * oa + c * ob,
+            b * oa + d * ob,
+            a * oc + c * od,
+            b * oc + d * od,
+            a * oe + c * of + e,
+            b * oe + d * of + f,
+        ])
+    }
+}
+
+impl Transform {
+    fn translate(x: f32, y: f32) -> Self {
+        Self([1.0, 0.0, 0.0, 1.0, x, y])
+    }
+
+    fn scale(x: f32, y: f32) -> Self {
+        Self([x, 0.0, 0.0, y, 0.0, 0.0])
+    }
+
+    fn rotate(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+
+        Self([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    /// Parse a `transform` attribute, e.g. `translate(10 20) rotate(45)`
+    fn parse(transform: &str) -> Result<Self, anyhow::Error> {
+        let mut result = Self::default();
+
+        for function in transform
+            .split(')')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+        {
+            let (name, arguments) = function
+                .split_once('(')
+                .ok_or_else(|| anyhow!("couldn't read transform '{}'", transform))?;
+            let name = name.trim_matches(|c: char| c.is_whitespace() || c == ',');
+            let arguments = parse_numbers(arguments)?;
+            let argument = |index: usize| arguments.get(index).copied();
+
+            let next = match (name, arguments.len()) {
+                ("matrix", 6) => Self([
+                    arguments[0],
+                    arguments[1],
+                    arguments[2],
+                    arguments[3],
+                    arguments[4],
+                    arguments[5],
+                ]),
+                ("translate", 1) | ("translate", 2) => {
+                    Self::translate(arguments[0], argument(1).unwrap_or_default())
+                }
+                ("scale", 1) | ("scale", 2) => {
+                    Self::scale(arguments[0], argument(1).unwrap_or(arguments[0]))
+                }
+                ("rotate", 1) => Self::rotate(arguments[0]),
+                ("rotate", 3) => {
+                    Self::translate(arguments[1], arguments[2])
+                        * Self::rotate(arguments[0])
+                        * Self::translate(-arguments[1], -arguments[2])
+                }
+                ("skewX", 1) => Self([1.0, 0.0, arguments[0].to_radians().tan(), 1.0, 0.0, 0.0]),
+                ("skewY", 1) => Self([1.0, arguments[0].to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+                _ => bail!("couldn't read transform function '{})'", function),
+            };
+
+            result = result * next;
+        }
+
+        Ok(result)
+    }
+
+    fn apply(&self, xy: Point2) -> Point2 {
+        let [a, b, c, d, e, f] = self.0;
+
+        pt2(a * xy.x + c * xy.y + e, b * xy.x + d * xy.y + f)
+    }
+
+    /// The most this transform stretches anything in any direction
+    fn max_scale(&self) -> f32 {
+        let [a, b, c, d, ..] = self.0;
+
+        vec2(a, b).length().max(vec2(c, d).length())
+    }
+
+    /// How much a circle's radius grows, if circles stay circles under this transform
+    fn uniform_scale(&self) -> Option<f32> {
+        let [a, b, c, d, ..] = self.0;
+        let is_similarity = ((a - d).abs() < 1e-6 && (b + c).abs() < 1e-6)
+            || ((a + d).abs() < 1e-6 && (b - c).abs() < 1e-6);
+
+        if is_similarity {
+            Some(vec2(a, b).length())
+        } else {
+            None
+        }
+    }
+}
+
+/// Read a length in CSS pixels, e.g. `12`, `12px` or `3.5mm`. Percentages can't be resolved
+/// without knowing what they're a percentage of, so they aren't read.
+fn parse_length(length: &str) -> Option<f32> {
+    let length = length.trim();
+    let split_at = length
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or_else(|| length.len());
+    let (number, unit) = length.split_at(split_at);
+    let number: f32 = number.trim().parse().ok()?;
+
+    let pixels_per_unit = match unit.trim() {
+        "" | "px" => 1.0,
+        "mm" => PIXELS_PER_MM,
+        "cm" => PIXELS_PER_MM * 10.0,
+        "in" => 96.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        _ => return None,
+    };
+
+    Some(number * pixels_per_unit)
+}
+
+/// Read a list of numbers separated by whitespace and/or commas. SVG lets the separator be left
+/// out when it's obvious, e.g. `10-5` and `.5.5` are both two numbers.
+fn parse_numbers(numbers: &str) -> Result<Vec<f32>, anyhow::Error> {
+    let mut parsed = Vec::new();
+    let mut current = String::new();
+    let mut push_current = |current: &mut String| -> Result<(), anyhow::Error> {
+        if !current.is_empty() {
+            parsed.push(
+                current
+                    .parse()
+                    .with_context(|| format!("'{}' isn't a number", current))?,
+            );
+            current.clear();
+        }
+
+        Ok(())
+    };
+
+    for c in numbers.chars() {
+        let starts_new_number = match c {
+            '-' | '+' => !current.is_empty() && !current.ends_with(|c| c == 'e' || c == 'E'),
+            '.' => current.contains('.') || current.contains(|c| c == 'e' || c == 'E'),
+            _ => false,
+        };
+
+        if c.is_whitespace() || c == ',' || starts_new_number {
+            push_current(&mut current)?;
+        }
+
+        if !c.is_whitespace() && c != ',' {
+            current.push(c);
+        }
+    }
+
+    push_current(&mut current)?;
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shapes_and_transforms() {
+        let drawing = read(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="50mm" viewBox="0 0 200 100">
+                <defs><circle cx="1" cy="1" r="1"/></defs>
+                <g id="outline" transform="translate(10,10)">
+                    <rect width="20" height="10"/>
+                    <circle cx="10" cy="10" r="5"/>
+                </g>
+                <g inkscape:label="curves">
+                    <path d="M0 0 C0 50 100 50 100 0 l10-10z"/>
+                    <polyline points="0,0 10,0 10,10"/>
+                </g>
+            </svg>"#,
+            0.1,
+        )
+        .unwrap();
+
+        let mm = PIXELS_PER_MM;
+        assert!((drawing.width - 100.0 * mm).abs() < 1e-3);
+        assert_eq!(drawing.layers.len(), 2);
+
+        let outline = &drawing.layers[0];
+        assert_eq!(outline.name, "outline");
+        match &outline.shapes[..] {
+            [Shape::Polygon(rect), Shape::Circle { center, radius }] => {
+                assert_eq!(rect.len(), 4);
+                assert!((rect[2] - pt2(15.0, 10.0) * mm).length() < 1e-3);
+                assert!((*center - pt2(10.0, 10.0) * mm).length() < 1e-3);
+                assert!((*radius - 2.5 * mm).abs() < 1e-3);
+            }
+            shapes => panic!("unexpected shapes {:?}", shapes),
+        }
+
+        let curves = &drawing.layers[1];
+        assert_eq!(curves.name, "curves");
+        match &curves.shapes[..] {
+            [Shape::Polygon(curve), Shape::Polyline(polyline)] => {
+                assert!(curve.len() > 10);
+                assert!((curve[curve.len() - 1] - pt2(55.0, -5.0) * mm).length() < 1e-3);
+                assert_eq!(polyline.len(), 3);
+            }
+            shapes => panic!("unexpected shapes {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn test_arcs_end_where_they_should() {
+        let drawing = read(
+            r#"<svg><path d="M10 10 A5 5 0 0 1 20 10 a5 10 30 1 0 10 10"/></svg>"#,
+            0.01,
+        )
+        .unwrap();
+
+        match &drawing.layers[0].shapes[..] {
+            [Shape::Polyline(points)] => {
+                // a half circle bulging up from the first point
+                let top = points.iter().map(|xy| xy.y).fold(f32::MAX, f32::min);
+                assert!((top - 5.0).abs() < 0.05);
+                assert!((*points.last().unwrap() - pt2(30.0, 20.0)).length() < 1e-3);
+            }
+            shapes => panic!("unexpected shapes {:?}", shapes),
+        }
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(
+            parse_numbers("10-5,.5.5 1e-2 -3").unwrap(),
+            vec![10.0, -5.0, 0.5, 0.5, 0.01, -3.0]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Generating a drawing on another thread so the window keeps responding while it's worked out.
+//! A [`Worker`] runs one job at a time and starting a new one cancels the last, so dragging a
+//! slider only ever waits on the drawing for where the slider ended up. Jobs are handed a
+//! [`Progress`] to say how far along they are and to check whether they're still wanted.
+
+use log::warn;
+use nannou::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+const PROGRESS_BAR_HEIGHT: f32 = 24.0;
+
+#[derive(Debug, Default)]
+struct Shared {
+    is_cancelled: AtomicBool,
+    /// The bits of an `f32` from 0 to 1, there's no atomic float
+    fraction_done: AtomicU32,
+}
+
+/// Shared between a job and the worker running it
+#[derive(Debug, Clone, Default)]
+pub struct Progress(Arc<Shared>);
+
+impl Progress {
+    /// Say that `done` out of `total` steps of the job are finished
+    pub fn set(&self, done: usize, total: usize) {
+        let fraction = if total == 0 {
+            1.0
+        } else {
+            (done as f32 / total as f32).min(1.0)
+        };
+
+        self.0
+            .fraction_done
+            .store(fraction.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn fraction_done(&self) -> f32 {
+        f32::from_bits(self.0.fraction_done.load(Ordering::Relaxed))
+    }
+
+    /// Whether the job's result is no longer wanted, jobs should check this every so often and
+    /// give up early when it is
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled.load(Ordering::Relaxed)
+    }
+
+    fn cancel(&self) {
+        self.0.is_cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug)]
+struct Job<T> {
+    progress: Progress,
+    result: Receiver<T>,
+}
+
+#[derive(Debug)]
+pub struct Worker<T> {
+    job: Option<Job<T>>,
+}
+
+impl<T> Default for Worker<T> {
+    fn default() -> Self {
+        Self { job: None }
+    }
+}
+
+impl<T: Send + 'static> Worker<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `generate` on its own thread, cancelling the job that's running now if there is one.
+    /// `generate` returns `None` when it gives up because it's been cancelled.
+    pub fn start<F>(&mut self, generate: F)
+    where
+        F: FnOnce(&Progress) -> Option<T> + Send + 'static,
+    {
+        self.cancel();
+
+        let progress = Progress::default();
+        let (sender, result) = channel();
+        let job_progress = progress.clone();
+        let spawned = thread::Builder::new()
+            .name("worker".to_owned())
+            .spawn(move || {
+                if let Some(value) = generate(&job_progress) {
+                    // nobody's waiting for it if the worker moved on or was dropped
+                    let _ = sender.send(value);
+                }
+            });
+
+        match spawned {
+            Ok(_) => self.job = Some(Job { progress, result }),
+            Err(err) => warn!("couldn't start a worker thread: {}", err),
+        }
+    }
+
+    /// Stop waiting for the running job, it stops as soon as it next checks
+    /// [`Progress::is_cancelled`]
+    pub fn cancel(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.progress.cancel();
+        }
+    }
+
+    /// The job's result if it finished since the last poll
+    pub fn poll(&mut self) -> Option<T> {
+        let job = self.job.as_ref()?;
+
+        match job.result.try_recv() {
+            Ok(value) => {
+                self.job = None;
+                Some(value)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                warn!("a worker stopped without finishing its job");
+                self.job = None;
+                None
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.job.is_some()
+    }
+
+    /// How far along the running job is, from 0 to 1
+    pub fn progress(&self) -> Option<f32> {
+        self.job.as_ref().map(|job| job.progress.fraction_done())
+    }
+}
+
+impl<T> Drop for Worker<T> {
+    fn drop(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.progress.cancel();
+        }
+    }
+}
+
+/// Generators that wait for events between updates have to keep updating while a worker is
+/// running, otherwise its result isn't picked up until the mouse next moves
+pub fn keep_updating_while(app: &App, is_busy: bool) {
+    let is_waiting = matches!(app.loop_mode(), LoopMode::Wait);
+
+    if is_busy && is_waiting {
+        app.set_loop_mode(LoopMode::refresh_sync());
+    } else if !is_busy && !is_waiting {
+        app.set_loop_mode(LoopMode::Wait);
+    }
+}
+
+/// Draw how far along a job is as a bar across the top of the window
+pub fn draw_progress_bar(draw: &Draw, window_rect: Rect, fraction_done: f32) {
+    let bar = Rect::from_w_h(window_rect.w(), PROGRESS_BAR_HEIGHT).align_top_of(window_rect);
+    let done = Rect::from_w_h(bar.w() * fraction_done, bar.h()).align_left_of(bar);
+
+    draw.rect()
+        .xy(bar.xy())
+        .wh(bar.wh())
+        .color(rgba(0.3, 0.3, 0.3, 0.9));
+    draw.rect()
+        .xy(done.xy())
+        .wh(done.wh())
+        .color(rgba(0.2, 0.5, 0.8, 0.9));
+    draw.text(&format!("generating... {:.0}%", fraction_done * 100.0))
+        .xy(bar.xy())
+        .wh(bar.wh())
+        .font_size(12)
+        .color(WHITE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wait_for<T: Send + 'static>(worker: &mut Worker<T>) -> Option<T> {
+        while worker.is_running() {
+            if let Some(value) = worker.poll() {
+                return Some(value);
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        None
+    }
+
+    #[test]
+    fn test_starting_a_job_cancels_the_one_running() {
+        let mut worker = Worker::new();
+
+        worker.start(|progress| {
+            while !progress.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            Some("cancelled")
+        });
+        worker.start(|progress| {
+            progress.set(1, 1);
+            Some("finished")
+        });
+
+        assert_eq!(wait_for(&mut worker), Some("finished"));
+        assert!(!worker.is_running());
+    }
+}